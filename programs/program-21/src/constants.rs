@@ -1,5 +1,6 @@
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::pubkey;
+use anchor_spl::token_2022::spl_token_2022::extension::ExtensionType;
 
 // Длина имени стола
 pub const TABLE_NAME_MIN_LEN: usize = 3;
@@ -11,18 +12,33 @@ pub const NORMALIZED_TABLE_NAME_PREFIX: &[u8] = b"twentyone_table";
 pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey = pubkey!("gSbePebfvPy7tRqimPoVecS2FqfpnffSGDMiu4c9pch");
 
 // Параметры игры
-pub const MAX_PLAYERS_LIMIT: u8 = 6;
+pub const MAX_PLAYERS_LIMIT: u8 = 7; // Верхний предел мест за столом; дилер выбирает число мест при создании (маски мест — u8)
 pub const MIN_PLAYERS_FOR_DEAL: u8 = 1;
-pub const NUM_DECKS: u8 = 4;
+pub const NUM_DECKS: u8 = 4; // Размер шуза по умолчанию
+pub const MIN_NUM_DECKS: u8 = 1;
+pub const MAX_NUM_DECKS: u8 = 8;
 pub const CARDS_IN_DECK: u16 = 52;
-pub const TOTAL_CARDS: u16 = NUM_DECKS as u16 * CARDS_IN_DECK;
-pub const DECK_RESHUFFLE_THRESHOLD_INDEX: u16 = (TOTAL_CARDS * 3) / 4; // 75%
+pub const DECK_RESHUFFLE_THRESHOLD_PERCENT: u16 = 75; // Перетасовка после 75% шуза
+
+// Скрытый шуз: ончейн хранится только корень Меркла над солеными позициями карт.
+pub const CARD_SALT_LEN: usize = 16;
+
+// --- Источники случайности для перетасовки ---
+// Сколько последних хешей слотов смешивает источник `SlotHashMix`.
+pub const SLOT_HASH_MIX_DEPTH: usize = 8;
+// Программа Switchboard On-Demand, владеющая аккаунтами случайности (VRF).
+pub const SWITCHBOARD_ON_DEMAND_PROGRAM_ID: Pubkey = pubkey!("SBondMDrcV3K4kxZR1HNVT7osZxAHVHgYXL5Ze1oMUv");
+// Смещения полей `RandomnessAccountData` (после 8-байтного дискриминатора: authority, queue, seed_slothash, seed_slot, oracle, reveal_slot, value).
+pub const VRF_SEED_SLOT_OFFSET: usize = 8 + 32 + 32 + 32;
+pub const VRF_REVEAL_SLOT_OFFSET: usize = VRF_SEED_SLOT_OFFSET + 8 + 32;
+pub const VRF_VALUE_OFFSET: usize = VRF_REVEAL_SLOT_OFFSET + 8;
 
 // Минимальное количество карт, необходимое для начала нового раунда.
 pub const MAX_CARDS_IN_HAND: usize = 11; // Примерное макс. кол-во карт в руке
 
 // Время на ход игрока
 pub const PLAYER_TURN_TIMEOUT_SECONDS: i64 = 15;
+pub const SWITCH_WINDOW_SECONDS: i64 = 15; // Окно обмена Blackjack Switch после раздачи
 
 // Выплаты
 /// Числитель для расчета ПРОФИТА от блэкджека. Выплата 1.3x (13/10).
@@ -30,16 +46,52 @@ pub const BLACKJACK_PAYOUT_PROFIT_NUMERATOR: u64 = 13;
 /// Знаменатель для расчета ПРОФИТА от блэкджека.
 pub const BLACKJACK_PAYOUT_PROFIT_DENOMINATOR: u64 = 10;
 
+// Бонусы Spanish 21 за 21 без удвоения: (числитель, знаменатель) ПРОФИТА.
+pub const SPANISH_21_FIVE_CARD_BONUS: (u64, u64) = (3, 2);
+pub const SPANISH_21_SIX_CARD_BONUS: (u64, u64) = (2, 1);
+pub const SPANISH_21_SEVEN_CARD_BONUS: (u64, u64) = (3, 1); // 7 карт и больше
+pub const SPANISH_21_MIXED_TRIPLE_BONUS: (u64, u64) = (3, 2); // 6-7-8 или 7-7-7 разных мастей
+pub const SPANISH_21_SUITED_TRIPLE_BONUS: (u64, u64) = (2, 1); // 6-7-8 или 7-7-7 одной масти
+pub const SPANISH_21_SPADED_TRIPLE_BONUS: (u64, u64) = (3, 1); // 6-7-8 или 7-7-7 пик
+
 // Комиссия платформы
 pub const OWNER_FEE_BPS: u64 = 2200; // 22% (1000 basis points = 10%)
 pub const BASIS_POINTS_DIVISOR: u64 = 10000;
 
 // Сиды для PDA
 pub const BET_ESCROW_SEED: &[u8] = b"bet_escrow";
+pub const ROUND_RECORD_SEED: &[u8] = b"round_record";
+pub const BET_BEHIND_SEED: &[u8] = b"bet_behind";
+pub const TOURNAMENT_SEED: &[u8] = b"tournament";
+pub const TOURNAMENT_PRIZE_SEED: &[u8] = b"tournament_prize";
+pub const SOL_UNWRAP_SEED: &[u8] = b"sol_unwrap";
+pub const PLAYER_VAULT_SEED: &[u8] = b"player_vault";
 
-pub const USDC_MINT_PUBKEY: Pubkey = pubkey!("DejYKjJTMYx6zWLAHdukSFbRyuLjiBFSQx68s7MZADJU");
+// Минт WSOL: ставки в нативном SOL хранятся на WSOL-эскроу стола и выплачиваются лампортами.
+pub const NATIVE_SOL_MINT_PUBKEY: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
+// Расширения минтов Token-2022, допустимые для ставок и залога: только описательные, не влияющие на переводы.
+// Комиссии за перевод, хуки, постоянный делегат и т.п. расходят баланс эскроу с записанной ставкой.
+pub const SUPPORTED_MINT_EXTENSIONS: [ExtensionType; 7] = [
+    ExtensionType::MintCloseAuthority,
+    ExtensionType::MetadataPointer,
+    ExtensionType::TokenMetadata,
+    ExtensionType::GroupPointer,
+    ExtensionType::TokenGroup,
+    ExtensionType::GroupMemberPointer,
+    ExtensionType::TokenGroupMember,
+];
 
-pub const MAX_HANDS_PER_PLAYER: usize = 2; // Максимум 1 сплит
+pub const MAX_SPOTS_PER_SEAT: usize = 3; // Боксов (отдельных ставок) на одном месте
+pub const MAX_HANDS_PER_SPOT: usize = 2; // Максимум 1 сплит на бокс
+pub const MAX_HANDS_PER_PLAYER: usize = MAX_SPOTS_PER_SEAT * MAX_HANDS_PER_SPOT;
+pub const MAX_BETS_BEHIND_PER_SEAT: u8 = 3; // Ставок зрителей на одно место за раунд
+pub const MAX_SIDE_BETS_PER_SEAT: usize = MAX_SPOTS_PER_SEAT * 2; // 21+3 и Perfect Pairs на каждый бокс
+pub const MAX_SIDE_BET_PAYOUT_ODDS: u16 = 1000; // Верхний предел выплаты побочной ставки в пейтейбле (к 1)
+pub const MAX_TOURNAMENT_ENTRANTS: usize = MAX_PLAYERS_LIMIT as usize; // Участник турнира занимает место за столом
+pub const MAX_TOURNAMENT_PAYOUT_PLACES: usize = 3; // Призовых мест в турнире
+pub const MAX_COLLATERAL_MINTS: usize = 4; // Минтов залога дилера в `TableAuthorityConfig`
+pub const MAX_VAULT_MINTS: usize = 8; // Токенов хранилищ игроков, учитываемых столом (см. `GameSession::vault_total`)
+pub const MAX_ROUND_RESULTS: usize = MAX_PLAYERS_LIMIT as usize * MAX_HANDS_PER_PLAYER; // Рук игроков в одном раунде
 
 // Допустимое проскальзывание при проверке цен оракула (в базисных пунктах. 10 = 0.1%)
 pub const PAYOUT_PRICE_SLIPPAGE_BPS: u64 = 300; // 3% slippage tolerance
@@ -1,7 +1,12 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Mint, Token, TokenAccount};
-use crate::state::{TableAuthorityConfig, GameSession};
-use crate::constants::{BET_ESCROW_SEED, NORMALIZED_TABLE_NAME_PREFIX, USDC_MINT_PUBKEY};
+use anchor_spl::token::Token;
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use crate::state::{TableAuthorityConfig, GameSession, PlayerSeat, RoundRecord, BetBehind, Tournament, PlayerVault};
+use crate::constants::{
+    BET_BEHIND_SEED, BET_ESCROW_SEED, NATIVE_SOL_MINT_PUBKEY, NORMALIZED_TABLE_NAME_PREFIX, PLAYER_VAULT_SEED, ROUND_RECORD_SEED, SOL_UNWRAP_SEED,
+    TOURNAMENT_PRIZE_SEED, TOURNAMENT_SEED,
+};
 use crate::errors::TwentyOneError;
 
 // --- КОНТЕКСТЫ ДЛЯ УПРАВЛЕНИЯ АВТОРИЗАЦИЕЙ ---
@@ -26,7 +31,20 @@ pub struct UpdateAuthorityConfig<'info> {
     #[account(
         mut,
         seeds = [TableAuthorityConfig::SEED_PREFIX],
-        bump = authority_config.bump
+        bump = authority_config.bump,
+        has_one = admin
+    )]
+    pub authority_config: Account<'info, TableAuthorityConfig>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetCollateralMints<'info> {
+    #[account(
+        mut,
+        seeds = [TableAuthorityConfig::SEED_PREFIX],
+        bump = authority_config.bump,
+        has_one = admin
     )]
     pub authority_config: Account<'info, TableAuthorityConfig>,
     pub admin: Signer<'info>,
@@ -44,7 +62,7 @@ pub struct InitializeTable<'info> {
         seeds = [NORMALIZED_TABLE_NAME_PREFIX, table_name_input.as_bytes()],
         bump
     )]
-    pub game_session_account: Account<'info, GameSession>,
+    pub game_session_account: AccountLoader<'info, GameSession>,
 
     #[account(mut)]
     pub dealer: Signer<'info>,
@@ -61,19 +79,27 @@ pub struct InitializeTable<'info> {
         ],
         bump
     )]
-    pub usdc_escrow_account: Account<'info, TokenAccount>,
+    pub usdc_escrow_account: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         constraint = dealer_usdc_token_account.mint == usdc_mint.key()
     )]
-    pub dealer_usdc_token_account: Account<'info, TokenAccount>,
+    pub dealer_usdc_token_account: InterfaceAccount<'info, TokenAccount>,
 
-    #[account(address = USDC_MINT_PUBKEY)]
-    pub usdc_mint: Account<'info, Mint>,
+    #[account(
+        constraint = authority_config.collateral_mints.contains(&usdc_mint.key()) @ TwentyOneError::CollateralMintNotAllowed
+    )]
+    pub usdc_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [TableAuthorityConfig::SEED_PREFIX],
+        bump = authority_config.bump
+    )]
+    pub authority_config: Account<'info, TableAuthorityConfig>,
 
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub rent: Sysvar<'info, Rent>,
     pub clock: Sysvar<'info, Clock>,
 }
@@ -82,7 +108,7 @@ pub struct InitializeTable<'info> {
 #[derive(Accounts)]
 pub struct DealerAction<'info> {
     #[account(mut)]
-    pub game_session_account: Account<'info, GameSession>,
+    pub game_session_account: AccountLoader<'info, GameSession>,
     pub dealer: Signer<'info>,
 }
 
@@ -93,7 +119,7 @@ pub struct DealerCloseTable<'info> {
         close = dealer,
         has_one = dealer,
     )]
-    pub game_session_account: Account<'info, GameSession>,
+    pub game_session_account: AccountLoader<'info, GameSession>,
 
     #[account(mut)]
     pub dealer: Signer<'info>,
@@ -106,7 +132,7 @@ pub struct DealerCloseTable<'info> {
 #[instruction(seat_index: u8)]
 pub struct JoinTable<'info> {
     #[account(mut)]
-    pub game_session_account: Account<'info, GameSession>,
+    pub game_session_account: AccountLoader<'info, GameSession>,
     
     /// CHECK: This is the Pubkey of the player being seated. The backend is responsible for verifying the player's identity.
     pub player_to_seat: AccountInfo<'info>,
@@ -122,26 +148,52 @@ pub struct JoinTable<'info> {
         bump
     )]
     pub authority_config: Account<'info, TableAuthorityConfig>,
+
+    #[account(
+        init_if_needed,
+        payer = backend_signer,
+        space = PlayerSeat::CALCULATED_LEN,
+        seeds = [PlayerSeat::SEED_PREFIX, game_session_account.key().as_ref(), &[seat_index]],
+        bump
+    )]
+    pub player_seat: AccountLoader<'info, PlayerSeat>,
+
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
 #[instruction(seat_index: u8)]
 pub struct LeaveTable<'info> {
     #[account(mut)]
-    pub game_session_account: Account<'info, GameSession>,
+    pub game_session_account: AccountLoader<'info, GameSession>,
+    #[account(
+        mut,
+        seeds = [PlayerSeat::SEED_PREFIX, game_session_account.key().as_ref(), &[seat_index]],
+        bump = player_seat.load()?.bump
+    )]
+    pub player_seat: AccountLoader<'info, PlayerSeat>,
     #[account(mut)]
     pub player_account: Signer<'info>,
 }
 
+/// Стол передается только для чтения: ставки на разных местах не конфликтуют по блокировкам.
 #[derive(Accounts)]
+#[instruction(seat_index: u8)]
 pub struct PlaceBet<'info> {
     #[account(mut)]
-    pub game_session_account: Account<'info, GameSession>,
+    pub game_session_account: AccountLoader<'info, GameSession>,
+
+    #[account(
+        mut,
+        seeds = [PlayerSeat::SEED_PREFIX, game_session_account.key().as_ref(), &[seat_index]],
+        bump = player_seat.load()?.bump
+    )]
+    pub player_seat: AccountLoader<'info, PlayerSeat>,
 
     #[account(mut)]
-    pub player_spl_token_account: Account<'info, TokenAccount>,
+    pub player_spl_token_account: InterfaceAccount<'info, TokenAccount>,
     
-    pub spl_token_mint: Account<'info, Mint>,
+    pub spl_token_mint: InterfaceAccount<'info, Mint>,
 
     #[account(
         init_if_needed,
@@ -155,7 +207,7 @@ pub struct PlaceBet<'info> {
         ],
         bump
     )]
-    pub game_session_spl_escrow_account: Account<'info, TokenAccount>,
+    pub game_session_spl_escrow_account: InterfaceAccount<'info, TokenAccount>,
 
     #[account(mut)]
     pub player_account: Signer<'info>,
@@ -165,14 +217,142 @@ pub struct PlaceBet<'info> {
     pub pyth_price_feed: AccountInfo<'info>,
 
     pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// Ставка в нативном SOL: эскроу — WSOL-аккаунт стола по той же схеме `BET_ESCROW_SEED`, что и для SPL-токенов.
+#[derive(Accounts)]
+#[instruction(seat_index: u8)]
+pub struct PlaceSolBet<'info> {
+    #[account(mut)]
+    pub game_session_account: AccountLoader<'info, GameSession>,
+
+    #[account(
+        mut,
+        seeds = [PlayerSeat::SEED_PREFIX, game_session_account.key().as_ref(), &[seat_index]],
+        bump = player_seat.load()?.bump
+    )]
+    pub player_seat: AccountLoader<'info, PlayerSeat>,
+
+    #[account(address = NATIVE_SOL_MINT_PUBKEY)]
+    pub native_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = player_account,
+        token::mint = native_mint,
+        token::authority = game_session_account,
+        seeds = [
+            BET_ESCROW_SEED,
+            game_session_account.key().as_ref(),
+            native_mint.key().as_ref()
+        ],
+        bump
+    )]
+    pub game_session_sol_escrow_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub player_account: Signer<'info>,
+
+    /// CHECK: This is a Pyth price feed account. It is validated in the instruction logic.
+    pub pyth_price_feed: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+    /// WSOL существует только в программе Token.
     pub token_program: Program<'info, Token>,
     pub rent: Sysvar<'info, Rent>,
 }
 
+/// Ставка зрителя на бокс места. Стол передается только для чтения, место — для учета ставок зрителей.
 #[derive(Accounts)]
+#[instruction(seat_index: u8, spot_index: u8)]
+pub struct PlaceBetBehind<'info> {
+    #[account(mut)]
+    pub game_session_account: AccountLoader<'info, GameSession>,
+
+    #[account(
+        mut,
+        seeds = [PlayerSeat::SEED_PREFIX, game_session_account.key().as_ref(), &[seat_index]],
+        bump = player_seat.load()?.bump
+    )]
+    pub player_seat: AccountLoader<'info, PlayerSeat>,
+
+    #[account(
+        init,
+        payer = backer,
+        space = BetBehind::CALCULATED_LEN,
+        seeds = [
+            BET_BEHIND_SEED,
+            game_session_account.key().as_ref(),
+            &[seat_index],
+            &[spot_index],
+            backer.key().as_ref()
+        ],
+        bump
+    )]
+    pub bet_behind: Account<'info, BetBehind>,
+
+    #[account(
+        mut,
+        constraint = backer_spl_token_account.mint == spl_token_mint.key() @ TwentyOneError::BetTokenMintMismatch
+    )]
+    pub backer_spl_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub spl_token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = backer,
+        token::mint = spl_token_mint,
+        token::authority = game_session_account,
+        seeds = [
+            BET_ESCROW_SEED, 
+            game_session_account.key().as_ref(),
+            spl_token_mint.key().as_ref()
+        ],
+        bump
+    )]
+    pub game_session_spl_escrow_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub backer: Signer<'info>,
+
+    /// CHECK: This is a Pyth price feed account. It is validated in the instruction logic.
+    pub pyth_price_feed: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(seat_index: u8)]
+pub struct ContributeShuffleSeed<'info> {
+    #[account(mut)]
+    pub game_session_account: AccountLoader<'info, GameSession>,
+
+    #[account(
+        seeds = [PlayerSeat::SEED_PREFIX, game_session_account.key().as_ref(), &[seat_index]],
+        bump = player_seat.load()?.bump
+    )]
+    pub player_seat: AccountLoader<'info, PlayerSeat>,
+
+    pub player_account: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(seat_index: u8)]
 pub struct PlayerAction<'info> {
     #[account(mut)]
-    pub game_session_account: Account<'info, GameSession>,
+    pub game_session_account: AccountLoader<'info, GameSession>,
+
+    #[account(
+        mut,
+        seeds = [PlayerSeat::SEED_PREFIX, game_session_account.key().as_ref(), &[seat_index]],
+        bump = player_seat.load()?.bump
+    )]
+    pub player_seat: AccountLoader<'info, PlayerSeat>,
     
     #[account(mut)]
     pub player_account: Signer<'info>,
@@ -181,30 +361,67 @@ pub struct PlayerAction<'info> {
 }
 
 #[derive(Accounts)]
+#[instruction(seat_index: u8)]
 pub struct PlayerActionDoubleOrSplit<'info> {
     #[account(mut)]
-    pub game_session_account: Account<'info, GameSession>,
+    pub game_session_account: AccountLoader<'info, GameSession>,
+
+    #[account(
+        mut,
+        seeds = [PlayerSeat::SEED_PREFIX, game_session_account.key().as_ref(), &[seat_index]],
+        bump = player_seat.load()?.bump
+    )]
+    pub player_seat: AccountLoader<'info, PlayerSeat>,
 
     #[account(mut)]
     pub player_account: Signer<'info>,
 
     #[account(
         mut,
-        // The escrow for this specific token must already exist
+        // The escrow for this specific token must already exist; its mint is checked against the hand in the handler
         seeds = [
             BET_ESCROW_SEED, 
             game_session_account.key().as_ref(),
-            player_spl_token_account.mint.as_ref()
+            game_session_spl_escrow_account.mint.as_ref()
         ],
         bump,
     )]
-    pub game_session_spl_escrow_account: Account<'info, TokenAccount>,
+    pub game_session_spl_escrow_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = game_session_spl_escrow_account.mint)]
+    pub bet_token_mint: InterfaceAccount<'info, Mint>,
 
+    /// Токен-аккаунт игрока, с которого доплачивается SPL-ставка. Для ставок в SOL не нужен.
     #[account(mut)]
-    pub player_spl_token_account: Account<'info, TokenAccount>,
+    pub player_spl_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
 
-    pub token_program: Program<'info, Token>,
+    /// Турнир стола. Обязателен за турнирным столом: дополнительная ставка списывается фишками,
+    /// а токен-аккаунты выше не используются.
+    #[account(
+        mut,
+        seeds = [TOURNAMENT_SEED, game_session_account.key().as_ref()],
+        bump = tournament.bump
+    )]
+    pub tournament: Option<Account<'info, Tournament>>,
+
+    /// Хранилище игрока в токене руки. Если передано, дополнительная ставка списывается с него без перевода.
+    #[account(
+        mut,
+        seeds = [
+            PLAYER_VAULT_SEED,
+            game_session_account.key().as_ref(),
+            player_account.key().as_ref(),
+            game_session_spl_escrow_account.mint.as_ref()
+        ],
+        bump = player_vault.bump
+    )]
+    pub player_vault: Option<Account<'info, PlayerVault>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
     pub clock: Sysvar<'info, Clock>,
+
+    /// Обязателен для ставок в SOL: доплата переводится лампортами и оборачивается на эскроу.
+    pub system_program: Option<Program<'info, System>>,
 }
 
 // --- СПЕЦИАЛИЗИРОВАННЫЕ КОНТЕКСТЫ ---
@@ -212,7 +429,7 @@ pub struct PlayerActionDoubleOrSplit<'info> {
 #[derive(Accounts)]
 pub struct BackendAuthorizedAction<'info> {
     #[account(mut)]
-    pub game_session_account: Account<'info, GameSession>,
+    pub game_session_account: AccountLoader<'info, GameSession>,
 
     #[account(
         mut,
@@ -229,15 +446,73 @@ pub struct BackendAuthorizedAction<'info> {
     pub clock: Sysvar<'info, Clock>,
 }
 
+/// Раскрытие карт для руки текущего хода: место проверяется в обработчике по `current_turn_seat_index`.
+#[derive(Accounts)]
+pub struct RevealPendingCards<'info> {
+    #[account(mut)]
+    pub game_session_account: AccountLoader<'info, GameSession>,
+
+    #[account(mut)]
+    pub player_seat: AccountLoader<'info, PlayerSeat>,
+
+    #[account(
+        mut,
+        constraint = backend_signer.key() == authority_config.backend_authority @ TwentyOneError::BackendSignerMismatch
+    )]
+    pub backend_signer: Signer<'info>,
+
+    #[account(
+        seeds = [TableAuthorityConfig::SEED_PREFIX],
+        bump
+    )]
+    pub authority_config: Account<'info, TableAuthorityConfig>,
+
+    pub clock: Sysvar<'info, Clock>,
+}
+
+#[derive(Accounts)]
+pub struct LockShuffleEntropy<'info> {
+    #[account(mut)]
+    pub game_session_account: AccountLoader<'info, GameSession>,
+
+    #[account(
+        mut,
+        constraint = backend_signer.key() == authority_config.backend_authority @ TwentyOneError::BackendSignerMismatch
+    )]
+    pub backend_signer: Signer<'info>,
+
+    #[account(
+        seeds = [TableAuthorityConfig::SEED_PREFIX],
+        bump
+    )]
+    pub authority_config: Account<'info, TableAuthorityConfig>,
+
+    /// CHECK: SlotHashes sysvar. Адрес проверяется ограничением, данные читаются вручную в `read_recent_slot_hashes`.
+    #[account(address = anchor_lang::solana_program::sysvar::slot_hashes::ID)]
+    pub recent_slothashes: UncheckedAccount<'info>,
+
+    /// CHECK: Аккаунт VRF для столов с источником `VrfAccount`. Адрес, владелец и формат проверяются в `VrfAccountSource`.
+    pub randomness_account: Option<UncheckedAccount<'info>>,
+
+    pub clock: Sysvar<'info, Clock>,
+}
 
 #[derive(Accounts)]
+#[instruction(seat_index: u8)]
 pub struct ForcePlayerAction<'info> {
     #[account(mut)]
-    pub game_session_account: Account<'info, GameSession>,
+    pub game_session_account: AccountLoader<'info, GameSession>,
+
+    #[account(
+        mut,
+        seeds = [PlayerSeat::SEED_PREFIX, game_session_account.key().as_ref(), &[seat_index]],
+        bump = player_seat.load()?.bump
+    )]
+    pub player_seat: AccountLoader<'info, PlayerSeat>,
     
     #[account(
         mut,
-        constraint = caller.key() == authority_config.backend_authority || caller.key() == game_session_account.dealer
+        constraint = caller.key() == authority_config.backend_authority || caller.key() == game_session_account.load()?.dealer
             @ TwentyOneError::UnauthorizedForceAction
     )]
     pub caller: Signer<'info>,
@@ -257,7 +532,7 @@ pub struct DealerWithdrawProfit<'info> {
         mut,
         has_one = dealer
     )]
-    pub game_session_account: Account<'info, GameSession>,
+    pub game_session_account: AccountLoader<'info, GameSession>,
 
     #[account(mut)]
     pub dealer: Signer<'info>,
@@ -271,16 +546,19 @@ pub struct DealerWithdrawProfit<'info> {
         ],
         bump
     )]
-    pub game_session_spl_escrow_account: Account<'info, TokenAccount>,
+    pub game_session_spl_escrow_account: InterfaceAccount<'info, TokenAccount>,
     
     #[account(mut)]
-    pub dealer_spl_token_account: Account<'info, TokenAccount>,
+    pub dealer_spl_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = dealer_spl_token_account.mint)]
+    pub spl_token_mint: InterfaceAccount<'info, Mint>,
 
     #[account(mut)]
     /// CHECK: The recipient of the owner's fee.
     pub owner_fee_spl_token_account: AccountInfo<'info>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 // --- КОНТЕКСТ ДЛЯ ФИНАЛИЗАЦИИ РАУНДА ---
@@ -291,12 +569,12 @@ pub struct FinalizeRound<'info> {
         mut,
         seeds = [
             NORMALIZED_TABLE_NAME_PREFIX,
-            game_session_account.table_name.as_bytes()
+            game_session_account.load()?.table_name().as_bytes()
         ],
-        bump = game_session_account.bump,
+        bump = game_session_account.load()?.bump,
         constraint = backend_signer.key() == authority_config.backend_authority @ TwentyOneError::BackendSignerMismatch
     )]
-    pub game_session_account: Account<'info, GameSession>,
+    pub game_session_account: AccountLoader<'info, GameSession>,
 
     #[account(
         seeds = [TableAuthorityConfig::SEED_PREFIX],
@@ -304,7 +582,386 @@ pub struct FinalizeRound<'info> {
     )]
     pub authority_config: Account<'info, TableAuthorityConfig>,
 
+    #[account(mut)]
     pub backend_signer: Signer<'info>,
 
+    /// Необязательная архивная запись раунда. Если передана, создается и заполняется итогами раунда.
+    #[account(
+        init,
+        payer = backend_signer,
+        space = RoundRecord::CALCULATED_LEN,
+        seeds = [
+            ROUND_RECORD_SEED,
+            game_session_account.key().as_ref(),
+            &game_session_account.load()?.round_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub round_record: Option<Account<'info, RoundRecord>>,
+
+    /// Турнир стола. Обязателен за турнирным столом: выплаты зачисляются фишками.
+    #[account(
+        mut,
+        seeds = [TOURNAMENT_SEED, game_session_account.key().as_ref()],
+        bump = tournament.bump
+    )]
+    pub tournament: Option<Account<'info, Tournament>>,
+
+    /// Эскроу залога дилера. Обязателен, если стол платит выигрыши в USDC и в раунде есть выигрыш.
+    #[account(
+        mut,
+        address = game_session_account.load()?.dealer_usdc_escrow
+    )]
+    pub usdc_escrow: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Минт залога для переводов выигрышей из `usdc_escrow`; соответствие эскроу проверяет `transfer_checked`.
+    pub usdc_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: Pyth-фид цены USDC для выигрышей в USDC. Владелец проверяется в `verify_bet_usd_value`.
+    pub usdc_price_feed: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Временный WSOL-аккаунт для вывода SOL-выплат. Создается и закрывается внутри `finalize_round`,
+    /// поэтому между транзакциями пуст; адрес проверяется seeds.
+    #[account(
+        mut,
+        seeds = [SOL_UNWRAP_SEED, game_session_account.key().as_ref()],
+        bump
+    )]
+    pub sol_unwrap_account: Option<UncheckedAccount<'info>>,
+
+    /// Минт WSOL. Вместе с `sol_unwrap_account` обязателен, если в раунде есть выплаты в SOL.
+    #[account(address = NATIVE_SOL_MINT_PUBKEY)]
+    pub native_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    /// Программа Token: эскроу SPL-токенов и WSOL. Для эскроу минтов Token-2022 нужен `token_2022_program`,
+    /// программа каждого перевода выбирается по владельцу минта.
     pub token_program: Program<'info, Token>,
+    pub token_2022_program: Option<Program<'info, Token2022>>,
+    pub system_program: Program<'info, System>,
+}
+
+
+// --- КОНТЕКСТЫ ДЛЯ ТУРНИРОВ ---
+
+#[derive(Accounts)]
+pub struct CreateTournament<'info> {
+    #[account(mut, has_one = dealer)]
+    pub game_session_account: AccountLoader<'info, GameSession>,
+
+    #[account(
+        init,
+        payer = dealer,
+        space = Tournament::CALCULATED_LEN,
+        seeds = [TOURNAMENT_SEED, game_session_account.key().as_ref()],
+        bump
+    )]
+    pub tournament: Account<'info, Tournament>,
+
+    pub buy_in_mint: InterfaceAccount<'info, Mint>,
+
+    /// Призовой фонд турнира: бай-ины участников до выплаты в `finalize_tournament`.
+    #[account(
+        init,
+        payer = dealer,
+        token::mint = buy_in_mint,
+        token::authority = tournament,
+        seeds = [TOURNAMENT_PRIZE_SEED, tournament.key().as_ref()],
+        bump
+    )]
+    pub prize_escrow: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub dealer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(seat_index: u8)]
+pub struct EnterTournament<'info> {
+    pub game_session_account: AccountLoader<'info, GameSession>,
+
+    #[account(
+        seeds = [PlayerSeat::SEED_PREFIX, game_session_account.key().as_ref(), &[seat_index]],
+        bump = player_seat.load()?.bump
+    )]
+    pub player_seat: AccountLoader<'info, PlayerSeat>,
+
+    #[account(
+        mut,
+        seeds = [TOURNAMENT_SEED, game_session_account.key().as_ref()],
+        bump = tournament.bump
+    )]
+    pub tournament: Account<'info, Tournament>,
+
+    #[account(
+        mut,
+        seeds = [TOURNAMENT_PRIZE_SEED, tournament.key().as_ref()],
+        bump = tournament.prize_escrow_bump
+    )]
+    pub prize_escrow: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = player_token_account.mint == tournament.buy_in_mint @ TwentyOneError::BetTokenMintMismatch
+    )]
+    pub player_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = tournament.buy_in_mint)]
+    pub buy_in_mint: InterfaceAccount<'info, Mint>,
+
+    pub player_account: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct StartTournament<'info> {
+    #[account(has_one = dealer)]
+    pub game_session_account: AccountLoader<'info, GameSession>,
+
+    #[account(
+        mut,
+        seeds = [TOURNAMENT_SEED, game_session_account.key().as_ref()],
+        bump = tournament.bump
+    )]
+    pub tournament: Account<'info, Tournament>,
+
+    pub dealer: Signer<'info>,
+}
+
+/// Ставка фишками. Турнир общий для всех мест, поэтому ставки участников упорядочиваются по нему.
+#[derive(Accounts)]
+#[instruction(seat_index: u8)]
+pub struct PlaceTournamentBet<'info> {
+    pub game_session_account: AccountLoader<'info, GameSession>,
+
+    #[account(
+        mut,
+        seeds = [PlayerSeat::SEED_PREFIX, game_session_account.key().as_ref(), &[seat_index]],
+        bump = player_seat.load()?.bump
+    )]
+    pub player_seat: AccountLoader<'info, PlayerSeat>,
+
+    #[account(
+        mut,
+        seeds = [TOURNAMENT_SEED, game_session_account.key().as_ref()],
+        bump = tournament.bump
+    )]
+    pub tournament: Account<'info, Tournament>,
+
+    pub player_account: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeTournament<'info> {
+    #[account(
+        mut,
+        has_one = dealer,
+        constraint = backend_signer.key() == authority_config.backend_authority @ TwentyOneError::BackendSignerMismatch
+    )]
+    pub game_session_account: AccountLoader<'info, GameSession>,
+
+    #[account(
+        mut,
+        close = dealer,
+        seeds = [TOURNAMENT_SEED, game_session_account.key().as_ref()],
+        bump = tournament.bump
+    )]
+    pub tournament: Account<'info, Tournament>,
+
+    /// Призовой фонд закрывается после выплат, рента возвращается дилеру.
+    #[account(
+        mut,
+        seeds = [TOURNAMENT_PRIZE_SEED, tournament.key().as_ref()],
+        bump = tournament.prize_escrow_bump
+    )]
+    pub prize_escrow: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = tournament.buy_in_mint)]
+    pub buy_in_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: Дилер стола, получает ренту турнира и призового фонда. Проверяется через `has_one`.
+    #[account(mut)]
+    pub dealer: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub backend_signer: Signer<'info>,
+
+    #[account(
+        seeds = [TableAuthorityConfig::SEED_PREFIX],
+        bump
+    )]
+    pub authority_config: Account<'info, TableAuthorityConfig>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+
+// --- КОНТЕКСТЫ ДЛЯ ХРАНИЛИЩА ИГРОКА ---
+
+/// Токены вносятся на эскроу ставок стола, баланс учитывается в `PlayerVault`.
+#[derive(Accounts)]
+pub struct DepositToVault<'info> {
+    #[account(mut)]
+    pub game_session_account: AccountLoader<'info, GameSession>,
+
+    #[account(
+        init_if_needed,
+        payer = player_account,
+        space = PlayerVault::CALCULATED_LEN,
+        seeds = [
+            PLAYER_VAULT_SEED,
+            game_session_account.key().as_ref(),
+            player_account.key().as_ref(),
+            spl_token_mint.key().as_ref()
+        ],
+        bump
+    )]
+    pub player_vault: Account<'info, PlayerVault>,
+
+    #[account(mut)]
+    pub player_spl_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub spl_token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = player_account,
+        token::mint = spl_token_mint,
+        token::authority = game_session_account,
+        seeds = [
+            BET_ESCROW_SEED,
+            game_session_account.key().as_ref(),
+            spl_token_mint.key().as_ref()
+        ],
+        bump
+    )]
+    pub game_session_spl_escrow_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub player_account: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFromVault<'info> {
+    #[account(mut)]
+    pub game_session_account: AccountLoader<'info, GameSession>,
+
+    #[account(
+        mut,
+        seeds = [
+            PLAYER_VAULT_SEED,
+            game_session_account.key().as_ref(),
+            player_account.key().as_ref(),
+            player_vault.token_mint.as_ref()
+        ],
+        bump = player_vault.bump
+    )]
+    pub player_vault: Account<'info, PlayerVault>,
+
+    #[account(
+        mut,
+        seeds = [
+            BET_ESCROW_SEED,
+            game_session_account.key().as_ref(),
+            player_vault.token_mint.as_ref()
+        ],
+        bump
+    )]
+    pub game_session_spl_escrow_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = player_vault.token_mint)]
+    pub spl_token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub player_spl_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub player_account: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// Ставка из хранилища: без токен-аккаунтов игрока и перевода токенов.
+#[derive(Accounts)]
+#[instruction(seat_index: u8)]
+pub struct PlaceVaultBet<'info> {
+    #[account(mut)]
+    pub game_session_account: AccountLoader<'info, GameSession>,
+
+    #[account(
+        mut,
+        seeds = [PlayerSeat::SEED_PREFIX, game_session_account.key().as_ref(), &[seat_index]],
+        bump = player_seat.load()?.bump
+    )]
+    pub player_seat: AccountLoader<'info, PlayerSeat>,
+
+    #[account(
+        mut,
+        seeds = [
+            PLAYER_VAULT_SEED,
+            game_session_account.key().as_ref(),
+            player_account.key().as_ref(),
+            player_vault.token_mint.as_ref()
+        ],
+        bump = player_vault.bump
+    )]
+    pub player_vault: Account<'info, PlayerVault>,
+
+    pub player_account: Signer<'info>,
+
+    /// CHECK: This is a Pyth price feed account. It is validated in the instruction logic.
+    pub pyth_price_feed: AccountInfo<'info>,
+}
+
+
+// --- КОНТЕКСТЫ ДЛЯ МИГРАЦИИ АККАУНТОВ МЕЖДУ ВЕРСИЯМИ РАСКЛАДКИ ---
+
+#[derive(Accounts)]
+pub struct MigrateGameSession<'info> {
+    /// CHECK: Стол старой версии не может быть загружен как `AccountLoader`.
+    /// Владелец проверяется ограничением, дискриминатор и версия — в `migrate_game_session`.
+    #[account(mut, owner = crate::ID)]
+    pub game_session_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = backend_signer.key() == authority_config.backend_authority @ TwentyOneError::BackendSignerMismatch
+    )]
+    pub backend_signer: Signer<'info>,
+
+    #[account(
+        seeds = [TableAuthorityConfig::SEED_PREFIX],
+        bump
+    )]
+    pub authority_config: Account<'info, TableAuthorityConfig>,
+
+    /// Эскроу залога дилера. Нужен только для столов версии 0, которые не хранили его адрес.
+    pub usdc_escrow: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAuthorityConfig<'info> {
+    /// CHECK: Конфиг версии 0 не десериализуется в текущую `TableAuthorityConfig`.
+    /// Адрес проверяется seeds, дискриминатор и подпись бэкенда — в `migrate_authority_config`.
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [TableAuthorityConfig::SEED_PREFIX],
+        bump
+    )]
+    pub authority_config: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub backend_signer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
 }
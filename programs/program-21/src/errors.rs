@@ -106,10 +106,146 @@ pub enum TwentyOneError {
     TokenMintNotInProfitTracker,
     #[msg("A commitment for the next shuffle must be provided when the deck is low.")]
     NextShuffleCommitmentRequired,
-    #[msg("A shuffle is required, but no commitment for the nonce was found.")]
+    #[msg("A shuffle is required, but no commitment for the shuffle secret was found.")]
     ShuffleCommitmentMissing,
-    #[msg("The revealed nonce for shuffling does not match the commitment.")]
+    #[msg("The revealed shuffle secret does not match the commitment.")]
     ShuffleCommitmentInvalid,
-    #[msg("A shuffle is required, but no nonce was provided.")]
-    ShuffleNonceRequired,
+    #[msg("The secret of the retired shoe must be revealed before a new shoe is committed.")]
+    ShuffleSecretRequired,
+    #[msg("This seat has already contributed a seed to the pending shuffle.")]
+    ShuffleSeedAlreadyContributed,
+    #[msg("The provided account is not a valid SlotHashes sysvar.")]
+    InvalidSlotHashesAccount,
+    #[msg("The shoe must be reshuffled before the next deal.")]
+    ShuffleRequired,
+    #[msg("The shoe does not need to be reshuffled yet.")]
+    ShuffleNotRequired,
+    #[msg("Shuffle entropy has already been locked for the next shoe.")]
+    ShuffleEntropyAlreadyLocked,
+    #[msg("Shuffle entropy must be locked before a new shoe is committed.")]
+    ShuffleEntropyNotLocked,
+    #[msg("The revealed secret does not reproduce the retired shoe's deck commitment.")]
+    ShoeRevealMismatch,
+    #[msg("Revealed cards must start at the current deck position.")]
+    CardRevealIndexMismatch,
+    #[msg("The Merkle proof for the revealed cards is invalid.")]
+    CardRevealProofInvalid,
+    #[msg("The number of revealed cards does not match the number of cards to draw.")]
+    CardRevealCountMismatch,
+    #[msg("The current hand is waiting for the backend to reveal a card.")]
+    CardRevealPending,
+    #[msg("There is no pending player action waiting for a card reveal.")]
+    NoPendingCardReveal,
+    #[msg("The dealer has no unrevealed hole card.")]
+    NoUnrevealedHoleCard,
+    #[msg("This table's randomness source does not accept player seeds.")]
+    PlayerSeedsNotAccepted,
+    #[msg("The table's randomness source requires its VRF account to be provided.")]
+    RandomnessAccountMissing,
+    #[msg("The provided VRF account does not match the table's randomness source.")]
+    RandomnessAccountMismatch,
+    #[msg("The VRF account has an invalid owner or layout.")]
+    InvalidRandomnessAccount,
+    #[msg("The VRF value has not been revealed or was requested before the shuffle commitment.")]
+    RandomnessNotFresh,
+    #[msg("The game session account already uses the current layout.")]
+    GameSessionAlreadyMigrated,
+    #[msg("The legacy game session account could not be decoded or does not fit the new layout.")]
+    LegacyGameSessionInvalid,
+    #[msg("Legacy tables can only be migrated between rounds, with no active bets.")]
+    LegacyTableRoundInProgress,
+    #[msg("Migrating a version 0 table needs its USDC collateral escrow.")]
+    LegacyCollateralEscrowMismatch,
+    #[msg("The number of decks in the shoe is out of the supported range.")]
+    InvalidNumDecks,
+    #[msg("The provided seat account does not belong to this table or seat.")]
+    SeatAccountMismatch,
+    #[msg("Seat accounts must cover exactly the seats taking part in the round, in seat order.")]
+    SeatAccountsIncomplete,
+    #[msg("The dealer cannot withdraw while bets of the round are unsettled.")]
+    WithdrawalWithPendingBets,
+    #[msg("The number of seats must be between 1 and the table limit.")]
+    InvalidSeatCount,
+    #[msg("The account was written by an unsupported layout version.")]
+    AccountVersionUnsupported,
+    #[msg("The authority config already uses the current layout.")]
+    AuthorityConfigAlreadyMigrated,
+    #[msg("All betting spots of this seat already have a bet.")]
+    TooManySpots,
+    #[msg("The seated player cannot bet behind their own spot.")]
+    BackerIsSeatedPlayer,
+    #[msg("The spot has no bet to follow.")]
+    SpotNotInPlay,
+    #[msg("The seat already has the maximum number of bets behind.")]
+    TooManyBetsBehind,
+    #[msg("The bet behind does not belong to this table or round.")]
+    BetBehindMismatch,
+    #[msg("Every bet behind of the round must be settled exactly once.")]
+    BetsBehindNotSettled,
+    #[msg("The side bet payout table has an out-of-range payout.")]
+    InvalidSideBetPaytable,
+    #[msg("This table does not offer this side bet.")]
+    SideBetNotOffered,
+    #[msg("This side bet has already been placed on the spot.")]
+    SideBetAlreadyPlaced,
+    #[msg("Every side bet of the round must be settled exactly once.")]
+    SideBetsNotSettled,
+    #[msg("The shoe composition does not match the game variant.")]
+    ShoeRulesVariantMismatch,
+    #[msg("Surrender is only allowed on the first two cards of an unsplit hand at tables that offer it.")]
+    SurrenderNotAllowed,
+    #[msg("Only a freshly doubled hand can be rescued, at tables that offer double-down rescue.")]
+    RescueNotAllowed,
+    #[msg("Second cards can be switched once per round, between two untouched hands of the seat, at Blackjack Switch tables.")]
+    SwitchNotAllowed,
+    #[msg("Both switched spots must carry the same bet.")]
+    SwitchBetsNotEqual,
+    #[msg("The dealer waits until the Blackjack Switch window closes.")]
+    SwitchWindowOpen,
+    #[msg("Token bets are not accepted at tournament tables, and tournaments start only at empty tables.")]
+    TournamentTable,
+    #[msg("Invalid tournament settings: check the buy-in, chip stack, round count, entrant limit and payout shares.")]
+    InvalidTournamentConfig,
+    #[msg("The tournament is not accepting entries.")]
+    TournamentNotOpen,
+    #[msg("The tournament is full or the player has already entered.")]
+    TournamentEntryRejected,
+    #[msg("The tournament is not running.")]
+    TournamentNotRunning,
+    #[msg("The player is not an entrant of this tournament.")]
+    NotTournamentEntrant,
+    #[msg("Not enough tournament chips for this bet.")]
+    InsufficientChips,
+    #[msg("The tournament account is required at tournament tables.")]
+    TournamentAccountRequired,
+    #[msg("The tournament still has rounds to play.")]
+    TournamentNotFinished,
+    #[msg("Prize recipients must be the finishers' buy-in token accounts, in finishing order.")]
+    PrizeRecipientMismatch,
+    #[msg("The USDC escrow and the USDC price feed are required to settle winnings in USDC.")]
+    UsdcSettlementAccountsRequired,
+    #[msg("The withdrawal exceeds the escrow balance the dealer is free to withdraw.")]
+    WithdrawalExceedsDealerBalance,
+    #[msg("The USDC winnings exceed the dealer's remaining collateral.")]
+    CollateralBalanceExceeded,
+    #[msg("Native SOL bets need the system program; SOL payouts also need the unwrap account and the native mint.")]
+    NativeSolAccountsRequired,
+    #[msg("Token-2022 mints with transfer fees, hooks or other transfer-altering extensions are not supported.")]
+    UnsupportedMintExtension,
+    #[msg("The Token-2022 program account is required to pay out Token-2022 bets.")]
+    Token2022ProgramRequired,
+    #[msg("The collateral mint is not allowed by the authority config.")]
+    CollateralMintNotAllowed,
+    #[msg("Collateral mints must be unique, non-default keys and fit in the authority config.")]
+    InvalidCollateralMints,
+    #[msg("The player vault balance is too low for this bet or withdrawal.")]
+    InsufficientVaultBalance,
+    #[msg("The player vault belongs to another table, player or token.")]
+    PlayerVaultMismatch,
+    #[msg("The table already tracks vault balances in the maximum number of tokens.")]
+    VaultTokensLimitReached,
+    #[msg("Players still hold vault balances at this table.")]
+    TableHasVaultBalances,
+    #[msg("The hand already holds the maximum number of cards.")]
+    HandFull,
 }
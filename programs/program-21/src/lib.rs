@@ -1,8 +1,7 @@
 #![allow(deprecated)]
 
 use anchor_lang::prelude::*;
-use anchor_spl::token::{TokenAccount, Transfer};
-use anchor_spl::token::spl_token;
+use anchor_spl::token_interface::{self, CloseAccount, InitializeAccount3, TokenAccount};
 use pyth_sdk::PriceFeed;
 
 // Локальные модули
@@ -12,6 +11,10 @@ mod state;
 mod utils;
 mod contexts;
 
+// Публичный API для офчейн-проверки тасования
+pub use state::{Card, Rank, Suit, ShoeRules, DeckComposition};
+pub use utils::verify_shoe;
+
 // Импорт из локальных модулей для удобства
 use constants::*;
 use errors::TwentyOneError; // Прямой импорт перечисления
@@ -26,18 +29,21 @@ use contexts::*;
 #[event]
 pub struct TableCreated {
     pub table_name: String,
+    pub round_id: u64,
     pub dealer: Pubkey,
 }
 
 #[event]
 pub struct TableClosed {
     pub table_name: String,
+    pub round_id: u64,
     pub dealer: Pubkey,
 }
 
 #[event]
 pub struct PlayerJoined {
     pub table_name: String,
+    pub round_id: u64,
     pub player: Pubkey,
     pub seat_index: u8,
 }
@@ -45,6 +51,7 @@ pub struct PlayerJoined {
 #[event]
 pub struct PlayerLeft {
     pub table_name: String,
+    pub round_id: u64,
     pub player: Pubkey,
     pub seat_index: u8,
 }
@@ -52,8 +59,33 @@ pub struct PlayerLeft {
 #[event]
 pub struct BetPlaced {
     pub table_name: String,
+    pub round_id: u64,
+    pub player: Pubkey,
+    pub seat_index: u8,
+    pub spot_index: u8,
+    pub amount: u64,
+    pub token_mint: Pubkey,
+}
+
+#[event]
+pub struct BetBehindPlaced {
+    pub table_name: String,
+    pub round_id: u64,
+    pub backer: Pubkey,
+    pub seat_index: u8,
+    pub spot_index: u8,
+    pub amount: u64,
+    pub token_mint: Pubkey,
+}
+
+#[event]
+pub struct SideBetPlaced {
+    pub table_name: String,
+    pub round_id: u64,
     pub player: Pubkey,
     pub seat_index: u8,
+    pub spot_index: u8,
+    pub kind: SideBetKind,
     pub amount: u64,
     pub token_mint: Pubkey,
 }
@@ -61,13 +93,17 @@ pub struct BetPlaced {
 #[event]
 pub struct RoundStarted {
     pub table_name: String,
+    pub round_id: u64,
     pub dealer_up_card: Card,
+    pub dealer_second_card: Option<Card>, // Только в Double Exposure: вторая карта дилера сдается открытой.
     pub player_hands: Vec<InitialPlayerHand>,
+    pub side_bets: Vec<SideBetEvaluation>,
 }
 
 #[event]
 pub struct PlayerActed {
     pub table_name: String,
+    pub round_id: u64,
     pub player: Pubkey,
     pub seat_index: u8,
     pub hand_index: u8,
@@ -75,22 +111,119 @@ pub struct PlayerActed {
     pub new_card: Option<Card>,
 }
 
+#[event]
+pub struct CardsSwitched {
+    pub table_name: String,
+    pub round_id: u64,
+    pub player: Pubkey,
+    pub seat_index: u8,
+    pub first_hand_index: u8,
+    pub second_hand_index: u8,
+    pub first_hand: Vec<Card>,
+    pub second_hand: Vec<Card>,
+}
+
 #[event]
 pub struct RoundFinished {
     pub table_name: String,
+    pub round_id: u64,
     pub dealer_hand: Vec<Card>,
     pub dealer_score: u8,
     pub results: Vec<PlayerHandResult>,
+    pub bets_behind: Vec<BetBehindResult>,
+    pub side_bets: Vec<SideBetResult>,
 }
 
 #[event]
 pub struct TableClosingDown {
     pub table_name: String,
+    pub round_id: u64,
 }
 
 #[event]
 pub struct DeckShuffled {
     pub table_name: String,
+    pub round_id: u64,
+    pub shoe_id: u64,
+    pub commitment: [u8; 32],
+    pub randomness_source: RandomnessSourceKind,
+    pub entropy: [u8; 32],
+    pub deck_commitment: [u8; 32],
+}
+
+#[event]
+pub struct ShoeRevealed {
+    pub table_name: String,
+    pub round_id: u64,
+    pub shoe_id: u64,
+    pub seed: [u8; 32],
+    pub commitment: [u8; 32],
+    pub revealed_secret: [u8; 32],
+    pub randomness_source: RandomnessSourceKind,
+    pub entropy: [u8; 32],
+    pub deck_hash: [u8; 32],
+    pub deck_commitment: [u8; 32],
+}
+
+#[event]
+pub struct TournamentCreated {
+    pub table_name: String,
+    pub round_id: u64,
+    pub buy_in_mint: Pubkey,
+    pub buy_in_amount: u64,
+    pub starting_chips: u64,
+    pub total_rounds: u16,
+}
+
+#[event]
+pub struct TournamentEntered {
+    pub table_name: String,
+    pub round_id: u64,
+    pub player: Pubkey,
+    pub seat_index: u8,
+    pub chips: u64,
+}
+
+#[event]
+pub struct TournamentStarted {
+    pub table_name: String,
+    pub round_id: u64,
+    pub entrants: u8,
+    pub prize_pool: u64,
+}
+
+#[event]
+pub struct TournamentFinished {
+    pub table_name: String,
+    pub round_id: u64,
+    pub standings: Vec<TournamentStanding>,
+}
+
+#[event]
+pub struct VaultDeposited {
+    pub table_name: String,
+    pub player: Pubkey,
+    pub token_mint: Pubkey,
+    pub amount: u64,
+    pub balance: u64,
+}
+
+#[event]
+pub struct VaultWithdrawn {
+    pub table_name: String,
+    pub player: Pubkey,
+    pub token_mint: Pubkey,
+    pub amount: u64,
+    pub balance: u64,
+}
+
+#[event]
+pub struct ShuffleSeedContributed {
+    pub table_name: String,
+    pub round_id: u64,
+    pub player: Pubkey,
+    pub seat_index: u8,
+    pub player_seeds_hash: [u8; 32],
 }
 
 // --- ОСНОВНАЯ ЛОГИКА ПРОГРАММЫ ---
@@ -102,10 +235,22 @@ pub mod program_21 {
     use super::*; // Для доступа к items из constants, errors, state, utils, и событий
 
     // --- NEW: initialize_authority_config ---
-    pub fn initialize_authority_config<'info>(ctx: Context<'_, '_, '_, 'info, InitializeAuthorityConfig<'info>>, backend_authority_pubkey: Pubkey) -> Result<()> {
+    /// Подписавший становится администратором конфига. `collateral_mints` — минты залога дилера
+    /// для кластера, где развернута программа.
+    pub fn initialize_authority_config<'info>(
+        ctx: Context<'_, '_, '_, 'info, InitializeAuthorityConfig<'info>>,
+        backend_authority_pubkey: Pubkey,
+        collateral_mints: Vec<Pubkey>,
+    ) -> Result<()> {
+        if !TableAuthorityConfig::is_valid_collateral_mints(&collateral_mints) {
+            return err!(TwentyOneError::InvalidCollateralMints);
+        }
         let authority_config = &mut ctx.accounts.authority_config;
         authority_config.backend_authority = backend_authority_pubkey;
         authority_config.bump = ctx.bumps.authority_config;
+        authority_config.version = TableAuthorityConfig::VERSION;
+        authority_config.admin = ctx.accounts.admin.key();
+        authority_config.collateral_mints = collateral_mints;
         Ok(())
     }
 
@@ -116,15 +261,21 @@ pub mod program_21 {
     }
 
     // --- 3.1. initialize_table ---
+    #[allow(clippy::too_many_arguments)] // Аргументы инструкции задают ее формат в IDL
     pub fn initialize_table<'info>(
         ctx: Context<'_, '_, '_, 'info, InitializeTable<'info>>,
         table_name_input: String,
         dealer_collateral_usd: u64,
-        shuffle_seed_nonce: u64,
+        first_shuffle_commitment: [u8; 32],
+        randomness_source: RandomnessSourceKind,
+        shoe_rules: ShoeRules,
+        seat_count: u8,
+        side_bet_paytable: SideBetPaytable,
+        game_variant: GameVariant,
+        settle_winnings_in_usdc: bool,
     ) -> Result<()> {
-        let game_session = &mut ctx.accounts.game_session_account;
+        let mut game_session = ctx.accounts.game_session_account.load_init()?;
         let dealer = &ctx.accounts.dealer;
-        let clock = &ctx.accounts.clock;
 
         // ВАЛИДАЦИЯ: Проверяем, что клиент прислал уже нормализованное имя.
         let normalized_table_name = normalize_and_validate_table_name(&table_name_input)?;
@@ -135,31 +286,46 @@ pub mod program_21 {
         if dealer_collateral_usd == 0 { return err!(TwentyOneError::MinBetIsZero); }
         
         if ctx.accounts.dealer_usdc_token_account.mint != ctx.accounts.usdc_mint.key() { return err!(TwentyOneError::UsdcMintMismatch); }
-
-        anchor_spl::token::transfer( CpiContext::new( ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.dealer_usdc_token_account.to_account_info(),
-                    to: ctx.accounts.usdc_escrow_account.to_account_info(),
-                    authority: dealer.to_account_info(),
-                }),
-            dealer_collateral_usd
+        verify_supported_mint(&ctx.accounts.usdc_mint.to_account_info())?;
+
+        transfer_tokens(
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.dealer_usdc_token_account.to_account_info(),
+            &ctx.accounts.usdc_mint.to_account_info(),
+            &ctx.accounts.usdc_escrow_account.to_account_info(),
+            &dealer.to_account_info(),
+            dealer_collateral_usd,
+            &[],
         )?;
 
         // Используем уже проверенное и нормализованное имя из `table_name_input`.
-        game_session.table_name = table_name_input;
+        game_session.version = GameSession::VERSION;
+        game_session.set_table_name(&table_name_input)?;
         game_session.dealer = dealer.key();
         game_session.dealer_locked_usdc_amount = dealer_collateral_usd;
-        game_session.game_state = GameState::AcceptingBets;
+        game_session.dealer_collateral_balance = dealer_collateral_usd;
+        game_session.dealer_usdc_escrow = ctx.accounts.usdc_escrow_account.key();
+        game_session.dealer_usdc_escrow_bump = ctx.bumps.usdc_escrow_account;
+        game_session.set_game_state(GameState::AcceptingBets);
+        game_session.set_seat_count(seat_count)?;
+        game_session.set_side_bet_paytable(side_bet_paytable)?;
         
-        let seed_hash = generate_shuffle_seed_hash( clock.slot, clock.unix_timestamp, &dealer.key(), shuffle_seed_nonce);
-        game_session.shuffle_deck(seed_hash)?;
+        // Шуза нет до первой перетасовки: бэкенд фиксирует элементы сида (`lock_shuffle_entropy`)
+        // и публикует корень Меркла первого шуза (`commit_shoe`) перед первой раздачей.
+        game_session.set_shoe_rules(shoe_rules)?;
+        game_session.set_game_variant(game_variant)?;
+        game_session.set_settles_winnings_in_usdc(settle_winnings_in_usdc);
+        game_session.current_deck_index = shoe_rules.total_cards();
+        game_session.set_next_shuffle_commitment(Some(first_shuffle_commitment));
+        game_session.next_shuffle_commitment_slot = ctx.accounts.clock.slot;
+        game_session.set_randomness_source(randomness_source);
         
         game_session.dealer_hand = Hand::default();
-        game_session.player_seats = vec![PlayerSeat::default(); MAX_PLAYERS_LIMIT as usize];
         game_session.bump = ctx.bumps.game_session_account;
 
         emit!(TableCreated {
-            table_name: game_session.table_name.clone(),
+            table_name: game_session.table_name().to_string(),
+            round_id: game_session.round_id,
             dealer: game_session.dealer,
         });
 
@@ -168,18 +334,27 @@ pub mod program_21 {
 
     // --- 3.2. join_table (ЗАЩИЩЕНАЯ ВЕРСИЯ) ---
     pub fn join_table<'info>(ctx: Context<'_, '_, '_, 'info, JoinTable<'info>>, seat_index: u8) -> Result<()> {
-        let game_session = &mut ctx.accounts.game_session_account;
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
         let player_to_seat_key = ctx.accounts.player_to_seat.key();
-        let seat_idx = seat_index as usize;
 
-        if seat_idx >= game_session.player_seats.len() { return err!(TwentyOneError::InvalidSeatIndex); }
-        if game_session.player_seats[seat_idx].player_pubkey.is_some() { return err!(TwentyOneError::SeatTaken); }
+        if seat_index >= game_session.seat_count() { return err!(TwentyOneError::InvalidSeatIndex); }
+        if game_session.is_seat_occupied(seat_index) { return err!(TwentyOneError::SeatTaken); }
 
-        game_session.player_seats[seat_idx].player_pubkey = Some(player_to_seat_key);
-        game_session.player_seats[seat_idx].reset_for_new_round(); 
+        // Аккаунт места создается при первой посадке за это место и затем переиспользуется.
+        let mut player_seat = match ctx.accounts.player_seat.load_init() {
+            Ok(player_seat) => player_seat,
+            Err(_) => ctx.accounts.player_seat.load_mut()?,
+        };
+        player_seat.game_session = ctx.accounts.game_session_account.key();
+        player_seat.seat_index = seat_index;
+        player_seat.bump = ctx.bumps.player_seat;
+        player_seat.set_player_pubkey(Some(player_to_seat_key));
+        player_seat.reset_for_new_round();
+        game_session.set_seat_occupied(seat_index, true);
 
         emit!(PlayerJoined {
-            table_name: game_session.table_name.clone(),
+            table_name: game_session.table_name().to_string(),
+            round_id: game_session.round_id,
             player: player_to_seat_key,
             seat_index,
         });
@@ -189,22 +364,24 @@ pub mod program_21 {
 
     // --- 3.3. leave_table ---
     pub fn leave_table<'info>(ctx: Context<'_, '_, '_, 'info, LeaveTable<'info>>, seat_index: u8) -> Result<()> {
-        let game_session = &mut ctx.accounts.game_session_account;
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
+        let mut player_seat = ctx.accounts.player_seat.load_mut()?;
         let player_account = &ctx.accounts.player_account;
-        let seat_idx = seat_index as usize;
 
-        verify_player_at_seat(game_session, player_account.as_ref(), seat_index)?;
-        if game_session.player_seats[seat_idx].is_active_in_round { return err!(TwentyOneError::PlayerHasActiveBet); }
-        if (game_session.game_state != GameState::AcceptingBets && game_session.game_state != GameState::RoundOver) &&
-           !game_session.player_seats[seat_idx].hands.is_empty() {
+        verify_player_at_seat(&player_seat, player_account.as_ref(), seat_index)?;
+        if player_seat.is_active_in_round() { return err!(TwentyOneError::PlayerHasActiveBet); }
+        if (game_session.game_state() != GameState::AcceptingBets && game_session.game_state() != GameState::RoundOver) &&
+           !player_seat.hands().is_empty() {
             return err!(TwentyOneError::PlayerHasActiveBet);
         }
         
-        game_session.player_seats[seat_idx].player_pubkey = None;
-        game_session.player_seats[seat_idx].reset_for_new_round();
+        player_seat.set_player_pubkey(None);
+        player_seat.reset_for_new_round();
+        game_session.set_seat_occupied(seat_index, false);
 
         emit!(PlayerLeft {
-            table_name: game_session.table_name.clone(),
+            table_name: game_session.table_name().to_string(),
+            round_id: game_session.round_id,
             player: player_account.key(),
             seat_index,
         });
@@ -213,63 +390,292 @@ pub mod program_21 {
     }
 
     // --- 3.4. place_bet (С ПРОВЕРКОЙ ЦЕНЫ) ---
+    /// Ставка на следующий свободный бокс места: игрок может играть до `MAX_SPOTS_PER_SEAT` боксов.
     pub fn place_bet<'info>(
         ctx: Context<'_, '_, '_, 'info, PlaceBet<'info>>,
         seat_index: u8,
         amount_staked_ui: u64, 
         usd_value_of_bet: u64,
     ) -> Result<()> {
-        let game_session = &mut ctx.accounts.game_session_account;
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
+        let mut player_seat = ctx.accounts.player_seat.load_mut()?;
         let player_account = &ctx.accounts.player_account;
         let token_mint_key = ctx.accounts.player_spl_token_account.mint;
 
-        if game_session.game_state != GameState::AcceptingBets { return err!(TwentyOneError::NotAcceptingBets); }
+        if game_session.game_state() != GameState::AcceptingBets { return err!(TwentyOneError::NotAcceptingBets); }
+        if game_session.is_tournament() { return err!(TwentyOneError::TournamentTable); }
 
-        verify_player_at_seat(game_session, player_account.as_ref(), seat_index)?;
-        let seat_idx = seat_index as usize;
-
-        if game_session.player_seats[seat_idx].is_active_in_round { return err!(TwentyOneError::PlayerHasActiveBet); }
+        verify_player_at_seat(&player_seat, player_account.as_ref(), seat_index)?;
         
-        let price_feed_account = &ctx.accounts.pyth_price_feed;
-        if *price_feed_account.owner != PYTH_RECEIVER_PROGRAM_ID { return err!(TwentyOneError::InvalidPriceFeedOwner); }
+        verify_bet_usd_value(&ctx.accounts.pyth_price_feed, amount_staked_ui, usd_value_of_bet)?;
+        verify_supported_mint(&ctx.accounts.spl_token_mint.to_account_info())?;
+
+        transfer_tokens(
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.player_spl_token_account.to_account_info(),
+            &ctx.accounts.spl_token_mint.to_account_info(),
+            &ctx.accounts.game_session_spl_escrow_account.to_account_info(),
+            &ctx.accounts.player_account.to_account_info(),
+            amount_staked_ui,
+            &[],
+        )?;
+        
+        // Каждый вызов занимает следующий свободный бокс места.
+        let spot_index = player_seat.add_spot(Hand::new(token_mint_key, amount_staked_ui, usd_value_of_bet))?;
+        player_seat.set_active_in_round(true);
+        game_session.seats_with_bets |= 1 << seat_index;
+
+        emit!(BetPlaced {
+            table_name: game_session.table_name().to_string(),
+            round_id: game_session.upcoming_round_id(),
+            player: player_account.key(),
+            seat_index,
+            spot_index,
+            amount: amount_staked_ui,
+            token_mint: token_mint_key,
+        });
+
+        Ok(())
+    }
 
-        let price_feed = PriceFeed::try_from_slice(&price_feed_account.data.borrow())
-            .map_err(|_| error!(TwentyOneError::PriceFeedStale))?;
-        let price = price_feed.get_price_unchecked();
+    // --- 3.4.1. contribute_shuffle_seed ---
+    /// Игрок за столом добавляет свой сид к следующей перетасовке (не более одного раза на место).
+    /// Принимается только после того, как бэкенд зафиксировал коммитмент своего секрета.
+    pub fn contribute_shuffle_seed<'info>(
+        ctx: Context<'_, '_, '_, 'info, ContributeShuffleSeed<'info>>,
+        seat_index: u8,
+        seed: [u8; 32],
+    ) -> Result<()> {
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
+        let player_account = &ctx.accounts.player_account;
 
-        let calculated_value_usd = (amount_staked_ui as u128)
-            .checked_mul(price.price as u128).ok_or(TwentyOneError::ArithmeticOverflow)?
-            .checked_div(10u128.pow(price.expo.abs() as u32)).ok_or(TwentyOneError::ArithmeticOverflow)?;
+        if game_session.game_state() != GameState::AcceptingBets { return err!(TwentyOneError::NotAcceptingBets); }
+        verify_player_at_seat(&*ctx.accounts.player_seat.load()?, player_account.as_ref(), seat_index)?;
+        if game_session.randomness_source() != RandomnessSourceKind::CommitReveal { return err!(TwentyOneError::PlayerSeedsNotAccepted); }
+        if game_session.next_shuffle_commitment().is_none() { return err!(TwentyOneError::ShuffleCommitmentMissing); }
 
-        let slippage_amount = (usd_value_of_bet as u128 * PAYOUT_PRICE_SLIPPAGE_BPS as u128) / 10000;
-        let lower_bound = (usd_value_of_bet as u128).saturating_sub(slippage_amount);
-        let upper_bound = (usd_value_of_bet as u128).saturating_add(slippage_amount);
-        
-        if calculated_value_usd < lower_bound || calculated_value_usd > upper_bound {
-             return err!(TwentyOneError::PayoutCalculationMismatch);
+        let seat_bit = 1u8 << seat_index;
+        if game_session.player_seed_contributors & seat_bit != 0 {
+            return err!(TwentyOneError::ShuffleSeedAlreadyContributed);
+        }
+
+        game_session.player_seeds_hash = accumulate_player_seed(&game_session.player_seeds_hash, &player_account.key(), &seed);
+        game_session.player_seed_contributors |= seat_bit;
+
+        emit!(ShuffleSeedContributed {
+            table_name: game_session.table_name().to_string(),
+            round_id: game_session.upcoming_round_id(),
+            player: player_account.key(),
+            seat_index,
+            player_seeds_hash: game_session.player_seeds_hash,
+        });
+
+        Ok(())
+    }
+
+    // --- 3.4.2. lock_shuffle_entropy ---
+    /// Фиксирует элементы сида следующего шуза: коммитмент секрета и энтропию из источника стола.
+    /// После этого бэкенд вычисляет порядок карт офчейн и публикует корень Меркла в `commit_shoe`.
+    pub fn lock_shuffle_entropy<'info>(ctx: Context<'_, '_, '_, 'info, LockShuffleEntropy<'info>>) -> Result<()> {
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
+
+        if game_session.game_state() != GameState::AcceptingBets { return err!(TwentyOneError::NotAcceptingBets); }
+        if !game_session.needs_shuffle() { return err!(TwentyOneError::ShuffleNotRequired); }
+        if game_session.pending_shoe().is_some() { return err!(TwentyOneError::ShuffleEntropyAlreadyLocked); }
+
+        let secret_commitment = game_session.next_shuffle_commitment().ok_or(TwentyOneError::ShuffleCommitmentMissing)?;
+        game_session.set_next_shuffle_commitment(None);
+        // Энтропия появляется только после коммита, поэтому бэкенд не может подобрать под нее секрет.
+        let slot_hashes = ctx.accounts.recent_slothashes.to_account_info();
+        let entropy = match game_session.randomness_source() {
+            RandomnessSourceKind::CommitReveal => CommitRevealSource {
+                player_seeds_hash: game_session.player_seeds_hash,
+                slot_hashes: &slot_hashes,
+            }.entropy()?,
+            RandomnessSourceKind::VrfAccount(expected_account) => {
+                let vrf_account = ctx.accounts.randomness_account.as_ref()
+                    .ok_or(TwentyOneError::RandomnessAccountMissing)?
+                    .to_account_info();
+                VrfAccountSource {
+                    vrf_account: &vrf_account,
+                    expected_account,
+                    min_seed_slot: game_session.next_shuffle_commitment_slot,
+                }.entropy()?
+            }
+            RandomnessSourceKind::SlotHashMix => SlotHashMixSource { slot_hashes: &slot_hashes }.entropy()?,
+        };
+
+        let source = game_session.randomness_source();
+        game_session.set_pending_shoe(Some(ShoeEntropy::new(secret_commitment, source, entropy)));
+        // Сиды игроков действуют только на одну перетасовку
+        game_session.player_seeds_hash = [0u8; 32];
+        game_session.player_seed_contributors = 0;
+
+        Ok(())
+    }
+
+    // --- 3.4.3. commit_shoe ---
+    /// Вводит в игру новый шуз по корню Меркла, вычисленному бэкендом из зафиксированных элементов сида.
+    /// Если в игре был предыдущий шуз, бэкенд обязан раскрыть его секрет: контракт пересобирает
+    /// старый шуз и его дерево и сверяет корень с тем, из которого раздавались карты.
+    pub fn commit_shoe<'info>(
+        ctx: Context<'_, '_, '_, 'info, BackendAuthorizedAction<'info>>,
+        deck_commitment: [u8; 32],
+        retired_shoe_secret: Option<[u8; 32]>,
+    ) -> Result<()> {
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
+
+        if game_session.game_state() != GameState::AcceptingBets { return err!(TwentyOneError::NotAcceptingBets); }
+        let entropy = game_session.pending_shoe().ok_or(TwentyOneError::ShuffleEntropyNotLocked)?;
+
+        if let Some(retired) = game_session.active_shoe() {
+            let secret = retired_shoe_secret.ok_or(TwentyOneError::ShuffleSecretRequired)?;
+            if hash_shuffle_secret(&secret) != retired.secret_commitment {
+                return err!(TwentyOneError::ShuffleCommitmentInvalid);
+            }
+
+            let seed = generate_shuffle_seed_hash(&secret, &retired.entropy);
+            let retired_deck = verify_shoe(seed, game_session.shoe_rules());
+            if compute_deck_merkle_root(&retired_deck, &secret) != game_session.deck_commitment {
+                return err!(TwentyOneError::ShoeRevealMismatch);
+            }
+
+            emit!(ShoeRevealed {
+                table_name: game_session.table_name().to_string(),
+                round_id: game_session.round_id,
+                shoe_id: game_session.shoe_id,
+                seed,
+                commitment: retired.secret_commitment,
+                revealed_secret: secret,
+                randomness_source: retired.source(),
+                entropy: retired.entropy,
+                deck_hash: hash_deck_order(&retired_deck),
+                deck_commitment: game_session.deck_commitment,
+            });
         }
 
-        anchor_spl::token::transfer( CpiContext::new( ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.player_spl_token_account.to_account_info(),
-                    to: ctx.accounts.game_session_spl_escrow_account.to_account_info(),
-                    authority: ctx.accounts.player_account.to_account_info(),
-                }),
-            amount_staked_ui
+        game_session.set_pending_shoe(None);
+        game_session.start_new_shoe(deck_commitment, entropy)?;
+
+        emit!(DeckShuffled {
+            table_name: game_session.table_name().to_string(),
+            round_id: game_session.upcoming_round_id(),
+            shoe_id: game_session.shoe_id,
+            commitment: entropy.secret_commitment,
+            randomness_source: entropy.source(),
+            entropy: entropy.entropy,
+            deck_commitment,
+        });
+
+        Ok(())
+    }
+
+    // --- 3.4.4. place_bet_behind ---
+    /// Зритель ставит на бокс места, на который игрок уже сделал ставку. Ставка повторяет исход этого бокса,
+    /// но зритель не ходит и не влияет на игру.
+    pub fn place_bet_behind<'info>(
+        ctx: Context<'_, '_, '_, 'info, PlaceBetBehind<'info>>,
+        seat_index: u8,
+        spot_index: u8,
+        amount_staked_ui: u64,
+        usd_value_of_bet: u64,
+    ) -> Result<()> {
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
+        let mut player_seat = ctx.accounts.player_seat.load_mut()?;
+        let backer = &ctx.accounts.backer;
+        let token_mint_key = ctx.accounts.spl_token_mint.key();
+
+        if game_session.game_state() != GameState::AcceptingBets { return err!(TwentyOneError::NotAcceptingBets); }
+        if game_session.is_tournament() { return err!(TwentyOneError::TournamentTable); }
+        if player_seat.player_pubkey() == Some(backer.key()) { return err!(TwentyOneError::BackerIsSeatedPlayer); }
+        if !player_seat.hands().iter().any(|hand| hand.spot_index == spot_index) {
+            return err!(TwentyOneError::SpotNotInPlay);
+        }
+        if player_seat.bets_behind >= MAX_BETS_BEHIND_PER_SEAT { return err!(TwentyOneError::TooManyBetsBehind); }
+
+        verify_bet_usd_value(&ctx.accounts.pyth_price_feed, amount_staked_ui, usd_value_of_bet)?;
+        verify_supported_mint(&ctx.accounts.spl_token_mint.to_account_info())?;
+
+        transfer_tokens(
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.backer_spl_token_account.to_account_info(),
+            &ctx.accounts.spl_token_mint.to_account_info(),
+            &ctx.accounts.game_session_spl_escrow_account.to_account_info(),
+            &backer.to_account_info(),
+            amount_staked_ui,
+            &[],
         )?;
-        
-        let player_seat = &mut game_session.player_seats[seat_idx];
-        player_seat.current_bet_token_mint = Some(token_mint_key);
-        player_seat.current_bet_amount_staked_ui = amount_staked_ui;
-        player_seat.current_bet_usd_value = usd_value_of_bet;
-        player_seat.is_active_in_round = true;
-        player_seat.hands.clear();
-        player_seat.hands.push(Hand::new(token_mint_key, amount_staked_ui));
 
-        emit!(BetPlaced {
-            table_name: game_session.table_name.clone(),
+        player_seat.bets_behind += 1;
+        game_session.seats_with_bets |= 1 << seat_index;
+        let bet_behind = &mut ctx.accounts.bet_behind;
+        bet_behind.game_session = ctx.accounts.game_session_account.key();
+        bet_behind.backer = backer.key();
+        bet_behind.round_id = game_session.upcoming_round_id();
+        bet_behind.seat_index = seat_index;
+        bet_behind.spot_index = spot_index;
+        bet_behind.token_mint = token_mint_key;
+        bet_behind.amount = amount_staked_ui;
+        bet_behind.bet_usd_value = usd_value_of_bet;
+        bet_behind.bump = ctx.bumps.bet_behind;
+
+        emit!(BetBehindPlaced {
+            table_name: game_session.table_name().to_string(),
+            round_id: bet_behind.round_id,
+            backer: backer.key(),
+            seat_index,
+            spot_index,
+            amount: amount_staked_ui,
+            token_mint: token_mint_key,
+        });
+
+        Ok(())
+    }
+
+    // --- 3.4.5. place_side_bet ---
+    /// Побочная ставка (21+3 или Perfect Pairs) на бокс, на котором игрок уже сделал основную ставку.
+    /// Комбинация определяется сразу после раздачи, выплата — по пейтейблу стола в `finalize_round`.
+    pub fn place_side_bet<'info>(
+        ctx: Context<'_, '_, '_, 'info, PlaceBet<'info>>,
+        seat_index: u8,
+        spot_index: u8,
+        kind: SideBetKind,
+        amount_staked_ui: u64,
+        usd_value_of_bet: u64,
+    ) -> Result<()> {
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
+        let mut player_seat = ctx.accounts.player_seat.load_mut()?;
+        let player_account = &ctx.accounts.player_account;
+        let token_mint_key = ctx.accounts.player_spl_token_account.mint;
+
+        if game_session.game_state() != GameState::AcceptingBets { return err!(TwentyOneError::NotAcceptingBets); }
+        if game_session.is_tournament() { return err!(TwentyOneError::TournamentTable); }
+        if !game_session.side_bet_paytable().offers(kind) { return err!(TwentyOneError::SideBetNotOffered); }
+
+        verify_player_at_seat(&player_seat, player_account.as_ref(), seat_index)?;
+        player_seat.add_side_bet(SideBet::new(kind, spot_index, token_mint_key, amount_staked_ui, usd_value_of_bet))?;
+
+        verify_bet_usd_value(&ctx.accounts.pyth_price_feed, amount_staked_ui, usd_value_of_bet)?;
+        verify_supported_mint(&ctx.accounts.spl_token_mint.to_account_info())?;
+
+        transfer_tokens(
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.player_spl_token_account.to_account_info(),
+            &ctx.accounts.spl_token_mint.to_account_info(),
+            &ctx.accounts.game_session_spl_escrow_account.to_account_info(),
+            &ctx.accounts.player_account.to_account_info(),
+            amount_staked_ui,
+            &[],
+        )?;
+        game_session.seats_with_bets |= 1 << seat_index;
+
+        emit!(SideBetPlaced {
+            table_name: game_session.table_name().to_string(),
+            round_id: game_session.upcoming_round_id(),
             player: player_account.key(),
             seat_index,
+            spot_index,
+            kind,
             amount: amount_staked_ui,
             token_mint: token_mint_key,
         });
@@ -277,157 +683,289 @@ pub mod program_21 {
         Ok(())
     }
 
-    // --- 3.5. deal_initial_cards (С ЛОГИКОЙ COMMIT-REVEAL) ---
+    // --- 3.4.6. place_tournament_bet ---
+    /// Участник турнира ставит фишки на следующий свободный бокс места. Токены не переводятся:
+    /// ставка списывается со стека в `Tournament`, выплата зачисляется туда же в `finalize_round`.
+    pub fn place_tournament_bet<'info>(
+        ctx: Context<'_, '_, '_, 'info, PlaceTournamentBet<'info>>,
+        seat_index: u8,
+        chips: u64,
+    ) -> Result<()> {
+        let game_session = ctx.accounts.game_session_account.load()?;
+        let mut player_seat = ctx.accounts.player_seat.load_mut()?;
+        let tournament = &mut ctx.accounts.tournament;
+        let player_account = &ctx.accounts.player_account;
+
+        if game_session.game_state() != GameState::AcceptingBets { return err!(TwentyOneError::NotAcceptingBets); }
+        if tournament.status != TournamentStatus::Running { return err!(TwentyOneError::TournamentNotRunning); }
+        if chips == 0 { return err!(TwentyOneError::MinBetIsZero); }
+
+        verify_player_at_seat(&player_seat, player_account.as_ref(), seat_index)?;
+        tournament.debit_chips(&player_account.key(), chips)?;
+
+        // Фишки не имеют минта и цены: стоимость ставки в расчетах равна числу фишек.
+        let spot_index = player_seat.add_spot(Hand::new(Pubkey::default(), chips, chips))?;
+        player_seat.set_active_in_round(true);
+
+        emit!(BetPlaced {
+            table_name: game_session.table_name().to_string(),
+            round_id: game_session.upcoming_round_id(),
+            player: player_account.key(),
+            seat_index,
+            spot_index,
+            amount: chips,
+            token_mint: Pubkey::default(),
+        });
+
+        Ok(())
+    }
+
+    // --- 3.4.7. place_sol_bet ---
+    /// Ставка в нативном SOL на следующий свободный бокс места. Лампорты оборачиваются в WSOL на эскроу стола,
+    /// дальше ставка рассчитывается как ставка WSOL; выплаты по ней разворачиваются в `finalize_round`.
+    pub fn place_sol_bet<'info>(
+        ctx: Context<'_, '_, '_, 'info, PlaceSolBet<'info>>,
+        seat_index: u8,
+        lamports: u64,
+        usd_value_of_bet: u64,
+    ) -> Result<()> {
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
+        let mut player_seat = ctx.accounts.player_seat.load_mut()?;
+        let player_account = &ctx.accounts.player_account;
+
+        if game_session.game_state() != GameState::AcceptingBets { return err!(TwentyOneError::NotAcceptingBets); }
+        if game_session.is_tournament() { return err!(TwentyOneError::TournamentTable); }
+
+        verify_player_at_seat(&player_seat, player_account.as_ref(), seat_index)?;
+
+        verify_bet_usd_value(&ctx.accounts.pyth_price_feed, lamports, usd_value_of_bet)?;
+
+        wrap_sol_into_escrow(
+            &player_account.to_account_info(),
+            &ctx.accounts.game_session_sol_escrow_account.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            lamports,
+        )?;
+
+        let spot_index = player_seat.add_spot(Hand::new(NATIVE_SOL_MINT_PUBKEY, lamports, usd_value_of_bet))?;
+        player_seat.set_active_in_round(true);
+        game_session.seats_with_bets |= 1 << seat_index;
+
+        emit!(BetPlaced {
+            table_name: game_session.table_name().to_string(),
+            round_id: game_session.upcoming_round_id(),
+            player: player_account.key(),
+            seat_index,
+            spot_index,
+            amount: lamports,
+            token_mint: NATIVE_SOL_MINT_PUBKEY,
+        });
+
+        Ok(())
+    }
+
+    // --- 3.4.8. place_vault_bet ---
+    /// Ставка на следующий свободный бокс места из хранилища игрока: баланс списывается без перевода токенов,
+    /// токены уже лежат на эскроу ставок стола.
+    pub fn place_vault_bet<'info>(
+        ctx: Context<'_, '_, '_, 'info, PlaceVaultBet<'info>>,
+        seat_index: u8,
+        amount_staked_ui: u64,
+        usd_value_of_bet: u64,
+    ) -> Result<()> {
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
+        let mut player_seat = ctx.accounts.player_seat.load_mut()?;
+        let player_account = &ctx.accounts.player_account;
+        let player_vault = &mut ctx.accounts.player_vault;
+
+        if game_session.game_state() != GameState::AcceptingBets { return err!(TwentyOneError::NotAcceptingBets); }
+        if game_session.is_tournament() { return err!(TwentyOneError::TournamentTable); }
+
+        verify_player_at_seat(&player_seat, player_account.as_ref(), seat_index)?;
+
+        verify_bet_usd_value(&ctx.accounts.pyth_price_feed, amount_staked_ui, usd_value_of_bet)?;
+
+        player_vault.debit(amount_staked_ui)?;
+        game_session.debit_vault_total(player_vault.token_mint, amount_staked_ui)?;
+
+        let spot_index = player_seat.add_spot(Hand::new(player_vault.token_mint, amount_staked_ui, usd_value_of_bet))?;
+        player_seat.set_active_in_round(true);
+        game_session.seats_with_bets |= 1 << seat_index;
+
+        emit!(BetPlaced {
+            table_name: game_session.table_name().to_string(),
+            round_id: game_session.upcoming_round_id(),
+            player: player_account.key(),
+            seat_index,
+            spot_index,
+            amount: amount_staked_ui,
+            token_mint: player_vault.token_mint,
+        });
+
+        Ok(())
+    }
+
+    // --- 3.5. deal_initial_cards ---
+    /// Раздает начальные карты. Бэкенд раскрывает подряд идущие карты шуза в порядке раздачи:
+    /// первая карта каждому игроку, открытая карта дилера, вторая карта каждому игроку.
+    /// Следующая позиция шуза сдается дилеру закрытой и раскрывается только в `dealer_play_turn`.
+    /// В Double Exposure вместо закрытой карты раскрывается еще одна: вторая карта дилера.
+    /// `remaining_accounts`: аккаунты всех занятых мест стола (`PlayerSeat`) по возрастанию номера места.
     pub fn deal_initial_cards<'info>(
-        ctx: Context<'_, '_, '_, 'info, BackendAuthorizedAction<'info>>,
-        shuffle_seed_nonce: Option<u64>
+        ctx: Context<'_, '_, 'info, 'info, BackendAuthorizedAction<'info>>,
+        reveal: CardRangeReveal,
     ) -> Result<()> {
-        let game_session = &mut ctx.accounts.game_session_account;
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
         
-        if game_session.game_state != GameState::AcceptingBets {
+        if game_session.game_state() != GameState::AcceptingBets {
             return err!(TwentyOneError::InvalidGameStateForDeal);
         }
+        if game_session.needs_shuffle() {
+            return err!(TwentyOneError::ShuffleRequired);
+        }
 
-        // --- ФАЗА "REVEAL" ---
-        if game_session.current_deck_index >= DECK_RESHUFFLE_THRESHOLD_INDEX {
-            // Требуется перетасовка. Проверяем nonce.
-            let nonce = shuffle_seed_nonce.ok_or(TwentyOneError::ShuffleNonceRequired)?;
-            let commitment = game_session.next_shuffle_commitment.ok_or(TwentyOneError::ShuffleCommitmentMissing)?;
-
-            let seed_hash = generate_shuffle_seed_hash(
-                ctx.accounts.clock.slot,
-                ctx.accounts.clock.unix_timestamp,
-                &ctx.accounts.backend_signer.key(),
-                nonce, // Используем раскрытый nonce
-            );
+        // Новый раунд получает следующий по порядку номер.
+        game_session.round_id = game_session.round_id.checked_add(1).ok_or(TwentyOneError::ArithmeticOverflow)?;
+        game_session.round_dealt_at = ctx.accounts.clock.unix_timestamp;
 
-            if seed_hash != commitment {
-                return err!(TwentyOneError::ShuffleCommitmentInvalid);
+        // Ставки принимаются без блокировки стола, поэтому участники раунда определяются по аккаунтам мест.
+        let seat_loaders = load_seat_accounts(
+            ctx.remaining_accounts,
+            &ctx.accounts.game_session_account.key(),
+            game_session.occupied_seats,
+        )?;
+        let mut active_seats = Vec::with_capacity(seat_loaders.len());
+        for loader in &seat_loaders {
+            let player_seat = loader.load_mut()?;
+            if player_seat.is_active_in_round() {
+                active_seats.push(player_seat);
             }
-
-            // Тасуем колоду и сбрасываем коммит
-            game_session.shuffle_deck(seed_hash)?;
-            game_session.next_shuffle_commitment = None;
-            
-            emit!(DeckShuffled {
-                table_name: game_session.table_name.clone(),
-            });
         }
 
-        let active_player_count = game_session.player_seats.iter().filter(|s| s.is_active_in_round).count();
+        let active_player_count = active_seats.len();
         if active_player_count < MIN_PLAYERS_FOR_DEAL as usize {
             return err!(TwentyOneError::NotEnoughPlayers);
         }
+        let spot_count: usize = active_seats.iter().map(|seat| seat.hands().len()).sum();
+        // В Double Exposure вторая карта дилера тоже открыта и раскрывается вместе с остальными.
+        let exposes_dealer_cards = game_session.game_variant().exposes_dealer_cards();
+        let dealer_open_cards = if exposes_dealer_cards { 2 } else { 1 };
+        if reveal.cards.len() != spot_count * 2 + dealer_open_cards {
+            return err!(TwentyOneError::CardRevealCountMismatch);
+        }
+        let mut dealt_cards = game_session.draw_cards(&reveal)?.into_iter();
         
         game_session.dealer_hand = Hand::default();
 
-        for seat_idx in 0..game_session.player_seats.len() {
-            if game_session.player_seats[seat_idx].is_active_in_round {
-                let card = game_session.draw_card()?;
-                if let Some(hand) = game_session.player_seats[seat_idx].hands.get_mut(0) {
-                    hand.add_card(card);
+        for deal_pass in 0..2 {
+            for player_seat in active_seats.iter_mut() {
+                for hand in player_seat.hands_mut() {
+                    hand.add_card(dealt_cards.next().ok_or(TwentyOneError::CardRevealCountMismatch)?)?;
                 }
             }
-        }
-        let card_for_dealer_1 = game_session.draw_card()?;
-        game_session.dealer_hand.add_card(card_for_dealer_1);
-
-        for seat_idx in 0..game_session.player_seats.len() {
-            if game_session.player_seats[seat_idx].is_active_in_round {
-                let card = game_session.draw_card()?;
-                if let Some(hand) = game_session.player_seats[seat_idx].hands.get_mut(0) {
-                    hand.add_card(card);
-                }
+            if deal_pass == 0 || exposes_dealer_cards {
+                let dealer_card = dealt_cards.next().ok_or(TwentyOneError::CardRevealCountMismatch)?;
+                game_session.dealer_hand.add_card(dealer_card)?;
+            } else {
+                // Закрытая карта: позиция в шузе фиксируется сейчас, а сама карта никому не видна до хода дилера.
+                let hole_card_index = game_session.draw_unrevealed_card()?;
+                game_session.dealer_hand.set_unrevealed_card_index(Some(hole_card_index));
             }
         }
-        let card_for_dealer_2 = game_session.draw_card()?;
-        game_session.dealer_hand.add_card(card_for_dealer_2);
         
-        let mut all_players_have_blackjack_or_resolved = true;
-        let mut initial_hands_for_event: Vec<InitialPlayerHand> = Vec::with_capacity(active_player_count);
-
-        for seat_idx in 0..game_session.player_seats.len() {
-            if game_session.player_seats[seat_idx].is_active_in_round {
-                if let Some(hand) = game_session.player_seats[seat_idx].hands.get_mut(0) {
-                    if hand.is_blackjack() { hand.status = HandStatus::Blackjack; } 
-                    else { all_players_have_blackjack_or_resolved = false; }
-                }
-                let player_seat = &game_session.player_seats[seat_idx];
+        let mut initial_hands_for_event: Vec<InitialPlayerHand> = Vec::with_capacity(spot_count);
+        let mut side_bets_for_event: Vec<SideBetEvaluation> = Vec::new();
+        let dealer_up_card = game_session.dealer_hand.card(0).ok_or(ProgramError::InvalidInstructionData)?;
+        let dealer_shows_blackjack = exposes_dealer_cards && game_session.dealer_hand.is_blackjack();
+        game_session.round_seats = 0;
+        game_session.seats_to_act = 0;
+
+        for player_seat in active_seats.iter_mut() {
+            let seat_index = player_seat.seat_index;
+            let player = player_seat.player_pubkey().ok_or(ProgramError::InvalidInstructionData)?;
+            game_session.round_seats |= 1 << seat_index;
+            for hand in player_seat.hands_mut() {
+                if hand.is_blackjack() { hand.set_status(HandStatus::Blackjack); }
                 initial_hands_for_event.push(InitialPlayerHand {
-                    player: player_seat.player_pubkey.ok_or(ProgramError::InvalidInstructionData)?,
-                    seat_index: seat_idx as u8,
-                    hand: player_seat.hands.get(0).ok_or(ProgramError::InvalidInstructionData)?.cards.clone(),
+                    player,
+                    seat_index,
+                    spot_index: hand.spot_index,
+                    hand: hand.cards(),
                 });
             }
+            // Побочные ставки рассчитываются по начальным картам бокса, до любых действий игрока.
+            let side_bet_outcomes = player_seat.side_bets().iter().map(|side_bet| {
+                let hand = player_seat.hands().iter()
+                    .find(|hand| hand.spot_index == side_bet.spot_index)
+                    .ok_or(TwentyOneError::SpotNotInPlay)?;
+                evaluate_side_bet(side_bet.kind(), hand, dealer_up_card)
+            }).collect::<Result<Vec<_>>>()?;
+            for (side_bet, outcome) in player_seat.side_bets_mut().iter_mut().zip(side_bet_outcomes) {
+                side_bet.set_outcome(outcome);
+                side_bets_for_event.push(SideBetEvaluation {
+                    player,
+                    seat_index,
+                    spot_index: side_bet.spot_index,
+                    kind: side_bet.kind(),
+                    outcome,
+                });
+            }
+            // Место ходит, если хотя бы один его бокс без блэкджека. Против открытого блэкджека дилера не ходит никто.
+            if let Some(first_hand) = player_seat.get_first_active_hand_index().filter(|_| !dealer_shows_blackjack) {
+                game_session.set_seat_to_act(seat_index, first_hand as u8);
+            }
         }
 
-        if all_players_have_blackjack_or_resolved && active_player_count > 0 {
-            game_session.game_state = GameState::RoundOver;
+        // Если ходить некому, раунд сразу переходит к дилеру: исход зависит от его закрытой карты.
+        if let Some(seat_index) = game_session.first_seat_to_act() {
+            game_session.set_game_state(GameState::PlayerTurns);
+            let first_hand = game_session.first_hand_to_act(seat_index);
+            game_session.set_current_turn(seat_index, first_hand, ctx.accounts.clock.unix_timestamp);
         } else {
-            game_session.game_state = GameState::PlayerTurns;
-            let first_player_to_act_idx = game_session.player_seats.iter().position(
-                |s| s.is_active_in_round && s.hands.get(0).map_or(false, |h| h.status != HandStatus::Blackjack)
-            ); 
-            if let Some(idx) = first_player_to_act_idx {
-                game_session.current_turn_seat_index = Some(idx as u8);
-                game_session.current_turn_hand_index = Some(0);
-                game_session.current_turn_start_timestamp = Some(ctx.accounts.clock.unix_timestamp);
-            } else {
-                game_session.game_state = GameState::RoundOver;
-            }
+            game_session.set_game_state(GameState::DealerTurn);
         }
         
         emit!(RoundStarted {
-            table_name: game_session.table_name.clone(),
-            dealer_up_card: *game_session.dealer_hand.cards.get(0).ok_or(ProgramError::InvalidInstructionData)?,
+            table_name: game_session.table_name().to_string(),
+            round_id: game_session.round_id,
+            dealer_up_card,
+            dealer_second_card: if exposes_dealer_cards { game_session.dealer_hand.card(1) } else { None },
             player_hands: initial_hands_for_event,
+            side_bets: side_bets_for_event,
         });
 
         Ok(())
     }
     
     // --- 3.6. player_action_hit ---
+    /// Игрок просит карту. Карту раскрывает бэкенд в `reveal_pending_cards`,
+    /// поэтому игрок не может узнать ее до того, как подписал действие.
     pub fn player_action_hit<'info>(ctx: Context<'_, '_, '_, 'info, PlayerAction<'info>>, seat_index: u8, hand_index: u8) -> Result<()> {
-        let game_session = &mut ctx.accounts.game_session_account;
-        verify_player_turn_and_hand(game_session, ctx.accounts.player_account.as_ref(), seat_index, hand_index)?;
-        
-        let table_name = game_session.table_name.clone();
-        let player_key = ctx.accounts.player_account.key();
-        
-        let new_card = game_session.draw_card()?;
-        let hand = game_session.player_seats[seat_index as usize].hands.get_mut(hand_index as usize)
-            .ok_or_else(|| error!(TwentyOneError::InvalidHandIndex))?;
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
+        let player_seat = ctx.accounts.player_seat.load()?;
+        verify_player_turn_and_hand(&game_session, &player_seat, ctx.accounts.player_account.as_ref(), seat_index, hand_index)?;
+        // Карту в полную руку сдать нельзя: отклоняем запрос до того, как бэкенд раскроет позицию шуза.
+        if player_seat.hands()[hand_index as usize].is_full() { return err!(TwentyOneError::HandFull); }
 
-        hand.add_card(new_card);
-        hand.update_status_after_card_drawn();
-        
-        emit!(PlayerActed {
-            table_name,
-            player: player_key,
-            seat_index,
-            hand_index,
-            action: PlayerActionType::Hit,
-            new_card: Some(new_card),
-        });
-        
-        if hand.status != HandStatus::Playing {
-            determine_next_player_or_transition_to_dealer(game_session, ctx.accounts.clock.unix_timestamp)?;
-        }
+        game_session.set_pending_draw(Some(PendingDraw::Hit));
         Ok(())
     }
 
     // --- 3.7. player_action_stand ---
     pub fn player_action_stand<'info>(ctx: Context<'_, '_, '_, 'info, PlayerAction<'info>>, seat_index: u8, hand_index: u8) -> Result<()> {
-        let game_session = &mut ctx.accounts.game_session_account;
-        verify_player_turn_and_hand(game_session, ctx.accounts.player_account.as_ref(), seat_index, hand_index)?;
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
+        let mut player_seat = ctx.accounts.player_seat.load_mut()?;
+        verify_player_turn(&game_session, &player_seat, ctx.accounts.player_account.as_ref(), seat_index, hand_index)?;
 
-        let hand = game_session.player_seats[seat_index as usize].hands.get_mut(hand_index as usize)
+        let hand = player_seat.hands_mut().get_mut(hand_index as usize)
             .ok_or_else(|| error!(TwentyOneError::InvalidHandIndex))?;
         
-        hand.status = HandStatus::Stood;
+        hand.stand()?;
 
         emit!(PlayerActed {
-            table_name: game_session.table_name.clone(),
+            table_name: game_session.table_name().to_string(),
+            round_id: game_session.round_id,
             player: ctx.accounts.player_account.key(),
             seat_index,
             hand_index,
@@ -435,171 +973,430 @@ pub mod program_21 {
             new_card: None,
         });
 
-        determine_next_player_or_transition_to_dealer(game_session, ctx.accounts.clock.unix_timestamp)?;
+        determine_next_player_or_transition_to_dealer(&mut game_session, &player_seat, ctx.accounts.clock.unix_timestamp)?;
         Ok(())
     }
 
     // --- 3.8. player_action_double_down ---
+    /// Игрок удваивает ставку. Дополнительная ставка переводится сразу (или списывается с `player_vault`), карту раскрывает бэкенд.
+    /// За столом Free Bet удвоение жестких 9–11 оплачивает заведение: перевода нет, ставка учитывается как бесплатная.
     pub fn player_action_double_down<'info>(ctx: Context<'_, '_, '_, 'info, PlayerActionDoubleOrSplit<'info>>, seat_index: u8, hand_index: u8) -> Result<()> {
-        let game_session = &mut ctx.accounts.game_session_account;
-        verify_player_turn_and_hand(game_session, ctx.accounts.player_account.as_ref(), seat_index, hand_index)?;
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
+        let mut player_seat = ctx.accounts.player_seat.load_mut()?;
+        verify_player_turn_and_hand(&game_session, &player_seat, ctx.accounts.player_account.as_ref(), seat_index, hand_index)?;
         
         let hand_token_mint_for_bet;
         let hand_original_bet_amount;
+        let is_free_double;
         
         {
-            let player_seat_check = &game_session.player_seats[seat_index as usize];
-            let hand_check = player_seat_check.hands.get(hand_index as usize).ok_or(TwentyOneError::InvalidHandIndex)?;
-            if hand_check.cards.len() != 2 { return err!(TwentyOneError::CannotDoubleNotTwoCards); }
+            let hand_check = player_seat.hands().get(hand_index as usize).ok_or(TwentyOneError::InvalidHandIndex)?;
+            if hand_check.card_count() != 2 { return err!(TwentyOneError::CannotDoubleNotTwoCards); }
             hand_token_mint_for_bet = hand_check.token_mint_for_bet;
             hand_original_bet_amount = hand_check.original_bet_amount;
+            is_free_double = game_session.game_variant().is_free_double(hand_check);
         }
 
-        if ctx.accounts.player_spl_token_account.mint != hand_token_mint_for_bet { return err!(TwentyOneError::BetTokenMintMismatch); }
-        
-        let additional_stake = hand_original_bet_amount;
-        if ctx.accounts.player_spl_token_account.amount < additional_stake { return err!(TwentyOneError::InsufficientFundsForDoubleDown); }
-
-        anchor_spl::token::transfer( CpiContext::new( ctx.accounts.token_program.to_account_info(), Transfer {
-                from: ctx.accounts.player_spl_token_account.to_account_info(),
-                to: ctx.accounts.game_session_spl_escrow_account.to_account_info(),
-                authority: ctx.accounts.player_account.to_account_info(),
-            }), additional_stake)?;
+        if game_session.is_tournament() {
+            // За турнирным столом удвоение списывается фишками.
+            if !is_free_double {
+                let tournament = ctx.accounts.tournament.as_mut().ok_or(TwentyOneError::TournamentAccountRequired)?;
+                tournament.debit_chips(&ctx.accounts.player_account.key(), hand_original_bet_amount)?;
+            }
+        } else if ctx.accounts.game_session_spl_escrow_account.mint != hand_token_mint_for_bet {
+            return err!(TwentyOneError::BetTokenMintMismatch);
+        } else if !is_free_double {
+            let additional_stake = hand_original_bet_amount;
+            if let Some(player_vault) = ctx.accounts.player_vault.as_mut() {
+                player_vault.debit(additional_stake)?;
+                game_session.debit_vault_total(player_vault.token_mint, additional_stake)?;
+            } else if hand_token_mint_for_bet == NATIVE_SOL_MINT_PUBKEY {
+                let system_program = ctx.accounts.system_program.as_ref().ok_or(TwentyOneError::NativeSolAccountsRequired)?;
+                if ctx.accounts.player_account.lamports() < additional_stake { return err!(TwentyOneError::InsufficientFundsForDoubleDown); }
+                wrap_sol_into_escrow(
+                    &ctx.accounts.player_account.to_account_info(),
+                    &ctx.accounts.game_session_spl_escrow_account.to_account_info(),
+                    &system_program.to_account_info(),
+                    &ctx.accounts.token_program.to_account_info(),
+                    additional_stake,
+                )?;
+            } else {
+                let player_token_account = ctx.accounts.player_spl_token_account.as_ref().ok_or(TwentyOneError::BetTokenMintMismatch)?;
+                if player_token_account.mint != hand_token_mint_for_bet { return err!(TwentyOneError::BetTokenMintMismatch); }
+                if player_token_account.amount < additional_stake { return err!(TwentyOneError::InsufficientFundsForDoubleDown); }
+
+                transfer_tokens(
+                    &ctx.accounts.token_program.to_account_info(),
+                    &player_token_account.to_account_info(),
+                    &ctx.accounts.bet_token_mint.to_account_info(),
+                    &ctx.accounts.game_session_spl_escrow_account.to_account_info(),
+                    &ctx.accounts.player_account.to_account_info(),
+                    additional_stake,
+                    &[],
+                )?;
+            }
+        }
 
-        let new_card = game_session.draw_card()?;
-        let hand = game_session.player_seats[seat_index as usize].hands.get_mut(hand_index as usize).ok_or(TwentyOneError::InvalidHandIndex)?;
+        let hand = player_seat.hands_mut().get_mut(hand_index as usize).ok_or(TwentyOneError::InvalidHandIndex)?;
         hand.bet_multiplier_x100 = 200;
-        hand.add_card(new_card);
-        
-        hand.update_status_after_card_drawn();
-        if hand.status != HandStatus::Busted { hand.status = HandStatus::DoubledAndStood; }
-        
-        emit!(PlayerActed {
-            table_name: game_session.table_name.clone(),
-            player: ctx.accounts.player_account.key(),
-            seat_index,
-            hand_index,
-            action: PlayerActionType::DoubleDown,
-            new_card: Some(new_card),
-        });
-
-        determine_next_player_or_transition_to_dealer(game_session, ctx.accounts.clock.unix_timestamp)?;
+        if is_free_double {
+            hand.free_stake_x100 += 100;
+        }
+        game_session.set_pending_draw(Some(PendingDraw::DoubleDown));
         Ok(())
     }
     
     // --- 3.9. player_action_split ---
+    /// Игрок делит пару. Ставка на новую руку переводится сразу (или списывается с `player_vault`), по карте в каждую руку раскрывает бэкенд.
+    /// За столом Free Bet ставку на новую руку (кроме сплита десяток) оплачивает заведение.
     pub fn player_action_split<'info>(ctx: Context<'_, '_, '_, 'info, PlayerActionDoubleOrSplit<'info>>, seat_index: u8, hand_index: u8) -> Result<()> {
-        if hand_index != 0 { return err!(TwentyOneError::CannotSplitAlreadySplit); }
-        
-        let game_session = &mut ctx.accounts.game_session_account;
-        verify_player_turn_and_hand(game_session, ctx.accounts.player_account.as_ref(), seat_index, hand_index)?;
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
+        let mut player_seat = ctx.accounts.player_seat.load_mut()?;
+        verify_player_turn_and_hand(&game_session, &player_seat, ctx.accounts.player_account.as_ref(), seat_index, hand_index)?;
 
-        let (original_hand_token_mint, original_hand_bet_amount);
+        let (original_hand_token_mint, original_hand_bet_amount, original_hand_bet_usd_value, spot_index, is_free_split);
         {
-            let player_seat_check = &game_session.player_seats[seat_index as usize];
-            if player_seat_check.hands.len() != 1 { return err!(TwentyOneError::CannotSplitAlreadySplit); }
-            let original_hand_check = player_seat_check.hands.get(0).ok_or(TwentyOneError::InvalidHandIndex)?;
-            if original_hand_check.cards.len() != 2 { return err!(TwentyOneError::CannotSplitNotTwoCards); }
-            if original_hand_check.cards[0].default_value() != original_hand_check.cards[1].default_value() { return err!(TwentyOneError::CannotSplitRanksMismatch); }
+            let original_hand_check = player_seat.hands().get(hand_index as usize).ok_or(TwentyOneError::InvalidHandIndex)?;
+            // Каждый бокс можно разделить один раз.
+            if original_hand_check.is_split() { return err!(TwentyOneError::CannotSplitAlreadySplit); }
+            if original_hand_check.card_count() != 2 { return err!(TwentyOneError::CannotSplitNotTwoCards); }
+            let original_cards = original_hand_check.cards();
+            if original_cards[0].default_value() != original_cards[1].default_value() { return err!(TwentyOneError::CannotSplitRanksMismatch); }
             original_hand_token_mint = original_hand_check.token_mint_for_bet;
             original_hand_bet_amount = original_hand_check.original_bet_amount;
+            original_hand_bet_usd_value = original_hand_check.bet_usd_value;
+            spot_index = original_hand_check.spot_index;
+            is_free_split = game_session.game_variant().is_free_split(original_cards[0]);
         }
 
-        if ctx.accounts.player_spl_token_account.mint != original_hand_token_mint { return err!(TwentyOneError::BetTokenMintMismatch); }
-        
         let stake_for_new_hand = original_hand_bet_amount;
-        if ctx.accounts.player_spl_token_account.amount < stake_for_new_hand { return err!(TwentyOneError::InsufficientFundsForSplit); }
+        if game_session.is_tournament() {
+            // За турнирным столом ставка на новую руку списывается фишками.
+            if !is_free_split {
+                let tournament = ctx.accounts.tournament.as_mut().ok_or(TwentyOneError::TournamentAccountRequired)?;
+                tournament.debit_chips(&ctx.accounts.player_account.key(), stake_for_new_hand)?;
+            }
+        } else if ctx.accounts.game_session_spl_escrow_account.mint != original_hand_token_mint {
+            return err!(TwentyOneError::BetTokenMintMismatch);
+        } else if !is_free_split {
+            if let Some(player_vault) = ctx.accounts.player_vault.as_mut() {
+                player_vault.debit(stake_for_new_hand)?;
+                game_session.debit_vault_total(player_vault.token_mint, stake_for_new_hand)?;
+            } else if original_hand_token_mint == NATIVE_SOL_MINT_PUBKEY {
+                let system_program = ctx.accounts.system_program.as_ref().ok_or(TwentyOneError::NativeSolAccountsRequired)?;
+                if ctx.accounts.player_account.lamports() < stake_for_new_hand { return err!(TwentyOneError::InsufficientFundsForSplit); }
+                wrap_sol_into_escrow(
+                    &ctx.accounts.player_account.to_account_info(),
+                    &ctx.accounts.game_session_spl_escrow_account.to_account_info(),
+                    &system_program.to_account_info(),
+                    &ctx.accounts.token_program.to_account_info(),
+                    stake_for_new_hand,
+                )?;
+            } else {
+                let player_token_account = ctx.accounts.player_spl_token_account.as_ref().ok_or(TwentyOneError::BetTokenMintMismatch)?;
+                if player_token_account.mint != original_hand_token_mint { return err!(TwentyOneError::BetTokenMintMismatch); }
+                if player_token_account.amount < stake_for_new_hand { return err!(TwentyOneError::InsufficientFundsForSplit); }
+
+                transfer_tokens(
+                    &ctx.accounts.token_program.to_account_info(),
+                    &player_token_account.to_account_info(),
+                    &ctx.accounts.bet_token_mint.to_account_info(),
+                    &ctx.accounts.game_session_spl_escrow_account.to_account_info(),
+                    &ctx.accounts.player_account.to_account_info(),
+                    stake_for_new_hand,
+                    &[],
+                )?;
+            }
+        }
+
+        let original_hand = &mut player_seat.hands_mut()[hand_index as usize];
+        let card_for_new_hand = original_hand.pop_card().ok_or(ProgramError::InvalidInstructionData)?;
+        original_hand.set_split(true);
+        
+        let mut new_hand = Hand::new(original_hand_token_mint, stake_for_new_hand, original_hand_bet_usd_value);
+        new_hand.spot_index = spot_index;
+        new_hand.set_split(true);
+        if is_free_split {
+            new_hand.free_stake_x100 = 100;
+        }
+        new_hand.add_card(card_for_new_hand)?;
+
+        player_seat.insert_split_hand(hand_index as usize, new_hand)?;
+
+        game_session.set_pending_draw(Some(PendingDraw::Split));
+        Ok(())
+    }
+
+    // --- 3.9.1. reveal_pending_cards ---
+    /// Бэкенд раскрывает карты для действия игрока, ожидающего раздачи (hit, double down, split),
+    /// и завершает это действие.
+    pub fn reveal_pending_cards<'info>(ctx: Context<'_, '_, '_, 'info, RevealPendingCards<'info>>, reveal: CardRangeReveal) -> Result<()> {
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
+        let mut player_seat = ctx.accounts.player_seat.load_mut()?;
+        let now = ctx.accounts.clock.unix_timestamp;
+
+        if game_session.game_state() != GameState::PlayerTurns { return err!(TwentyOneError::NotPlayerTurnsState); }
+        verify_current_turn_seat(&game_session, &ctx.accounts.game_session_account.key(), &player_seat)?;
+        let pending_draw = game_session.pending_draw().ok_or(TwentyOneError::NoPendingCardReveal)?;
+        let seat_index = game_session.current_turn_seat_index().ok_or(TwentyOneError::WrongSeatForTurn)?;
+        let hand_index = game_session.current_turn_hand_index().ok_or(TwentyOneError::WrongHandForTurn)?;
+
+        let cards_to_draw = if pending_draw == PendingDraw::Split { 2 } else { 1 };
+        if reveal.cards.len() != cards_to_draw { return err!(TwentyOneError::CardRevealCountMismatch); }
+        let new_cards = game_session.draw_cards(&reveal)?;
+        game_session.set_pending_draw(None);
+
+        let table_name = game_session.table_name().to_string();
+        let round_id = game_session.round_id;
+        let player_key = player_seat.player_pubkey().ok_or(TwentyOneError::SeatNotTaken)?;
+
+        let game_variant = game_session.game_variant();
+        let turn_continues = match pending_draw {
+            PendingDraw::Hit | PendingDraw::DoubleDown => {
+                let hand = player_seat.hands_mut().get_mut(hand_index as usize).ok_or(TwentyOneError::InvalidHandIndex)?;
+                hand.add_card(new_cards[0])?;
+                hand.update_status_after_card_drawn();
+                let action = if pending_draw == PendingDraw::Hit {
+                    PlayerActionType::Hit
+                } else {
+                    if hand.status() != HandStatus::Busted { hand.set_status(HandStatus::DoubledAndStood); }
+                    PlayerActionType::DoubleDown
+                };
+
+                emit!(PlayerActed {
+                    table_name,
+                    round_id,
+                    player: player_key,
+                    seat_index,
+                    hand_index,
+                    action,
+                    new_card: Some(new_cards[0]),
+                });
+                // Spanish 21: ход остается на удвоенной руке, пока игрок решает, спасать ли удвоение.
+                let awaits_rescue = pending_draw == PendingDraw::DoubleDown
+                    && game_variant.allows_double_rescue()
+                    && hand.status() == HandStatus::DoubledAndStood
+                    && hand.calculate_score().0 < 21;
+                hand.status() == HandStatus::Playing || awaits_rescue
+            },
+            PendingDraw::Split => {
+                let split_hand_indices = [hand_index as usize, hand_index as usize + 1];
+                for (hand_idx_to_deal, card_dealt) in split_hand_indices.into_iter().zip(new_cards) {
+                    let hand = player_seat.hands_mut().get_mut(hand_idx_to_deal).ok_or(TwentyOneError::InvalidHandIndex)?;
+                    hand.add_card(card_dealt)?;
+
+                    if hand.card(0).is_some_and(|card| card.is_ace()) { hand.set_status(HandStatus::Stood); }
+                    else { hand.update_status_after_card_drawn(); }
+
+                    emit!(PlayerActed {
+                        table_name: table_name.clone(),
+                        round_id,
+                        player: player_key,
+                        seat_index,
+                        hand_index: hand_idx_to_deal as u8,
+                        action: PlayerActionType::Split,
+                        new_card: Some(card_dealt),
+                    });
+                }
+                player_seat.hands()[hand_index as usize].status() == HandStatus::Playing
+            },
+        };
+
+        if turn_continues {
+            // Игрок ждал раскрытия карты бэкендом, поэтому таймер хода начинается заново.
+            game_session.set_current_turn(seat_index, hand_index, now);
+        } else {
+            determine_next_player_or_transition_to_dealer(&mut game_session, &player_seat, now)?;
+        }
+        Ok(())
+    }
+
+    // --- 3.9.2. player_action_surrender ---
+    /// Поздний отказ (Spanish 21): игрок сдает руку на первых двух картах и получает назад половину ставки,
+    /// если у дилера нет блэкджека.
+    pub fn player_action_surrender<'info>(ctx: Context<'_, '_, '_, 'info, PlayerAction<'info>>, seat_index: u8, hand_index: u8) -> Result<()> {
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
+        let mut player_seat = ctx.accounts.player_seat.load_mut()?;
+        verify_player_turn_and_hand(&game_session, &player_seat, ctx.accounts.player_account.as_ref(), seat_index, hand_index)?;
+        if !game_session.game_variant().allows_surrender() { return err!(TwentyOneError::SurrenderNotAllowed); }
 
-        anchor_spl::token::transfer( CpiContext::new( ctx.accounts.token_program.to_account_info(), Transfer {
-                from: ctx.accounts.player_spl_token_account.to_account_info(),
-                to: ctx.accounts.game_session_spl_escrow_account.to_account_info(),
-                authority: ctx.accounts.player_account.to_account_info(),
-            }), stake_for_new_hand)?;
+        let hand = player_seat.hands_mut().get_mut(hand_index as usize).ok_or(TwentyOneError::InvalidHandIndex)?;
+        if hand.card_count() != 2 || hand.is_split() { return err!(TwentyOneError::SurrenderNotAllowed); }
+        hand.set_status(HandStatus::Surrendered);
 
-        let table_name_for_event = game_session.table_name.clone();
-        let player_key_for_event = ctx.accounts.player_account.key();
+        emit!(PlayerActed {
+            table_name: game_session.table_name().to_string(),
+            round_id: game_session.round_id,
+            player: ctx.accounts.player_account.key(),
+            seat_index,
+            hand_index,
+            action: PlayerActionType::Surrender,
+            new_card: None,
+        });
 
-        let card_for_new_hand = {
-            let player_seat = &mut game_session.player_seats[seat_index as usize];
-            player_seat.hands[0].cards.pop().ok_or(ProgramError::InvalidInstructionData)?
-        };
-        
-        let mut new_hand = Hand::new(original_hand_token_mint, stake_for_new_hand);
-        new_hand.add_card(card_for_new_hand);
+        determine_next_player_or_transition_to_dealer(&mut game_session, &player_seat, ctx.accounts.clock.unix_timestamp)?;
+        Ok(())
+    }
 
-        let player_seat = &mut game_session.player_seats[seat_index as usize];
-        player_seat.hands.push(new_hand);
+    // --- 3.9.3. player_action_rescue ---
+    /// Спасение удвоения (Spanish 21): получив карту на удвоение, игрок забирает удвоение и отдает
+    /// первоначальную ставку. Доступно, пока ход остается на удвоенной руке; `player_action_stand` отказывается от спасения.
+    pub fn player_action_rescue<'info>(ctx: Context<'_, '_, '_, 'info, PlayerAction<'info>>, seat_index: u8, hand_index: u8) -> Result<()> {
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
+        let mut player_seat = ctx.accounts.player_seat.load_mut()?;
+        verify_player_turn(&game_session, &player_seat, ctx.accounts.player_account.as_ref(), seat_index, hand_index)?;
+        if !game_session.game_variant().allows_double_rescue() { return err!(TwentyOneError::RescueNotAllowed); }
+
+        let hand = player_seat.hands_mut().get_mut(hand_index as usize).ok_or(TwentyOneError::InvalidHandIndex)?;
+        if hand.status() != HandStatus::DoubledAndStood { return err!(TwentyOneError::RescueNotAllowed); }
+        hand.set_status(HandStatus::Rescued);
 
         emit!(PlayerActed {
-            table_name: table_name_for_event,
-            player: player_key_for_event,
+            table_name: game_session.table_name().to_string(),
+            round_id: game_session.round_id,
+            player: ctx.accounts.player_account.key(),
             seat_index,
             hand_index,
-            action: PlayerActionType::Split,
+            action: PlayerActionType::Rescue,
             new_card: None,
         });
 
-        for hand_idx_to_deal in 0..2 {
-             let card_dealt = game_session.draw_card()?;
-             let hand = game_session.player_seats[seat_index as usize].hands.get_mut(hand_idx_to_deal).unwrap();
-             hand.add_card(card_dealt);
+        determine_next_player_or_transition_to_dealer(&mut game_session, &player_seat, ctx.accounts.clock.unix_timestamp)?;
+        Ok(())
+    }
 
-             if hand.cards[0].is_ace() { hand.status = HandStatus::Stood; } 
-             else { hand.update_status_after_card_drawn(); }
+    // --- 3.9.4. player_action_switch ---
+    /// Обмен (Blackjack Switch): игрок с несколькими боксами с равными ставками меняет местами вторые карты двух рук.
+    /// Обмен возможен в свой ход до первого действия или в окне сразу после раздачи, пока место еще не ходило:
+    /// так поменять карты могут и места, у которых все боксы — блэкджеки. Обмен может вернуть ход на руку,
+    /// которая до него была блэкджеком; тогда место встает в очередь хода.
+    pub fn player_action_switch<'info>(
+        ctx: Context<'_, '_, '_, 'info, PlayerAction<'info>>,
+        seat_index: u8,
+        first_hand_index: u8,
+        second_hand_index: u8,
+    ) -> Result<()> {
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
+        let mut player_seat = ctx.accounts.player_seat.load_mut()?;
+        let now = ctx.accounts.clock.unix_timestamp;
+        if !game_session.game_variant().allows_switch() { return err!(TwentyOneError::SwitchNotAllowed); }
+
+        let is_own_turn = game_session.game_state() == GameState::PlayerTurns
+            && game_session.current_turn_seat_index() == Some(seat_index);
+        if is_own_turn {
+            let current_hand_index = game_session.current_turn_hand_index().ok_or(TwentyOneError::WrongHandForTurn)?;
+            verify_player_turn(&game_session, &player_seat, ctx.accounts.player_account.as_ref(), seat_index, current_hand_index)?;
+        } else {
+            verify_player_at_seat(&player_seat, ctx.accounts.player_account.as_ref(), seat_index)?;
+            if game_session.round_seats & (1 << seat_index) == 0 || !game_session.is_switch_window_open(now) {
+                return err!(TwentyOneError::SwitchNotAllowed);
+            }
         }
-        
-        let player_seat = &mut game_session.player_seats[seat_index as usize];
-        if player_seat.hands[0].status != HandStatus::Playing {
-            determine_next_player_or_transition_to_dealer(game_session, ctx.accounts.clock.unix_timestamp)?;
+
+        player_seat.switch_second_cards(first_hand_index as usize, second_hand_index as usize)?;
+
+        emit!(CardsSwitched {
+            table_name: game_session.table_name().to_string(),
+            round_id: game_session.round_id,
+            player: ctx.accounts.player_account.key(),
+            seat_index,
+            first_hand_index,
+            second_hand_index,
+            first_hand: player_seat.hands()[first_hand_index as usize].cards(),
+            second_hand: player_seat.hands()[second_hand_index as usize].cards(),
+        });
+
+        if is_own_turn {
+            determine_next_player_or_transition_to_dealer(&mut game_session, &player_seat, now)?;
         } else {
-            game_session.current_turn_start_timestamp = Some(ctx.accounts.clock.unix_timestamp);
+            match player_seat.get_first_active_hand_index() {
+                Some(first_hand) => {
+                    game_session.set_seat_to_act(seat_index, first_hand as u8);
+                    // Все остальные места уже походили: ход возвращается к игрокам.
+                    if game_session.game_state() == GameState::DealerTurn {
+                        game_session.set_game_state(GameState::PlayerTurns);
+                        game_session.set_current_turn(seat_index, first_hand as u8, now);
+                    }
+                }
+                None => game_session.clear_seat_to_act(seat_index),
+            }
         }
         Ok(())
     }
 
     // --- 3.10. dealer_play_turn (ЗАЩИЩЕНАЯ ВЕРСИЯ) ---
-    pub fn dealer_play_turn<'info>(ctx: Context<'_, '_, '_, 'info, BackendAuthorizedAction<'info>>) -> Result<()> {
-        let game_session = &mut ctx.accounts.game_session_account;
-        if game_session.game_state != GameState::DealerTurn { return err!(TwentyOneError::NotDealerTurnState); }
+    /// Бэкенд раскрывает закрытую карту дилера (`hole_card`), после чего дилер добирает карты до 17
+    /// (берет на мягких 17). В `reveal` бэкенд раскрывает ровно столько карт, сколько нужно дилеру;
+    /// если дилеру карты не нужны, передается пустой диапазон. В Double Exposure закрытой карты нет
+    /// и `hole_card` передается пустым. За столом Blackjack Switch дилер ждет конца окна обмена,
+    /// пока какое-то место еще может поменять карты.
+    /// `remaining_accounts`: аккаунты мест, участвующих в раунде, по возрастанию номера места.
+    pub fn dealer_play_turn<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BackendAuthorizedAction<'info>>,
+        hole_card: CardRangeReveal,
+        reveal: CardRangeReveal,
+    ) -> Result<()> {
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
+        if game_session.game_state() != GameState::DealerTurn { return err!(TwentyOneError::NotDealerTurnState); }
+        let switch_window_open = game_session.is_switch_window_open(ctx.accounts.clock.unix_timestamp);
+
+        if game_session.dealer_hand.has_unrevealed_card() {
+            game_session.reveal_dealer_hole_card(&hole_card)?;
+        } else if !hole_card.cards.is_empty() {
+            // Обе карты дилера уже открыты (Double Exposure).
+            return err!(TwentyOneError::CardRevealCountMismatch);
+        }
+
+        let mut revealed_cards = if reveal.cards.is_empty() {
+            Vec::new()
+        } else {
+            game_session.draw_cards(&reveal)?
+        }.into_iter();
+
+        // Если у всех игроков перебор или блэкджек, дилер только открывает карту и не добирает.
+        let mut dealer_must_draw = false;
+        for loader in load_seat_accounts(ctx.remaining_accounts, &ctx.accounts.game_session_account.key(), game_session.round_seats)? {
+            let player_seat = loader.load()?;
+            // Место, еще не решившее об обмене, может успеть поменять карты до конца окна.
+            if switch_window_open && player_seat.can_switch() {
+                return err!(TwentyOneError::SwitchWindowOpen);
+            }
+            dealer_must_draw |= player_seat.hands().iter()
+                .any(|hand| matches!(hand.status(), HandStatus::Stood | HandStatus::DoubledAndStood));
+        }
 
         loop {
             let (score, is_soft) = game_session.dealer_hand.calculate_score();
             
             if score > 21 {
-                game_session.dealer_hand.status = HandStatus::Busted;
+                game_session.dealer_hand.set_status(HandStatus::Busted);
                 break;
             }
-            if score > 17 {
-                game_session.dealer_hand.status = HandStatus::Stood;
+            if !dealer_must_draw || score > 17 || (score == 17 && !is_soft) {
+                game_session.dealer_hand.set_status(HandStatus::Stood);
                 break;
             }
-            if score == 17 {
-                if is_soft {
-                    let card = game_session.draw_card()?;
-                    game_session.dealer_hand.add_card(card);
-                } else {
-                    game_session.dealer_hand.status = HandStatus::Stood;
-                    break;
-                }
-            } else {
-                let card = game_session.draw_card()?;
-                game_session.dealer_hand.add_card(card);
-            }
+            let card = revealed_cards.next().ok_or(TwentyOneError::CardRevealCountMismatch)?;
+            game_session.dealer_hand.add_card(card)?;
+        }
+        if revealed_cards.next().is_some() {
+            return err!(TwentyOneError::CardRevealCountMismatch);
         }
         
-        game_session.game_state = GameState::RoundOver;
+        game_session.set_game_state(GameState::RoundOver);
         Ok(())
     }
     
     // --- 3.11. dealer_prepare_to_close ---
     pub fn dealer_prepare_to_close<'info>(ctx: Context<'_, '_, '_, 'info, DealerAction<'info>>) -> Result<()> {
-        let game_session = &mut ctx.accounts.game_session_account;
-        verify_dealer_signer(game_session, &ctx.accounts.dealer)?;
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
+        verify_dealer_signer(&game_session, &ctx.accounts.dealer)?;
         
-        game_session.closing_down = true;
+        game_session.set_closing_down(true);
 
         emit!(TableClosingDown {
-            table_name: game_session.table_name.clone(),
+            table_name: game_session.table_name().to_string(),
+            round_id: game_session.round_id,
         });
 
         Ok(())
@@ -608,9 +1405,23 @@ pub mod program_21 {
     // --- 3.11 & 3.12: ЕДИНАЯ ФУНКЦИЯ ФИНАЛИЗАЦИИ РАУНДА ---
     /// Выполняет полную финализацию раунда: проверяет результаты, сверяет цены и выплачивает выигрыши.
     /// Эта функция атомарно выполняет все действия, которые раньше были разделены на `resolve_round` и `execute_payouts`.
+    /// `remaining_accounts`: сначала аккаунты мест раунда по возрастанию номера места,
+    /// затем аккаунты выплат и ставок зрителей, на которые ссылаются индексы в `instructions`,
+    /// `bet_behind_instructions` и `side_bet_instructions`. Рассчитанные аккаунты `BetBehind` закрываются в пользу зрителя.
+    /// За турнирным столом выплата руки — фишки: `payout_amount_ui` равна ожидаемой выплате и зачисляется
+    /// на стек участника в `Tournament`, индексы токен-аккаунтов и оракула не используются.
+    /// Если стол платит выигрыши в USDC, `payout_amount_ui` — только возврат ставки в токене ставки,
+    /// а выигрыш (`usdc_payout_amount`) переводится из USDC-эскроу залога дилера.
+    /// Выплаты по ставкам в нативном SOL собираются из WSOL-эскроу во временный `sol_unwrap_account`,
+    /// который закрывается в пользу бэкенда, а тот переводит лампорты получателям. Для таких выплат
+    /// `player_token_account_index` указывает на системный аккаунт получателя.
+    /// Если `player_token_account_index` указывает на `PlayerVault` получателя, выплата зачисляется на его баланс
+    /// без перевода: токены хранилищ лежат на эскроу ставок стола.
     pub fn finalize_round<'info>(
-        ctx: Context<'_, '_, '_, 'info, FinalizeRound<'info>>,
+        ctx: Context<'_, '_, 'info, 'info, FinalizeRound<'info>>,
         instructions: Vec<FinalizeInstruction>,
+        bet_behind_instructions: Vec<BetBehindInstruction>,
+        side_bet_instructions: Vec<SideBetInstruction>,
         next_shuffle_commit: Option<[u8; 32]>
     ) -> Result<()> {
         // --- ФАЗА 0: ИЗВЛЕЧЕНИЕ ДАННЫХ ВО ИЗБЕЖАНИЕ КОНФЛИКТА ЗАИМСТВОВАНИЙ ---
@@ -618,8 +1429,10 @@ pub mod program_21 {
         // мы сначала извлекаем все необходимые данные, которые не будут меняться (immutable borrows).
         // Мы клонируем эти данные, чтобы они имели независимое время жизни.
         let game_session_info = ctx.accounts.game_session_account.to_account_info();
-        let table_name_clone = ctx.accounts.game_session_account.table_name.clone();
-        let bump_seed_copy = [ctx.accounts.game_session_account.bump];
+        let (table_name_clone, bump_seed_copy) = {
+            let game_session = ctx.accounts.game_session_account.load()?;
+            (game_session.table_name().to_string(), [game_session.bump])
+        };
 
         let signer_seeds = &[&[
             NORMALIZED_TABLE_NAME_PREFIX,
@@ -628,72 +1441,125 @@ pub mod program_21 {
         ][..]];
 
         let token_program_info = ctx.accounts.token_program.to_account_info();
+        let token_2022_program_info = ctx.accounts.token_2022_program.as_ref().map(|program| program.to_account_info());
+        let usdc_escrow_info = ctx.accounts.usdc_escrow.as_ref().map(|escrow| escrow.to_account_info());
+        let usdc_mint_info = ctx.accounts.usdc_mint.as_ref().map(|mint| mint.to_account_info());
+        let usdc_price_feed = ctx.accounts.usdc_price_feed.as_ref().map(|feed| feed.to_account_info());
+        let backend_signer_info = ctx.accounts.backend_signer.to_account_info();
+        let system_program_info = ctx.accounts.system_program.to_account_info();
+        let sol_unwrap_info = ctx.accounts.sol_unwrap_account.as_ref().map(|account| account.to_account_info());
+        let native_mint_info = ctx.accounts.native_mint.as_ref().map(|mint| mint.to_account_info());
+        let sol_unwrap_bump = ctx.bumps.sol_unwrap_account;
         // Клонируем срез remaining_accounts, чтобы получить полностью независимый вектор Vec.
         let remaining_accounts = ctx.remaining_accounts.to_vec();
 
         // Теперь, когда все immutable borrows завершены, мы можем безопасно взять mutable borrow.
-        let game_session = &mut ctx.accounts.game_session_account;
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
 
-        if game_session.game_state != GameState::RoundOver {
+        if game_session.game_state() != GameState::RoundOver {
             return err!(TwentyOneError::NotRoundOverState);
         }
 
+        let seat_loaders = load_seat_accounts(
+            ctx.remaining_accounts,
+            &ctx.accounts.game_session_account.key(),
+            game_session.round_seats,
+        )?;
+        let mut round_seats = seat_loaders.iter().map(|loader| loader.load_mut()).collect::<Result<Vec<_>>>()?;
+
         // --- ФАЗА 1: ВЕРИФИКАЦИЯ И РАСЧЕТ ---
         struct CalculatedPayout {
             player_token_account_index: usize,
             escrow_account_index: usize,
+            mint_account_index: usize,
             amount: u64,
+            unwrap_sol: bool,
+            recipient: Pubkey,
+            token_mint: Pubkey,
         }
         let mut calculated_payouts: Vec<CalculatedPayout> = Vec::with_capacity(instructions.len());
+        // Выигрыши в USDC из залога дилера: (индекс USDC-аккаунта получателя, сумма).
+        let mut usdc_payouts: Vec<(usize, u64)> = Vec::new();
         let mut event_results: Vec<PlayerHandResult> = Vec::with_capacity(instructions.len());
 
         let dealer_final_score = game_session.dealer_hand.calculate_score().0;
-        let dealer_is_busted = game_session.dealer_hand.status == HandStatus::Busted;
+        let dealer_is_busted = game_session.dealer_hand.status() == HandStatus::Busted;
         let dealer_has_blackjack = game_session.dealer_hand.is_blackjack();
+        let game_variant = game_session.game_variant();
+        let is_tournament = game_session.is_tournament();
+        if is_tournament && ctx.accounts.tournament.is_none() {
+            return err!(TwentyOneError::TournamentAccountRequired);
+        }
+        let mut chip_payouts: Vec<(Pubkey, u64)> = Vec::new();
+        let settles_winnings_in_usdc = game_session.settles_winnings_in_usdc() && !is_tournament;
 
         for instruction in instructions.iter() {
-            let player_seat = game_session.player_seats.get(instruction.seat_index as usize)
+            let player_seat = round_seats.iter()
+                .find(|seat| seat.seat_index == instruction.seat_index)
                 .ok_or(TwentyOneError::InvalidSeatIndex)?;
-            if player_seat.player_pubkey.unwrap() != instruction.player {
+            if player_seat.player_pubkey() != Some(instruction.player) {
                 return err!(TwentyOneError::PlayerMismatch);
             }
-            let hand = player_seat.hands.get(instruction.hand_index as usize)
+            let hand = player_seat.hands().get(instruction.hand_index as usize)
                 .ok_or(TwentyOneError::InvalidHandIndex)?;
             
-            if hand.cards != instruction.hand_cards {
+            if hand.cards() != instruction.hand_cards {
                 return err!(TwentyOneError::HandCardsMismatch);
             }
 
-            let bet_usd_value: u128 = player_seat.current_bet_usd_value as u128;
+            let unwrap_sol = hand.token_mint_for_bet == NATIVE_SOL_MINT_PUBKEY;
+            let bet_usd_value: u128 = hand.bet_usd_value as u128;
             let effective_bet_usd = (bet_usd_value * hand.bet_multiplier_x100 as u128) / 100;
+            let free_stake_usd = (bet_usd_value * hand.free_stake_x100 as u128) / 100;
+            // Первоначальная ставка бокса лежит на первой его руке, остальные руки получены сплитом.
+            let is_first_hand_of_spot = player_seat.hands().iter()
+                .position(|spot_hand| spot_hand.spot_index == hand.spot_index) == Some(instruction.hand_index as usize);
+            let original_stake_usd = if is_first_hand_of_spot { bet_usd_value } else { 0 };
             
-            let (_expected_payout_usd, expected_outcome) = calculate_expected_usd_return(
-                hand, effective_bet_usd, dealer_final_score, dealer_is_busted, dealer_has_blackjack
+            let (expected_payout_usd, expected_outcome) = calculate_expected_usd_return(
+                hand, effective_bet_usd, original_stake_usd, free_stake_usd, dealer_final_score, dealer_is_busted, dealer_has_blackjack, game_variant
             )?;
 
             if instruction.outcome != expected_outcome {
                 return err!(TwentyOneError::OutcomeMismatch);
             }
 
-            if instruction.payout_amount_ui > 0 {
-                let pyth_account = &ctx.remaining_accounts[instruction.pyth_feed_index as usize];
-                if *pyth_account.owner != PYTH_RECEIVER_PROGRAM_ID {
-                    return err!(TwentyOneError::InvalidPriceFeedOwner);
-                }
-                let price_feed = PriceFeed::try_from_slice(&pyth_account.data.borrow())
-                    .map_err(|_| error!(TwentyOneError::PriceFeedStale))?;
-                let pyth_price = price_feed.get_price_unchecked();
-
-                let price_diff = (pyth_price.price - instruction.expected_price).abs();
-                let max_diff = (instruction.expected_price * PAYOUT_PRICE_SLIPPAGE_BPS as i64) / 10000;
-                if price_diff > max_diff {
+            if is_tournament {
+                // Турнирная ставка сделана фишками: выплата известна точно и зачисляется без оракула.
+                if instruction.payout_amount_ui as u128 != expected_payout_usd {
                     return err!(TwentyOneError::PayoutCalculationMismatch);
                 }
+                chip_payouts.push((instruction.player, instruction.payout_amount_ui));
+            } else if settles_winnings_in_usdc {
+                // Собственная ставка игрока без доли заведения (Free Bet).
+                let stake_x100 = (hand.bet_multiplier_x100 - hand.free_stake_x100) as u128;
+                let stake_amount = u64::try_from(hand.original_bet_amount as u128 * stake_x100 / 100)
+                    .map_err(|_| error!(TwentyOneError::ArithmeticOverflow))?;
+                verify_usdc_settlement(
+                    usdc_price_feed.as_ref(), expected_payout_usd, effective_bet_usd - free_stake_usd, stake_amount,
+                    instruction.payout_amount_ui, instruction.usdc_payout_amount,
+                )?;
+                calculated_payouts.push(CalculatedPayout {
+                    player_token_account_index: instruction.player_token_account_index as usize,
+                    escrow_account_index: instruction.escrow_account_index as usize,
+                    mint_account_index: instruction.mint_account_index as usize,
+                    amount: instruction.payout_amount_ui,
+                    unwrap_sol,
+                    recipient: instruction.player,
+                    token_mint: hand.token_mint_for_bet,
+                });
+                usdc_payouts.push((instruction.usdc_token_account_index as usize, instruction.usdc_payout_amount));
+            } else if instruction.payout_amount_ui > 0 {
+                verify_payout_price(&ctx.remaining_accounts[instruction.pyth_feed_index as usize], instruction.expected_price)?;
 
                 calculated_payouts.push(CalculatedPayout {
                     player_token_account_index: instruction.player_token_account_index as usize,
                     escrow_account_index: instruction.escrow_account_index as usize,
+                    mint_account_index: instruction.mint_account_index as usize,
                     amount: instruction.payout_amount_ui,
+                    unwrap_sol,
+                    recipient: instruction.player,
+                    token_mint: hand.token_mint_for_bet,
                 });
             }
 
@@ -701,64 +1567,359 @@ pub mod program_21 {
                 player: instruction.player,
                 seat_index: instruction.seat_index,
                 hand_index: instruction.hand_index,
-                hand_cards: hand.cards.clone(),
+                hand_cards: hand.cards(),
                 hand_score: hand.calculate_score().0,
                 outcome: expected_outcome,
                 payout: instruction.payout_amount_ui,
             });
         }
 
+        // Ставки зрителей получают исход первой руки бокса, за которым стоят, от своей ставки:
+        // удвоение и сплит игрока их не затрагивают.
+        let mut bet_behind_results: Vec<BetBehindResult> = Vec::with_capacity(bet_behind_instructions.len());
+        let mut settled_bets_behind: Vec<(Account<'info, BetBehind>, &AccountInfo<'info>, u8)> = Vec::with_capacity(bet_behind_instructions.len());
+
+        for instruction in bet_behind_instructions.iter() {
+            let bet_behind_info = ctx.remaining_accounts.get(instruction.bet_behind_index as usize)
+                .ok_or(TwentyOneError::BetBehindMismatch)?;
+            let backer_info = ctx.remaining_accounts.get(instruction.backer_account_index as usize)
+                .ok_or(TwentyOneError::BetBehindMismatch)?;
+            if settled_bets_behind.iter().any(|(settled, _, _)| settled.key() == bet_behind_info.key()) {
+                return err!(TwentyOneError::BetBehindMismatch);
+            }
+
+            let bet_behind = Account::<BetBehind>::try_from(bet_behind_info)?;
+            if bet_behind.game_session != ctx.accounts.game_session_account.key()
+                || bet_behind.round_id != game_session.round_id
+                || bet_behind.backer != backer_info.key()
+            {
+                return err!(TwentyOneError::BetBehindMismatch);
+            }
+
+            let hand = round_seats.iter()
+                .find(|seat| seat.seat_index == bet_behind.seat_index)
+                .and_then(|seat| seat.hands().iter().find(|hand| hand.spot_index == bet_behind.spot_index))
+                .ok_or(TwentyOneError::BetBehindMismatch)?;
+
+            let (expected_payout_usd, expected_outcome) = calculate_expected_usd_return(
+                hand, bet_behind.bet_usd_value as u128, bet_behind.bet_usd_value as u128, 0, dealer_final_score, dealer_is_busted, dealer_has_blackjack, game_variant
+            )?;
+            if instruction.outcome != expected_outcome {
+                return err!(TwentyOneError::OutcomeMismatch);
+            }
+
+            let unwrap_sol = bet_behind.token_mint == NATIVE_SOL_MINT_PUBKEY;
+            if settles_winnings_in_usdc {
+                verify_usdc_settlement(
+                    usdc_price_feed.as_ref(), expected_payout_usd, bet_behind.bet_usd_value as u128, bet_behind.amount,
+                    instruction.payout_amount_ui, instruction.usdc_payout_amount,
+                )?;
+                calculated_payouts.push(CalculatedPayout {
+                    player_token_account_index: instruction.backer_token_account_index as usize,
+                    escrow_account_index: instruction.escrow_account_index as usize,
+                    mint_account_index: instruction.mint_account_index as usize,
+                    amount: instruction.payout_amount_ui,
+                    unwrap_sol,
+                    recipient: bet_behind.backer,
+                    token_mint: bet_behind.token_mint,
+                });
+                usdc_payouts.push((instruction.usdc_token_account_index as usize, instruction.usdc_payout_amount));
+            } else if instruction.payout_amount_ui > 0 {
+                verify_payout_price(&ctx.remaining_accounts[instruction.pyth_feed_index as usize], instruction.expected_price)?;
+
+                calculated_payouts.push(CalculatedPayout {
+                    player_token_account_index: instruction.backer_token_account_index as usize,
+                    escrow_account_index: instruction.escrow_account_index as usize,
+                    mint_account_index: instruction.mint_account_index as usize,
+                    amount: instruction.payout_amount_ui,
+                    unwrap_sol,
+                    recipient: bet_behind.backer,
+                    token_mint: bet_behind.token_mint,
+                });
+            }
+
+            bet_behind_results.push(BetBehindResult {
+                backer: bet_behind.backer,
+                seat_index: bet_behind.seat_index,
+                spot_index: bet_behind.spot_index,
+                outcome: expected_outcome,
+                payout: instruction.payout_amount_ui,
+            });
+            let seat_index = bet_behind.seat_index;
+            settled_bets_behind.push((bet_behind, backer_info, seat_index));
+        }
+
+        // Каждая ставка зрителя, учтенная на месте, должна быть рассчитана в этом раунде.
+        for player_seat in round_seats.iter() {
+            let settled = settled_bets_behind.iter().filter(|(_, _, seat_index)| *seat_index == player_seat.seat_index).count();
+            if settled != player_seat.bets_behind as usize {
+                return err!(TwentyOneError::BetsBehindNotSettled);
+            }
+        }
+
+        // Комбинации побочных ставок определены при раздаче: проверяется только стоимость выплаты по пейтейблу стола.
+        let side_bet_paytable = game_session.side_bet_paytable();
+        let mut side_bet_results: Vec<SideBetResult> = Vec::with_capacity(side_bet_instructions.len());
+        let mut settled_side_bets: Vec<(u8, u8)> = Vec::with_capacity(side_bet_instructions.len());
+
+        for instruction in side_bet_instructions.iter() {
+            let side_bet_key = (instruction.seat_index, instruction.side_bet_index);
+            if settled_side_bets.contains(&side_bet_key) {
+                return err!(TwentyOneError::SideBetsNotSettled);
+            }
+            let player_seat = round_seats.iter()
+                .find(|seat| seat.seat_index == instruction.seat_index)
+                .ok_or(TwentyOneError::InvalidSeatIndex)?;
+            let side_bet = player_seat.side_bets().get(instruction.side_bet_index as usize)
+                .ok_or(TwentyOneError::SideBetsNotSettled)?;
+
+            let odds = side_bet_paytable.odds(side_bet.outcome());
+            let unwrap_sol = side_bet.token_mint == NATIVE_SOL_MINT_PUBKEY;
+            if settles_winnings_in_usdc {
+                // Выплата: возврат ставки плюс выигрыш "к 1" по пейтейблу, при проигрыше 0.
+                let expected_payout_usd = if odds == 0 { 0 } else { side_bet.bet_usd_value as u128 * (odds as u128 + 1) };
+                verify_usdc_settlement(
+                    usdc_price_feed.as_ref(), expected_payout_usd, side_bet.bet_usd_value as u128, side_bet.amount,
+                    instruction.payout_amount_ui, instruction.usdc_payout_amount,
+                )?;
+                calculated_payouts.push(CalculatedPayout {
+                    player_token_account_index: instruction.player_token_account_index as usize,
+                    escrow_account_index: instruction.escrow_account_index as usize,
+                    mint_account_index: instruction.mint_account_index as usize,
+                    amount: instruction.payout_amount_ui,
+                    unwrap_sol,
+                    recipient: player_seat.player_pubkey().unwrap_or_default(),
+                    token_mint: side_bet.token_mint,
+                });
+                usdc_payouts.push((instruction.usdc_token_account_index as usize, instruction.usdc_payout_amount));
+            } else if odds == 0 {
+                if instruction.payout_amount_ui != 0 {
+                    return err!(TwentyOneError::PayoutCalculationMismatch);
+                }
+            } else {
+                // Выплата: возврат ставки плюс выигрыш "к 1" по пейтейблу.
+                let expected_payout_usd = side_bet.bet_usd_value
+                    .checked_mul(odds as u64 + 1)
+                    .ok_or(TwentyOneError::ArithmeticOverflow)?;
+                verify_bet_usd_value(&ctx.remaining_accounts[instruction.pyth_feed_index as usize], instruction.payout_amount_ui, expected_payout_usd)?;
+
+                calculated_payouts.push(CalculatedPayout {
+                    player_token_account_index: instruction.player_token_account_index as usize,
+                    escrow_account_index: instruction.escrow_account_index as usize,
+                    mint_account_index: instruction.mint_account_index as usize,
+                    amount: instruction.payout_amount_ui,
+                    unwrap_sol,
+                    recipient: player_seat.player_pubkey().unwrap_or_default(),
+                    token_mint: side_bet.token_mint,
+                });
+            }
+
+            side_bet_results.push(SideBetResult {
+                player: player_seat.player_pubkey().unwrap_or_default(),
+                seat_index: instruction.seat_index,
+                spot_index: side_bet.spot_index,
+                kind: side_bet.kind(),
+                outcome: side_bet.outcome(),
+                payout: instruction.payout_amount_ui,
+            });
+            settled_side_bets.push(side_bet_key);
+        }
+
+        for player_seat in round_seats.iter() {
+            let settled = settled_side_bets.iter().filter(|(seat_index, _)| *seat_index == player_seat.seat_index).count();
+            if settled != player_seat.side_bets().len() {
+                return err!(TwentyOneError::SideBetsNotSettled);
+            }
+        }
+
         // --- ФАЗА 2: ОБНОВЛЕНИЕ СОСТОЯНИЯ И ИСПОЛНЕНИЕ ---
 
+        // Руку дилера сохраняем до сброса, иначе в событие и архив попадет пустая рука.
+        let dealer_final_cards = game_session.dealer_hand.cards();
+
+        if let Some(round_record) = ctx.accounts.round_record.as_mut() {
+            round_record.game_session = ctx.accounts.game_session_account.key();
+            round_record.round_id = game_session.round_id;
+            round_record.shoe_id = game_session.shoe_id;
+            round_record.deck_commitment = game_session.deck_commitment;
+            let shoe = game_session.active_shoe().unwrap_or_default();
+            round_record.shuffle_secret_commitment = shoe.secret_commitment;
+            round_record.shuffle_entropy = shoe.entropy;
+            round_record.dealer_hand = dealer_final_cards.clone();
+            round_record.dealer_score = dealer_final_score;
+            round_record.results = event_results.clone();
+            round_record.bump = ctx.bumps.round_record.ok_or(ProgramError::InvalidSeeds)?;
+        }
+
+        // Раунд турнира: выплаты зачисляются на стеки, после последнего раунда турнир ждет `finalize_tournament`.
+        if let Some(tournament) = ctx.accounts.tournament.as_mut().filter(|_| is_tournament) {
+            for (player, chips) in chip_payouts {
+                tournament.credit_chips(&player, chips)?;
+            }
+            if tournament.status == TournamentStatus::Running {
+                tournament.rounds_played += 1;
+                if tournament.rounds_played >= tournament.total_rounds {
+                    tournament.status = TournamentStatus::Finished;
+                }
+            }
+        }
+
+        for player_seat in round_seats.iter_mut() {
+            player_seat.reset_for_new_round();
+        }
+        drop(round_seats);
         game_session.reset_hands_for_new_round();
-        if !game_session.closing_down {
-            game_session.game_state = GameState::AcceptingBets;
+        if !game_session.closing_down() {
+            game_session.set_game_state(GameState::AcceptingBets);
         }
 
         emit!(RoundFinished {
-            table_name: game_session.table_name.clone(),
-            dealer_hand: game_session.dealer_hand.cards.clone(),
+            table_name: game_session.table_name().to_string(),
+            round_id: game_session.round_id,
+            dealer_hand: dealer_final_cards,
             dealer_score: dealer_final_score,
             results: event_results,
+            bets_behind: bet_behind_results,
+            side_bets: side_bet_results,
         });
 
+        // --- ФАЗА "COMMIT" ДЛЯ СЛЕДУЮЩЕЙ ПЕРЕТАСОВКИ ---
+        if game_session.needs_shuffle() {
+            // Для следующего раунда потребуется перетасовка, поэтому бэкенд ОБЯЗАН предоставить коммит sha256(secret).
+            let commit = next_shuffle_commit.ok_or(TwentyOneError::NextShuffleCommitmentRequired)?;
+            game_session.set_next_shuffle_commitment(Some(commit));
+            game_session.next_shuffle_commitment_slot = Clock::get()?.slot;
+        }
+
+        // Выигрыши в USDC платятся из залога дилера и не могут превысить его остаток.
+        for (_, amount) in usdc_payouts.iter() {
+            game_session.pay_from_collateral(*amount)?;
+        }
+
+        // Выплаты в хранилища игроков зачисляются на баланс, токены остаются на эскроу ставок стола.
+        let credits_vault = |payout: &CalculatedPayout| *remaining_accounts[payout.player_token_account_index].owner == crate::ID;
+        for payout in calculated_payouts.iter().filter(|payout| credits_vault(payout)) {
+            game_session.credit_vault_total(payout.token_mint, payout.amount)?;
+        }
+
+        // Аккаунт стола подписывает переводы ниже, поэтому его данные должны быть освобождены до CPI.
+        drop(game_session);
+
         // --- ФАЗА 3: ИСПОЛНЕНИЕ ВЫПЛАТ ---
 
-        for payout in calculated_payouts.iter() {
+        for payout in calculated_payouts.iter().filter(|payout| payout.amount > 0 && credits_vault(payout)) {
+            let mut player_vault = Account::<PlayerVault>::try_from(&ctx.remaining_accounts[payout.player_token_account_index])?;
+            if player_vault.game_session != game_session_info.key()
+                || player_vault.player != payout.recipient
+                || player_vault.token_mint != payout.token_mint
+            {
+                return err!(TwentyOneError::PlayerVaultMismatch);
+            }
+            player_vault.credit(payout.amount)?;
+            player_vault.exit(&crate::ID)?;
+        }
+
+        for payout in calculated_payouts.iter().filter(|payout| !payout.unwrap_sol && !credits_vault(payout)) {
             if payout.amount > 0 {
                 // Теперь мы используем наш собственный, независимый Vec.
-                let from_account_info = &remaining_accounts[payout.escrow_account_index];
-                let to_account_info = &remaining_accounts[payout.player_token_account_index];
-
-                let transfer_instruction = spl_token::instruction::transfer(
-                    token_program_info.key,
-                    from_account_info.key,
-                    to_account_info.key,
-                    game_session_info.key,
-                    &[],
+                let mint_account_info = &remaining_accounts[payout.mint_account_index];
+                transfer_tokens(
+                    token_program_for_mint(mint_account_info, &token_program_info, token_2022_program_info.as_ref())?,
+                    &remaining_accounts[payout.escrow_account_index],
+                    mint_account_info,
+                    &remaining_accounts[payout.player_token_account_index],
+                    &game_session_info,
                     payout.amount,
+                    signer_seeds,
                 )?;
+            }
+        }
 
-                // Вызываем `invoke_signed` с полным набором необходимых аккаунтов.
-                anchor_lang::solana_program::program::invoke_signed(
-                    &transfer_instruction,
-                    &[
-                        from_account_info.clone(),
-                        to_account_info.clone(),
-                        game_session_info.clone(),
-                        // ИСПРАВЛЕНИЕ: Добавляем token_program, он требуется для инструкции.
-                        token_program_info.clone(),
-                    ],
+        // Выплаты в SOL: WSOL собирается во временный аккаунт, он закрывается в пользу бэкенда,
+        // и бэкенд переводит лампорты получателям. Итоговый баланс бэкенда не меняется.
+        let sol_payouts: Vec<&CalculatedPayout> = calculated_payouts.iter()
+            .filter(|payout| payout.unwrap_sol && payout.amount > 0 && !credits_vault(payout))
+            .collect();
+        if !sol_payouts.is_empty() {
+            let sol_unwrap_info = sol_unwrap_info.ok_or(TwentyOneError::NativeSolAccountsRequired)?;
+            let native_mint_info = native_mint_info.ok_or(TwentyOneError::NativeSolAccountsRequired)?;
+            let sol_unwrap_bump = [sol_unwrap_bump.ok_or(TwentyOneError::NativeSolAccountsRequired)?];
+            let game_session_key = game_session_info.key();
+            let sol_unwrap_seeds = &[&[SOL_UNWRAP_SEED, game_session_key.as_ref(), &sol_unwrap_bump][..]];
+
+            // Посторонние лампорты на адресе не должны блокировать создание аккаунта.
+            let rent_lamports = Rent::get()?.minimum_balance(anchor_spl::token::TokenAccount::LEN);
+            let current_lamports = sol_unwrap_info.lamports();
+            if current_lamports == 0 {
+                anchor_lang::system_program::create_account(CpiContext::new_with_signer(system_program_info.clone(),
+                    anchor_lang::system_program::CreateAccount { from: backend_signer_info.clone(), to: sol_unwrap_info.clone() },
+                    sol_unwrap_seeds), rent_lamports, anchor_spl::token::TokenAccount::LEN as u64, token_program_info.key)?;
+            } else {
+                let top_up = rent_lamports.saturating_sub(current_lamports);
+                if top_up > 0 {
+                    anchor_lang::system_program::transfer(CpiContext::new(system_program_info.clone(),
+                        anchor_lang::system_program::Transfer { from: backend_signer_info.clone(), to: sol_unwrap_info.clone() }), top_up)?;
+                }
+                anchor_lang::system_program::allocate(CpiContext::new_with_signer(system_program_info.clone(),
+                    anchor_lang::system_program::Allocate { account_to_allocate: sol_unwrap_info.clone() },
+                    sol_unwrap_seeds), anchor_spl::token::TokenAccount::LEN as u64)?;
+                anchor_lang::system_program::assign(CpiContext::new_with_signer(system_program_info.clone(),
+                    anchor_lang::system_program::Assign { account_to_assign: sol_unwrap_info.clone() },
+                    sol_unwrap_seeds), token_program_info.key)?;
+            }
+            token_interface::initialize_account3(CpiContext::new(token_program_info.clone(), InitializeAccount3 {
+                    account: sol_unwrap_info.clone(),
+                    mint: native_mint_info.clone(),
+                    authority: game_session_info.clone(),
+                }))?;
+
+            for payout in sol_payouts.iter() {
+                transfer_tokens(
+                    &token_program_info,
+                    &remaining_accounts[payout.escrow_account_index],
+                    &native_mint_info,
+                    &sol_unwrap_info,
+                    &game_session_info,
+                    payout.amount,
                     signer_seeds,
                 )?;
             }
+
+            token_interface::close_account(CpiContext::new_with_signer(token_program_info.clone(), CloseAccount {
+                    account: sol_unwrap_info,
+                    destination: backend_signer_info.clone(),
+                    authority: game_session_info.clone(),
+                }, signer_seeds))?;
+
+            for payout in sol_payouts {
+                anchor_lang::system_program::transfer(CpiContext::new(system_program_info.clone(),
+                    anchor_lang::system_program::Transfer {
+                        from: backend_signer_info.clone(),
+                        to: remaining_accounts[payout.player_token_account_index].clone(),
+                    }), payout.amount)?;
+            }
         }
 
-        // --- ФАЗА "COMMIT" ДЛЯ СЛЕДУЮЩЕЙ ПЕРЕТАСОВКИ ---
-        if game_session.current_deck_index >= DECK_RESHUFFLE_THRESHOLD_INDEX {
-            // Для следующего раунда потребуется перетасовка, поэтому бэкенд ОБЯЗАН предоставить коммит.
-            let commit = next_shuffle_commit.ok_or(TwentyOneError::NextShuffleCommitmentRequired)?;
-            game_session.next_shuffle_commitment = Some(commit);
+        // Выигрыши в USDC переводятся из USDC-эскроу залога дилера.
+        for (usdc_token_account_index, amount) in usdc_payouts {
+            if amount == 0 {
+                continue;
+            }
+            let usdc_escrow_info = usdc_escrow_info.as_ref().ok_or(TwentyOneError::UsdcSettlementAccountsRequired)?;
+            let usdc_mint_info = usdc_mint_info.as_ref().ok_or(TwentyOneError::UsdcSettlementAccountsRequired)?;
+            transfer_tokens(
+                token_program_for_mint(usdc_mint_info, &token_program_info, token_2022_program_info.as_ref())?,
+                usdc_escrow_info,
+                usdc_mint_info,
+                &remaining_accounts[usdc_token_account_index],
+                &game_session_info,
+                amount,
+                signer_seeds,
+            )?;
+        }
+
+        for (bet_behind, backer_info, _) in settled_bets_behind {
+            bet_behind.close(backer_info.clone())?;
         }
 
         Ok(())
@@ -771,12 +1932,22 @@ pub mod program_21 {
         token_mint_to_withdraw: Pubkey,
         remaining_balances: Vec<TokenBalance>,
     ) -> Result<()> {
-        let game_session = &ctx.accounts.game_session_account;
+        if token_mint_to_withdraw != ctx.accounts.spl_token_mint.key() { return err!(TwentyOneError::BetTokenMintMismatch); }
+        let (dealer_locked_usdc_amount, table_name, bump) = {
+            let game_session = ctx.accounts.game_session_account.load()?;
+            game_session.verify_dealer_withdrawal(
+                &ctx.accounts.game_session_spl_escrow_account.key(),
+                &token_mint_to_withdraw,
+                ctx.accounts.game_session_spl_escrow_account.amount,
+                amount_to_withdraw_ui,
+            )?;
+            (game_session.dealer_locked_usdc_amount, game_session.table_name().to_string(), game_session.bump)
+        };
         
         // БЕЗ HashMap! Проверяем цены через прямое сравнение с Pyth
         let mut total_remaining_value_usd: u128 = 0;
         
-        for (_balance_idx, balance) in remaining_balances.iter().enumerate() {
+        for balance in remaining_balances.iter() {
             // Бэкенд присылает индекс соответствующего Pyth price feed
             let pyth_feed_index = balance.pyth_feed_index as usize;
             let pyth_account = &ctx.remaining_accounts[pyth_feed_index];
@@ -793,7 +1964,7 @@ pub mod program_21 {
             // Рассчитываем цену из Pyth
             let pyth_value_usd = (balance.amount as u128)
                 .checked_mul(pyth_price.price as u128).ok_or(TwentyOneError::ArithmeticOverflow)?
-                .checked_div(10u128.pow(pyth_price.expo.abs() as u32)).ok_or(TwentyOneError::ArithmeticOverflow)?;
+                .checked_div(10u128.pow(pyth_price.expo.unsigned_abs())).ok_or(TwentyOneError::ArithmeticOverflow)?;
             
             // Сверяем с готовой ценой от бэкенда (в рамках slippage)
             let backend_value_usd = balance.value_usd as u128;
@@ -811,7 +1982,7 @@ pub mod program_21 {
         }
         
         // ГЛАВНАЯ ПРОВЕРКА: остатки >= залога
-        if total_remaining_value_usd < game_session.dealer_locked_usdc_amount as u128 {
+        if total_remaining_value_usd < dealer_locked_usdc_amount as u128 {
             return err!(TwentyOneError::InsufficientBankValue);
         }
         
@@ -820,28 +1991,31 @@ pub mod program_21 {
             .checked_div(BASIS_POINTS_DIVISOR).ok_or(TwentyOneError::ArithmeticOverflow)?;
         let dealer_net_profit = amount_to_withdraw_ui.checked_sub(fee_amount).ok_or(TwentyOneError::ArithmeticOverflow)?;
 
-        let escrow_seeds = &[
-            BET_ESCROW_SEED,
-            game_session.to_account_info().key.as_ref(),
-            token_mint_to_withdraw.as_ref(),
-            &[ctx.bumps.game_session_spl_escrow_account]
-        ];
-        let signer_seeds = &[&escrow_seeds[..]];
+        // Эскроу принадлежит аккаунту стола, поэтому переводы подписывает PDA стола.
+        let signer_seeds: &[&[&[u8]]] = &[&[NORMALIZED_TABLE_NAME_PREFIX, table_name.as_bytes(), &[bump]]];
 
         if fee_amount > 0 {
-            anchor_spl::token::transfer( CpiContext::new_with_signer( ctx.accounts.token_program.to_account_info(), Transfer {
-                    from: ctx.accounts.game_session_spl_escrow_account.to_account_info(),
-                    to: ctx.accounts.owner_fee_spl_token_account.to_account_info(),
-                    authority: game_session.to_account_info(),
-                }, signer_seeds), fee_amount )?;
+            transfer_tokens(
+                &ctx.accounts.token_program.to_account_info(),
+                &ctx.accounts.game_session_spl_escrow_account.to_account_info(),
+                &ctx.accounts.spl_token_mint.to_account_info(),
+                &ctx.accounts.owner_fee_spl_token_account.to_account_info(),
+                &ctx.accounts.game_session_account.to_account_info(),
+                fee_amount,
+                signer_seeds,
+            )?;
         }
 
         if dealer_net_profit > 0 {
-            anchor_spl::token::transfer( CpiContext::new_with_signer( ctx.accounts.token_program.to_account_info(), Transfer {
-                    from: ctx.accounts.game_session_spl_escrow_account.to_account_info(),
-                    to: ctx.accounts.dealer_spl_token_account.to_account_info(),
-                    authority: game_session.to_account_info(),
-                }, signer_seeds), dealer_net_profit)?;
+            transfer_tokens(
+                &ctx.accounts.token_program.to_account_info(),
+                &ctx.accounts.game_session_spl_escrow_account.to_account_info(),
+                &ctx.accounts.spl_token_mint.to_account_info(),
+                &ctx.accounts.dealer_spl_token_account.to_account_info(),
+                &ctx.accounts.game_session_account.to_account_info(),
+                dealer_net_profit,
+                signer_seeds,
+            )?;
         }
         
         Ok(())
@@ -849,32 +2023,26 @@ pub mod program_21 {
 
     // --- 3.15. dealer_close_table (УПРОЩЕННАЯ ВЕРСИЯ) ---
     pub fn dealer_close_table<'info>(ctx: Context<'_, '_, '_, 'info, DealerCloseTable<'info>>) -> Result<()> {
-        let game_session = &ctx.accounts.game_session_account;
+        let game_session = ctx.accounts.game_session_account.load()?;
 
         // Базовые проверки состояния
-        if !(game_session.game_state == GameState::AcceptingBets || game_session.game_state == GameState::RoundOver) {
+        if !(game_session.game_state() == GameState::AcceptingBets || game_session.game_state() == GameState::RoundOver) {
             return err!(TwentyOneError::CannotCloseTableActiveGame);
         }
         
-        // Проверяем, что нет активных игроков
-        if game_session.player_seats.iter().any(|s| s.player_pubkey.is_some() && s.is_active_in_round) {
+        // Проверяем, что нет незавершенного раунда или турнира, в том числе ставок, сделанных до раздачи.
+        if game_session.seats_with_bets != 0 || game_session.round_seats != 0 || game_session.is_tournament() {
             return err!(TwentyOneError::CannotCloseTableActiveGame);
         }
 
-        // Проверяем, что escrow-счета пусты (опционально, для безопасности)
-        // Если фронтенд гарантирует это, то можно убрать эту проверку
-        for account_pair in ctx.remaining_accounts.chunks_exact(2) {
-            let escrow_account_info = &account_pair[0];
-            let escrow_data = escrow_account_info.try_borrow_data()?;
-            let escrow_account = TokenAccount::try_deserialize(&mut &escrow_data[..])?;
-            if escrow_account.amount > 0 {
-                return err!(TwentyOneError::TableHasActiveEscrow); // Добавьте эту ошибку в errors.rs
-            }
-            drop(escrow_data);
+        // Балансы хранилищ игроков учитываются самим столом: закрыть стол можно только после их вывода.
+        if game_session.has_vault_balances() {
+            return err!(TwentyOneError::TableHasVaultBalances);
         }
 
         emit!(TableClosed { 
-            table_name: game_session.table_name.clone(), 
+            table_name: game_session.table_name().to_string(), 
+            round_id: game_session.round_id,
             dealer: game_session.dealer 
         });
 
@@ -886,12 +2054,13 @@ pub mod program_21 {
 
     // --- 3.16. force_player_action ---
     pub fn force_player_action<'info>(ctx: Context<'_, '_, '_, 'info, ForcePlayerAction<'info>>, seat_index: u8, hand_index: u8, action: ForcedAction) -> Result<()> {
-        let game_session = &mut ctx.accounts.game_session_account;
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
+        let mut player_seat = ctx.accounts.player_seat.load_mut()?;
         let clock = &ctx.accounts.clock;
 
-        if game_session.game_state != GameState::PlayerTurns { return err!(TwentyOneError::NotPlayerTurnsState); }
+        if game_session.game_state() != GameState::PlayerTurns { return err!(TwentyOneError::NotPlayerTurnsState); }
         
-        let (current_seat, current_hand) = (game_session.current_turn_seat_index, game_session.current_turn_hand_index);
+        let (current_seat, current_hand) = (game_session.current_turn_seat_index(), game_session.current_turn_hand_index());
         
         // ИСПРАВЛЕНИЕ: Разделяем проверку на две, чтобы использовать существующие ошибки
         if current_seat != Some(seat_index) { 
@@ -901,47 +2070,37 @@ pub mod program_21 {
             return err!(TwentyOneError::WrongHandForTurn); 
         }
         
-        let start_time = game_session.current_turn_start_timestamp.ok_or(TwentyOneError::TurnTimerNotSet)?;
+        if game_session.pending_draw().is_some() { return err!(TwentyOneError::CardRevealPending); }
+        
+        let start_time = game_session.current_turn_start_timestamp().ok_or(TwentyOneError::TurnTimerNotSet)?;
         if clock.unix_timestamp <= start_time.checked_add(PLAYER_TURN_TIMEOUT_SECONDS).ok_or(TwentyOneError::ArithmeticOverflow)? {
              return err!(TwentyOneError::TurnTimeNotExpired);
         }
 
-        let player_pubkey = game_session.player_seats[seat_index as usize].player_pubkey.ok_or(TwentyOneError::SeatNotTaken)?;
-        let table_name_for_event = game_session.table_name.clone();
+        let player_pubkey = player_seat.player_pubkey().ok_or(TwentyOneError::SeatNotTaken)?;
+        let table_name_for_event = game_session.table_name().to_string();
+        let round_id = game_session.round_id;
         
         match action {
             ForcedAction::Hit => {
-                let new_card = game_session.draw_card()?;
-                let hand = game_session.player_seats[seat_index as usize].hands.get_mut(hand_index as usize)
+                let hand = player_seat.hands().get(hand_index as usize)
                     .ok_or(TwentyOneError::InvalidHandIndex)?;
 
-                if hand.status != HandStatus::Playing { return err!(TwentyOneError::HandActionOnFinalizedHand); }
-                
-                hand.add_card(new_card);
-                hand.update_status_after_card_drawn();
-
-                emit!(PlayerActed {
-                    table_name: table_name_for_event,
-                    player: player_pubkey,
-                    seat_index,
-                    hand_index,
-                    action: PlayerActionType::Hit,
-                    new_card: Some(new_card),
-                });
+                if hand.status() != HandStatus::Playing { return err!(TwentyOneError::HandActionOnFinalizedHand); }
+                if hand.is_full() { return err!(TwentyOneError::HandFull); }
 
-                if hand.status != HandStatus::Playing {
-                    determine_next_player_or_transition_to_dealer(game_session, clock.unix_timestamp)?;
-                }
+                // Карту раскрывает бэкенд в `reveal_pending_cards`, как и при обычном hit.
+                game_session.set_pending_draw(Some(PendingDraw::Hit));
             },
             ForcedAction::Stand => {
-                let hand = game_session.player_seats[seat_index as usize].hands.get_mut(hand_index as usize)
+                let hand = player_seat.hands_mut().get_mut(hand_index as usize)
                     .ok_or(TwentyOneError::InvalidHandIndex)?;
-                if hand.status != HandStatus::Playing { return err!(TwentyOneError::HandActionOnFinalizedHand); }
-                
-                hand.status = HandStatus::Stood;
+                // Просроченное решение о спасении удвоения (Spanish 21) означает отказ от него.
+                hand.stand()?;
 
                 emit!(PlayerActed {
                     table_name: table_name_for_event,
+                    round_id,
                     player: player_pubkey,
                     seat_index,
                     hand_index,
@@ -949,25 +2108,378 @@ pub mod program_21 {
                     new_card: None,
                 });
                 
-                determine_next_player_or_transition_to_dealer(game_session, clock.unix_timestamp)?;
+                determine_next_player_or_transition_to_dealer(&mut game_session, &player_seat, clock.unix_timestamp)?;
             },
             ForcedAction::Split => {
-                let hand = game_session.player_seats[seat_index as usize].hands.get_mut(hand_index as usize)
+                let hand = player_seat.hands_mut().get_mut(hand_index as usize)
                     .ok_or(TwentyOneError::InvalidHandIndex)?;
-                if hand.status != HandStatus::Playing { return err!(TwentyOneError::HandActionOnFinalizedHand); }
-                hand.status = HandStatus::Stood;
+                hand.stand()?;
                  emit!(PlayerActed {
                     table_name: table_name_for_event,
+                    round_id,
                     player: player_pubkey,
                     seat_index,
                     hand_index,
                     action: PlayerActionType::Stand,
                     new_card: None,
                 });
-                determine_next_player_or_transition_to_dealer(game_session, clock.unix_timestamp)?;
+                determine_next_player_or_transition_to_dealer(&mut game_session, &player_seat, clock.unix_timestamp)?;
             }
         }
         
         Ok(())
     }
+
+    // --- 3.17. migrate_game_session ---
+    /// Обновляет раскладку стола до `GameSession::VERSION` на месте.
+    /// Версия 0 (Borsh-формат до перехода на zero-copy) имеет тот же дискриминатор и распознается по размеру аккаунта.
+    /// Более поздние zero-copy версии только дописывают поля в конец и обновляются увеличением аккаунта.
+    /// Бэкенд доплачивает ренту, если новый размер аккаунта больше старого. Для версии 0 он также передает
+    /// эскроу залога и коммитмент первой перетасовки: открыто хранившийся шуз не переносится.
+    /// Конфиг авторизации должен быть обновлен раньше (`migrate_authority_config`).
+    pub fn migrate_game_session<'info>(
+        ctx: Context<'_, '_, '_, 'info, MigrateGameSession<'info>>,
+        first_shuffle_commitment: Option<[u8; 32]>,
+    ) -> Result<()> {
+        let game_session_info = ctx.accounts.game_session_account.to_account_info();
+
+        let (stored_version, legacy) = {
+            let data = game_session_info.try_borrow_data()?;
+            match GameSession::stored_version(&data) {
+                0 => (0, Some(LegacyGameSession::from_account_data(&data)?)),
+                GameSession::VERSION => return err!(TwentyOneError::GameSessionAlreadyMigrated),
+                version => (version, None),
+            }
+        };
+
+        top_up_rent_for_resize(
+            &game_session_info,
+            &ctx.accounts.backend_signer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            GameSession::CALCULATED_LEN,
+        )?;
+        game_session_info.resize(GameSession::CALCULATED_LEN)?;
+        match legacy {
+            Some(legacy) => {
+                let usdc_escrow = ctx.accounts.usdc_escrow.as_ref().ok_or(TwentyOneError::LegacyCollateralEscrowMismatch)?;
+                let (expected_escrow, escrow_bump) = Pubkey::find_program_address(
+                    &[BET_ESCROW_SEED, game_session_info.key.as_ref(), usdc_escrow.mint.as_ref()],
+                    ctx.program_id,
+                );
+                if usdc_escrow.key() != expected_escrow {
+                    return err!(TwentyOneError::LegacyCollateralEscrowMismatch);
+                }
+                let shuffle_commitment = first_shuffle_commitment.ok_or(TwentyOneError::NextShuffleCommitmentRequired)?;
+                GameSession::upgrade_from_v0(
+                    &mut game_session_info.try_borrow_mut_data()?,
+                    &legacy,
+                    expected_escrow,
+                    escrow_bump,
+                    shuffle_commitment,
+                    Clock::get()?.slot,
+                )?
+            }
+            None => GameSession::upgrade_appended_fields(&mut game_session_info.try_borrow_mut_data()?, stored_version)?,
+        }
+
+        Ok(())
+    }
+
+    // --- 3.18. migrate_authority_config ---
+    /// Обновляет конфиг авторизации версий 0 и 1 до `TableAuthorityConfig::VERSION`.
+    /// Подписывает текущий бэкенд, он же доплачивает ренту за увеличенный аккаунт и становится администратором;
+    /// список минтов залога после миграции пуст и задается через `set_collateral_mints`.
+    pub fn migrate_authority_config<'info>(ctx: Context<'_, '_, '_, 'info, MigrateAuthorityConfig<'info>>) -> Result<()> {
+        let authority_config_info = ctx.accounts.authority_config.to_account_info();
+
+        let legacy = {
+            let data = authority_config_info.try_borrow_data()?;
+            if data.len() < 8 || data[..8] != *TableAuthorityConfig::DISCRIMINATOR {
+                return err!(ErrorCode::AccountDiscriminatorMismatch);
+            }
+            if data.len() != LegacyTableAuthorityConfig::CALCULATED_LEN && data.len() != LegacyTableAuthorityConfig::V1_LEN {
+                return err!(TwentyOneError::AuthorityConfigAlreadyMigrated);
+            }
+            LegacyTableAuthorityConfig::deserialize(&mut &data[8..])?
+        };
+        if legacy.backend_authority != ctx.accounts.backend_signer.key() {
+            return err!(TwentyOneError::BackendSignerMismatch);
+        }
+
+        top_up_rent_for_resize(
+            &authority_config_info,
+            &ctx.accounts.backend_signer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            TableAuthorityConfig::CALCULATED_LEN,
+        )?;
+        authority_config_info.resize(TableAuthorityConfig::CALCULATED_LEN)?;
+
+        let upgraded = TableAuthorityConfig {
+            backend_authority: legacy.backend_authority,
+            bump: legacy.bump,
+            version: TableAuthorityConfig::VERSION,
+            admin: legacy.backend_authority,
+            collateral_mints: Vec::new(),
+        };
+        let mut data = authority_config_info.try_borrow_mut_data()?;
+        upgraded.serialize(&mut &mut data[8..])?;
+
+        Ok(())
+    }
+
+    // --- 3.18.1. set_collateral_mints ---
+    /// Заменяет список минтов залога. Уже созданные столы сохраняют свой залог до закрытия.
+    pub fn set_collateral_mints<'info>(ctx: Context<'_, '_, '_, 'info, SetCollateralMints<'info>>, collateral_mints: Vec<Pubkey>) -> Result<()> {
+        if !TableAuthorityConfig::is_valid_collateral_mints(&collateral_mints) {
+            return err!(TwentyOneError::InvalidCollateralMints);
+        }
+        ctx.accounts.authority_config.collateral_mints = collateral_mints;
+        Ok(())
+    }
+
+    // --- 3.19. create_tournament ---
+    /// Дилер открывает турнир за пустым столом. С этого момента стол принимает только ставки фишками
+    /// (`place_tournament_bet`) и снова становится обычным после `finalize_tournament`.
+    pub fn create_tournament<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreateTournament<'info>>,
+        buy_in_amount: u64,
+        starting_chips: u64,
+        total_rounds: u16,
+        max_entrants: u8,
+        payout_bps: Vec<u16>,
+    ) -> Result<()> {
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
+
+        if game_session.is_tournament() || game_session.occupied_seats != 0 || game_session.round_seats != 0 {
+            return err!(TwentyOneError::TournamentTable);
+        }
+        if buy_in_amount == 0
+            || starting_chips == 0
+            || total_rounds == 0
+            || max_entrants < 2
+            || max_entrants > game_session.seat_count()
+            || !Tournament::is_valid_payout(&payout_bps)
+            || payout_bps.len() > max_entrants as usize
+        {
+            return err!(TwentyOneError::InvalidTournamentConfig);
+        }
+        verify_supported_mint(&ctx.accounts.buy_in_mint.to_account_info())?;
+
+        let tournament = &mut ctx.accounts.tournament;
+        tournament.game_session = ctx.accounts.game_session_account.key();
+        tournament.buy_in_mint = ctx.accounts.buy_in_mint.key();
+        tournament.buy_in_amount = buy_in_amount;
+        tournament.starting_chips = starting_chips;
+        tournament.total_rounds = total_rounds;
+        tournament.rounds_played = 0;
+        tournament.max_entrants = max_entrants;
+        tournament.status = TournamentStatus::Registration;
+        tournament.payout_bps = payout_bps;
+        tournament.entrants = Vec::new();
+        tournament.bump = ctx.bumps.tournament;
+        tournament.prize_escrow_bump = ctx.bumps.prize_escrow;
+        game_session.set_tournament(true);
+
+        emit!(TournamentCreated {
+            table_name: game_session.table_name().to_string(),
+            round_id: game_session.round_id,
+            buy_in_mint: tournament.buy_in_mint,
+            buy_in_amount,
+            starting_chips,
+            total_rounds,
+        });
+
+        Ok(())
+    }
+
+    // --- 3.20. enter_tournament ---
+    /// Игрок, посаженный за стол, вносит бай-ин в призовой фонд и получает стартовый стек фишек.
+    pub fn enter_tournament<'info>(ctx: Context<'_, '_, '_, 'info, EnterTournament<'info>>, seat_index: u8) -> Result<()> {
+        let game_session = ctx.accounts.game_session_account.load()?;
+        let player_seat = ctx.accounts.player_seat.load()?;
+        let player_account = &ctx.accounts.player_account;
+        let tournament = &mut ctx.accounts.tournament;
+
+        if tournament.status != TournamentStatus::Registration { return err!(TwentyOneError::TournamentNotOpen); }
+        verify_player_at_seat(&player_seat, player_account.as_ref(), seat_index)?;
+        if tournament.entrants.len() >= tournament.max_entrants as usize
+            || tournament.entrants.iter().any(|entrant| entrant.player == player_account.key())
+        {
+            return err!(TwentyOneError::TournamentEntryRejected);
+        }
+
+        transfer_tokens(
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.player_token_account.to_account_info(),
+            &ctx.accounts.buy_in_mint.to_account_info(),
+            &ctx.accounts.prize_escrow.to_account_info(),
+            &player_account.to_account_info(),
+            tournament.buy_in_amount,
+            &[],
+        )?;
+
+        let chips = tournament.starting_chips;
+        tournament.entrants.push(TournamentEntrant { player: player_account.key(), chips });
+
+        emit!(TournamentEntered {
+            table_name: game_session.table_name().to_string(),
+            round_id: game_session.round_id,
+            player: player_account.key(),
+            seat_index,
+            chips,
+        });
+
+        Ok(())
+    }
+
+    // --- 3.21. start_tournament ---
+    /// Дилер закрывает регистрацию. Участников должно быть не меньше двух и не меньше призовых мест.
+    pub fn start_tournament<'info>(ctx: Context<'_, '_, '_, 'info, StartTournament<'info>>) -> Result<()> {
+        let game_session = ctx.accounts.game_session_account.load()?;
+        let tournament = &mut ctx.accounts.tournament;
+
+        if tournament.status != TournamentStatus::Registration { return err!(TwentyOneError::TournamentNotOpen); }
+        if tournament.entrants.len() < tournament.payout_bps.len().max(2) { return err!(TwentyOneError::NotEnoughPlayers); }
+
+        tournament.status = TournamentStatus::Running;
+        let prize_pool = tournament.buy_in_amount
+            .checked_mul(tournament.entrants.len() as u64)
+            .ok_or(TwentyOneError::ArithmeticOverflow)?;
+
+        emit!(TournamentStarted {
+            table_name: game_session.table_name().to_string(),
+            round_id: game_session.round_id,
+            entrants: tournament.entrants.len() as u8,
+            prize_pool,
+        });
+
+        Ok(())
+    }
+
+    // --- 3.22. finalize_tournament ---
+    /// Выплачивает призы после последнего раунда турнира и закрывает турнир и призовой фонд в пользу дилера.
+    /// `remaining_accounts`: токен-аккаунты бай-ин минта призеров в порядке итоговой таблицы (`Tournament::standings`).
+    pub fn finalize_tournament<'info>(ctx: Context<'_, '_, 'info, 'info, FinalizeTournament<'info>>) -> Result<()> {
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
+        let tournament = &ctx.accounts.tournament;
+
+        if tournament.status != TournamentStatus::Finished { return err!(TwentyOneError::TournamentNotFinished); }
+
+        let standings = tournament.standings();
+        let prizes = tournament.prizes(ctx.accounts.prize_escrow.amount)?;
+        if ctx.remaining_accounts.len() != prizes.len() {
+            return err!(TwentyOneError::PrizeRecipientMismatch);
+        }
+
+        let game_session_key = ctx.accounts.game_session_account.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[TOURNAMENT_SEED, game_session_key.as_ref(), &[tournament.bump]]];
+
+        for ((entrant, prize), recipient_info) in standings.iter().zip(&prizes).zip(ctx.remaining_accounts) {
+            let recipient = InterfaceAccount::<TokenAccount>::try_from(recipient_info)?;
+            if recipient.owner != entrant.player || recipient.mint != tournament.buy_in_mint {
+                return err!(TwentyOneError::PrizeRecipientMismatch);
+            }
+            transfer_tokens(
+                &ctx.accounts.token_program.to_account_info(),
+                &ctx.accounts.prize_escrow.to_account_info(),
+                &ctx.accounts.buy_in_mint.to_account_info(),
+                recipient_info,
+                &tournament.to_account_info(),
+                *prize,
+                signer_seeds,
+            )?;
+        }
+
+        token_interface::close_account(CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), CloseAccount {
+                account: ctx.accounts.prize_escrow.to_account_info(),
+                destination: ctx.accounts.dealer.to_account_info(),
+                authority: tournament.to_account_info(),
+            }, signer_seeds))?;
+
+        game_session.set_tournament(false);
+
+        emit!(TournamentFinished {
+            table_name: game_session.table_name().to_string(),
+            round_id: game_session.round_id,
+            standings: standings.iter().enumerate().map(|(place, entrant)| TournamentStanding {
+                player: entrant.player,
+                chips: entrant.chips,
+                prize: prizes.get(place).copied().unwrap_or_default(),
+            }).collect(),
+        });
+
+        Ok(())
+    }
+
+    // --- 3.23. deposit_to_vault ---
+    /// Вносит токены в хранилище игрока за этим столом. Токены переводятся на эскроу ставок стола.
+    pub fn deposit_to_vault<'info>(ctx: Context<'_, '_, '_, 'info, DepositToVault<'info>>, amount: u64) -> Result<()> {
+        let mut game_session = ctx.accounts.game_session_account.load_mut()?;
+        let player_account = &ctx.accounts.player_account;
+
+        if game_session.is_tournament() { return err!(TwentyOneError::TournamentTable); }
+        verify_supported_mint(&ctx.accounts.spl_token_mint.to_account_info())?;
+
+        transfer_tokens(
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.player_spl_token_account.to_account_info(),
+            &ctx.accounts.spl_token_mint.to_account_info(),
+            &ctx.accounts.game_session_spl_escrow_account.to_account_info(),
+            &player_account.to_account_info(),
+            amount,
+            &[],
+        )?;
+
+        let player_vault = &mut ctx.accounts.player_vault;
+        player_vault.game_session = ctx.accounts.game_session_account.key();
+        player_vault.player = player_account.key();
+        player_vault.token_mint = ctx.accounts.spl_token_mint.key();
+        player_vault.bump = ctx.bumps.player_vault;
+        player_vault.credit(amount)?;
+        game_session.credit_vault_total(player_vault.token_mint, amount)?;
+
+        emit!(VaultDeposited {
+            table_name: game_session.table_name().to_string(),
+            player: player_account.key(),
+            token_mint: player_vault.token_mint,
+            amount,
+            balance: player_vault.balance,
+        });
+
+        Ok(())
+    }
+
+    // --- 3.24. withdraw_from_vault ---
+    /// Выводит свободный баланс хранилища на токен-аккаунт игрока. Ставки в игре в баланс не входят,
+    /// поэтому вывод возможен в любой фазе раунда.
+    pub fn withdraw_from_vault<'info>(ctx: Context<'_, '_, '_, 'info, WithdrawFromVault<'info>>, amount: u64) -> Result<()> {
+        let player_vault = &mut ctx.accounts.player_vault;
+        player_vault.debit(amount)?;
+        let (table_name, bump) = {
+            let mut game_session = ctx.accounts.game_session_account.load_mut()?;
+            game_session.debit_vault_total(player_vault.token_mint, amount)?;
+            (game_session.table_name().to_string(), game_session.bump)
+        };
+
+        let signer_seeds: &[&[&[u8]]] = &[&[NORMALIZED_TABLE_NAME_PREFIX, table_name.as_bytes(), &[bump]]];
+        transfer_tokens(
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.game_session_spl_escrow_account.to_account_info(),
+            &ctx.accounts.spl_token_mint.to_account_info(),
+            &ctx.accounts.player_spl_token_account.to_account_info(),
+            &ctx.accounts.game_session_account.to_account_info(),
+            amount,
+            signer_seeds,
+        )?;
+
+        emit!(VaultWithdrawn {
+            table_name,
+            player: ctx.accounts.player_account.key(),
+            token_mint: player_vault.token_mint,
+            amount,
+            balance: player_vault.balance,
+        });
+
+        Ok(())
+    }
 } //
\ No newline at end of file
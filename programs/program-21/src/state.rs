@@ -5,7 +5,7 @@ use std::fmt; // Для реализации Display для Card
 // --- Enums ---
 
 /// Масть карты
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq, Debug, Default)]
 pub enum Suit {
     #[default]
     Hearts,   // Червы
@@ -14,8 +14,17 @@ pub enum Suit {
     Spades,   // Пики
 }
 
+impl Suit {
+    pub const ALL: [Suit; 4] = [Suit::Hearts, Suit::Diamonds, Suit::Clubs, Suit::Spades];
+
+    /// Восстанавливает масть из байта, в котором она хранится в zero-copy аккаунтах.
+    pub fn from_u8(value: u8) -> Self {
+        Self::ALL.get(value as usize).copied().unwrap_or_default()
+    }
+}
+
 /// Ранг карты
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq, Debug, Default)]
 pub enum Rank {
     Two, Three, Four, Five, Six, Seven, Eight, Nine, Ten,
     Jack,  // Валет
@@ -25,18 +34,37 @@ pub enum Rank {
     Ace,   // Туз
 }
 
+impl Rank {
+    pub const ALL: [Rank; 13] = [
+        Rank::Two, Rank::Three, Rank::Four, Rank::Five, Rank::Six, Rank::Seven,
+        Rank::Eight, Rank::Nine, Rank::Ten, Rank::Jack, Rank::Queen, Rank::King, Rank::Ace,
+    ];
+
+    /// Восстанавливает ранг из байта, в котором он хранится в zero-copy аккаунтах.
+    pub fn from_u8(value: u8) -> Self {
+        Self::ALL.get(value as usize).copied().unwrap_or_default()
+    }
+}
+
 /// Состояние игрового стола (сессии)
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
 pub enum GameState {
+    #[default]
     AcceptingBets,  // Идет прием ставок
     PlayerTurns,    // Ходы игроков
     DealerTurn,     // Ход дилера
     RoundOver,      // Раунд завершен, можно начинать новый
 }
 
-impl Default for GameState {
-    fn default() -> Self {
-        Self::AcceptingBets
+impl GameState {
+    /// Восстанавливает состояние из байта, в котором оно хранится в `GameSession`.
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            1 => GameState::PlayerTurns,
+            2 => GameState::DealerTurn,
+            3 => GameState::RoundOver,
+            _ => GameState::AcceptingBets,
+        }
     }
 }
 
@@ -49,13 +77,30 @@ pub enum HandStatus {
     Busted,           // Перебор (больше 21)
     Blackjack,        // Блэкджек (Туз + 10-очковая карта на первых двух картах)
     DoubledAndStood,  // Игрок удвоил ставку, получил одну карту и его ход на этой руке завершен
+    Surrendered,      // Игрок сдался на первых двух картах (поздний отказ)
+    Rescued,          // Игрок забрал удвоение после карты, отдав первоначальную ставку (double-down rescue)
+}
+
+impl HandStatus {
+    /// Восстанавливает статус из байта, в котором он хранится в `Hand`.
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            1 => HandStatus::Stood,
+            2 => HandStatus::Busted,
+            3 => HandStatus::Blackjack,
+            4 => HandStatus::DoubledAndStood,
+            5 => HandStatus::Surrendered,
+            6 => HandStatus::Rescued,
+            _ => HandStatus::Playing,
+        }
+    }
 }
 
 
 // --- Structs ---
 
 /// Представление игральной карты
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq)]
 pub struct Card {
     pub suit: Suit,
     pub rank: Rank,
@@ -79,6 +124,17 @@ impl Card {
         }
     }
 
+    /// Упакованное представление карты в одном байте: rank * 4 + suit.
+    /// В нем карта хранится в руках и кодируется в листьях дерева Меркла и хеше порядка шуза.
+    pub fn to_packed(&self) -> u8 {
+        self.rank as u8 * 4 + self.suit as u8
+    }
+
+    /// Восстанавливает карту из упакованного байта `to_packed`.
+    pub fn from_packed(packed: u8) -> Self {
+        Self { suit: Suit::from_u8(packed % 4), rank: Rank::from_u8(packed / 4) }
+    }
+
     /// Проверяет, является ли карта Тузом.
     pub fn is_ace(&self) -> bool {
         self.rank == Rank::Ace
@@ -111,33 +167,443 @@ impl fmt::Display for Card {
 }
 
 
-/// Рука карт игрока или дилера
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
+/// Состав одной колоды шуза.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum DeckComposition {
+    #[default]
+    Standard, // Полная 52-карточная колода.
+    Spanish,  // Колода без десяток (48 карт); валеты, дамы и короли остаются.
+}
+
+impl DeckComposition {
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            1 => DeckComposition::Spanish,
+            _ => DeckComposition::Standard,
+        }
+    }
+
+    /// Ранги, входящие в колоду, в порядке `Rank::ALL`.
+    pub fn ranks(self) -> impl Iterator<Item = Rank> {
+        Rank::ALL.into_iter().filter(move |rank| self == DeckComposition::Standard || *rank != Rank::Ten)
+    }
+
+    /// Количество карт в одной колоде.
+    pub fn cards_per_deck(self) -> u16 {
+        match self {
+            DeckComposition::Standard => CARDS_IN_DECK,
+            DeckComposition::Spanish => CARDS_IN_DECK - Suit::ALL.len() as u16,
+        }
+    }
+}
+
+/// Параметры состава шуза, необходимые для воспроизведения тасования (см. `verify_shoe`).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ShoeRules {
+    pub num_decks: u8,                  // Количество колод в шузе.
+    pub composition: DeckComposition,   // Состав каждой колоды.
+}
+
+impl Default for ShoeRules {
+    fn default() -> Self {
+        Self { num_decks: NUM_DECKS, composition: DeckComposition::Standard }
+    }
+}
+
+impl ShoeRules {
+    /// Допустим ли такой состав шуза.
+    pub fn is_valid(&self) -> bool {
+        (MIN_NUM_DECKS..=MAX_NUM_DECKS).contains(&self.num_decks)
+    }
+
+    /// Количество карт в шузе.
+    pub fn total_cards(&self) -> u16 {
+        self.num_decks as u16 * self.composition.cards_per_deck()
+    }
+
+    /// Позиция, начиная с которой перед следующей раздачей требуется перетасовка.
+    pub fn reshuffle_threshold_index(&self) -> u16 {
+        self.total_cards() * DECK_RESHUFFLE_THRESHOLD_PERCENT / 100
+    }
+
+    /// Глубина дерева Меркла шуза: листья дополняются до ближайшей степени двойки (256 для 4 колод, 512 для 8).
+    pub fn merkle_depth(&self) -> u32 {
+        self.total_cards().next_power_of_two().trailing_zeros()
+    }
+}
+
+/// Разновидность блэкджека, по правилам которой играет стол (выбирается в `initialize_table`).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum GameVariant {
+    #[default]
+    Classic,
+    Spanish21, // Шуз без десяток, 21 игрока всегда выигрывает, бонусы за 21, поздний отказ и спасение удвоения.
+    FreeBet,   // Бесплатные удвоения на жестких 9–11 и сплиты (кроме десяток), 22 у дилера — пуш.
+    DoubleExposure, // Обе карты дилера открыты, блэкджек платит 1:1, ничьи выигрывает дилер.
+    BlackjackSwitch, // Игрок с несколькими боксами может поменять местами вторые карты двух рук, блэкджек платит 1:1, 22 у дилера — пуш.
+}
+
+impl GameVariant {
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            1 => GameVariant::Spanish21,
+            2 => GameVariant::FreeBet,
+            3 => GameVariant::DoubleExposure,
+            4 => GameVariant::BlackjackSwitch,
+            _ => GameVariant::Classic,
+        }
+    }
+
+    /// Состав колод, которым играет разновидность.
+    pub fn deck_composition(self) -> DeckComposition {
+        match self {
+            GameVariant::Classic | GameVariant::FreeBet | GameVariant::DoubleExposure | GameVariant::BlackjackSwitch => {
+                DeckComposition::Standard
+            }
+            GameVariant::Spanish21 => DeckComposition::Spanish,
+        }
+    }
+
+    /// Разрешен ли поздний отказ (`player_action_surrender`).
+    pub fn allows_surrender(self) -> bool {
+        self == GameVariant::Spanish21
+    }
+
+    /// Разрешено ли забрать удвоение после карты (`player_action_rescue`).
+    pub fn allows_double_rescue(self) -> bool {
+        self == GameVariant::Spanish21
+    }
+
+    /// Оплачивает ли заведение удвоение руки: Free Bet, жесткие 9–11 на двух картах.
+    pub fn is_free_double(self, hand: &Hand) -> bool {
+        let (score, is_soft) = hand.calculate_score();
+        self == GameVariant::FreeBet && hand.card_count() == 2 && !is_soft && (9..=11).contains(&score)
+    }
+
+    /// Оплачивает ли заведение сплит пары: Free Bet, любые пары, кроме десяток и картинок.
+    pub fn is_free_split(self, pair_card: Card) -> bool {
+        self == GameVariant::FreeBet && pair_card.default_value() != 10
+    }
+
+    /// Разрешен ли обмен вторыми картами между руками места (`player_action_switch`).
+    pub fn allows_switch(self) -> bool {
+        self == GameVariant::BlackjackSwitch
+    }
+
+    /// Сдается ли вторая карта дилера открытой.
+    pub fn exposes_dealer_cards(self) -> bool {
+        self == GameVariant::DoubleExposure
+    }
+
+    /// Выигрывает ли дилер ничьи (кроме ничьей блэкджеков, которую выигрывает игрок).
+    pub fn dealer_wins_ties(self) -> bool {
+        self == GameVariant::DoubleExposure
+    }
+
+    /// Платит ли блэкджек игрока 1:1 вместо `BLACKJACK_PAYOUT_PROFIT_NUMERATOR / _DENOMINATOR`.
+    pub fn blackjack_pays_even_money(self) -> bool {
+        matches!(self, GameVariant::DoubleExposure | GameVariant::BlackjackSwitch)
+    }
+
+    /// Возвращает ли перебор дилера ровно с 22 очками ставки на руки без перебора.
+    pub fn dealer_22_pushes(self) -> bool {
+        matches!(self, GameVariant::FreeBet | GameVariant::BlackjackSwitch)
+    }
+}
+
+/// Побочная ставка на бокс.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SideBetKind {
+    TwentyOnePlusThree, // Две карты игрока и открытая карта дилера как покерная комбинация.
+    PerfectPairs,       // Пара в двух первых картах игрока.
+}
+
+impl SideBetKind {
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            1 => SideBetKind::PerfectPairs,
+            _ => SideBetKind::TwentyOnePlusThree,
+        }
+    }
+}
+
+/// Комбинация, определенная для побочной ставки после раздачи.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SideBetOutcome {
+    #[default]
+    Loss,
+    // --- 21+3 ---
+    Flush,          // Три карты одной масти.
+    Straight,       // Три карты подряд (туз — старший или младший).
+    ThreeOfAKind,   // Три карты одного ранга разных мастей.
+    StraightFlush,  // Три карты подряд одной масти.
+    SuitedTrips,    // Три одинаковые карты.
+    // --- Perfect Pairs ---
+    MixedPair,      // Пара разного цвета.
+    ColoredPair,    // Пара одного цвета, разных мастей.
+    PerfectPair,    // Пара одинаковых карт.
+}
+
+impl SideBetOutcome {
+    pub const ALL: [SideBetOutcome; 9] = [
+        SideBetOutcome::Loss, SideBetOutcome::Flush, SideBetOutcome::Straight, SideBetOutcome::ThreeOfAKind,
+        SideBetOutcome::StraightFlush, SideBetOutcome::SuitedTrips, SideBetOutcome::MixedPair,
+        SideBetOutcome::ColoredPair, SideBetOutcome::PerfectPair,
+    ];
+
+    pub fn from_u8(value: u8) -> Self {
+        Self::ALL.get(value as usize).copied().unwrap_or_default()
+    }
+}
+
+/// Пейтейбл побочных ставок стола: выплата "к 1" для каждой комбинации (выигрыш без учета возврата ставки).
+/// Ставка вида, у которого все выплаты нулевые, столом не принимается.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct SideBetPaytable {
+    // 21+3
+    pub flush: u16,
+    pub straight: u16,
+    pub three_of_a_kind: u16,
+    pub straight_flush: u16,
+    pub suited_trips: u16,
+    // Perfect Pairs
+    pub mixed_pair: u16,
+    pub colored_pair: u16,
+    pub perfect_pair: u16,
+}
+
+impl SideBetPaytable {
+    /// Количество выплат в пейтейбле (размер массива, которым он хранится в `GameSession`).
+    pub const LEN: usize = 8;
+
+    pub fn to_array(self) -> [u16; Self::LEN] {
+        [
+            self.flush, self.straight, self.three_of_a_kind, self.straight_flush, self.suited_trips,
+            self.mixed_pair, self.colored_pair, self.perfect_pair,
+        ]
+    }
+
+    pub fn from_array(odds: [u16; Self::LEN]) -> Self {
+        let [flush, straight, three_of_a_kind, straight_flush, suited_trips, mixed_pair, colored_pair, perfect_pair] = odds;
+        Self { flush, straight, three_of_a_kind, straight_flush, suited_trips, mixed_pair, colored_pair, perfect_pair }
+    }
+
+    /// Допустимы ли выплаты пейтейбла.
+    pub fn is_valid(&self) -> bool {
+        self.to_array().iter().all(|odds| *odds <= MAX_SIDE_BET_PAYOUT_ODDS)
+    }
+
+    /// Принимает ли стол побочную ставку этого вида.
+    pub fn offers(&self, kind: SideBetKind) -> bool {
+        match kind {
+            SideBetKind::TwentyOnePlusThree => {
+                (self.flush | self.straight | self.three_of_a_kind | self.straight_flush | self.suited_trips) != 0
+            }
+            SideBetKind::PerfectPairs => (self.mixed_pair | self.colored_pair | self.perfect_pair) != 0,
+        }
+    }
+
+    /// Выплата "к 1" за комбинацию; 0 — ставка проиграна.
+    pub fn odds(&self, outcome: SideBetOutcome) -> u16 {
+        match outcome {
+            SideBetOutcome::Loss => 0,
+            SideBetOutcome::Flush => self.flush,
+            SideBetOutcome::Straight => self.straight,
+            SideBetOutcome::ThreeOfAKind => self.three_of_a_kind,
+            SideBetOutcome::StraightFlush => self.straight_flush,
+            SideBetOutcome::SuitedTrips => self.suited_trips,
+            SideBetOutcome::MixedPair => self.mixed_pair,
+            SideBetOutcome::ColoredPair => self.colored_pair,
+            SideBetOutcome::PerfectPair => self.perfect_pair,
+        }
+    }
+}
+
+/// Источник публичной энтропии, который стол использует для перетасовки (выбирается в `initialize_table`).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum RandomnessSourceKind {
+    #[default]
+    CommitReveal,       // Сиды игроков, добавленные после коммита бэкенда, и хеш последнего слота.
+    VrfAccount(Pubkey), // Значение VRF из указанного аккаунта Switchboard On-Demand.
+    SlotHashMix,        // Смешивание нескольких последних хешей слотов.
+}
+
+impl RandomnessSourceKind {
+    /// Раскладывает источник на тег и аккаунт VRF для хранения в zero-copy аккаунте.
+    pub fn to_parts(self) -> (u8, Pubkey) {
+        match self {
+            RandomnessSourceKind::CommitReveal => (0, Pubkey::default()),
+            RandomnessSourceKind::VrfAccount(account) => (1, account),
+            RandomnessSourceKind::SlotHashMix => (2, Pubkey::default()),
+        }
+    }
+
+    /// Собирает источник из тега и аккаунта VRF (см. `to_parts`).
+    pub fn from_parts(kind: u8, account: Pubkey) -> Self {
+        match kind {
+            1 => RandomnessSourceKind::VrfAccount(account),
+            2 => RandomnessSourceKind::SlotHashMix,
+            _ => RandomnessSourceKind::CommitReveal,
+        }
+    }
+}
+
+/// Элементы сида одного шуза. Итоговый сид: sha256(secret || entropy),
+/// где секрет известен только бэкенду до вывода шуза из игры.
+#[zero_copy]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ShoeEntropy {
+    pub secret_commitment: [u8; 32],          // sha256(secret), зафиксированный до сбора энтропии.
+    pub entropy: [u8; 32],                    // Публичная энтропия на момент фиксации.
+    source_account: Pubkey,                   // Аккаунт VRF, если энтропия получена из него.
+    source_kind: u8,                          // Тег источника (см. `RandomnessSourceKind::to_parts`).
+}
+
+impl ShoeEntropy {
+    pub fn new(secret_commitment: [u8; 32], source: RandomnessSourceKind, entropy: [u8; 32]) -> Self {
+        let (source_kind, source_account) = source.to_parts();
+        Self { secret_commitment, entropy, source_account, source_kind }
+    }
+
+    /// Источник, из которого получена энтропия.
+    pub fn source(&self) -> RandomnessSourceKind {
+        RandomnessSourceKind::from_parts(self.source_kind, self.source_account)
+    }
+}
+
+/// Действие игрока, для завершения которого бэкенд должен раскрыть карты из шуза.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PendingDraw {
+    Hit,        // Одна карта в текущую руку.
+    DoubleDown, // Одна карта в текущую руку, после чего рука завершена.
+    Split,      // По одной карте в каждую из двух рук после сплита.
+}
+
+impl PendingDraw {
+    /// Байт, которым ожидающее действие хранится в `GameSession` (0 — ничего не ожидается).
+    pub fn to_u8(draw: Option<PendingDraw>) -> u8 {
+        match draw {
+            None => 0,
+            Some(PendingDraw::Hit) => 1,
+            Some(PendingDraw::DoubleDown) => 2,
+            Some(PendingDraw::Split) => 3,
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Option<PendingDraw> {
+        match value {
+            1 => Some(PendingDraw::Hit),
+            2 => Some(PendingDraw::DoubleDown),
+            3 => Some(PendingDraw::Split),
+            _ => None,
+        }
+    }
+}
+
+/// Рука карт игрока или дилера.
+/// Хранится прямо в zero-copy `GameSession` и `PlayerSeat`, поэтому имеет фиксированную раскладку:
+/// карты лежат в массиве, перечисления и `Option` хранятся как байты и читаются через методы.
+#[zero_copy]
+#[derive(Debug, Default)]
 pub struct Hand {
-    pub cards: Vec<Card>,                 // Карты в руке. Макс. ~11, но Vec для гибкости.
-    pub status: HandStatus,               // Текущий статус руки (Playing, Stood, Busted, Blackjack).
-    pub bet_multiplier_x100: u16,         // Множитель ставки, умноженный на 100 (например, 100 для 1.0x, 200 для 2.0x после удвоения).
     pub token_mint_for_bet: Pubkey,       // Минт токена, которым сделана ставка на эту руку.
     pub original_bet_amount: u64,         // Первоначальная сумма ставки на эту руку (в UI единицах токена).
+    pub bet_usd_value: u64,               // Проверенная стоимость первоначальной ставки в USD (в наименьших единицах, например центах).
+    pub bet_multiplier_x100: u16,         // Множитель ставки, умноженный на 100 (например, 100 для 1.0x, 200 для 2.0x после удвоения).
+    unrevealed_card_index: u16,           // Позиция в шузе сданной, но еще не раскрытой карты (закрытая карта дилера).
+    cards: [u8; MAX_CARDS_IN_HAND],       // Упакованные карты в руке (см. `Card::to_packed`), заняты первые `card_count`.
+    card_count: u8,
+    status: u8,                           // Текущий статус руки (HandStatus).
+    has_unrevealed_card: u8,
+    pub spot_index: u8,                   // Бокс места, на который сделана ставка (руки после сплита остаются в том же боксе).
+    is_split: u8,                         // Рука получена сплитом: повторно делить ее нельзя.
+    pub free_stake_x100: u16,             // Часть множителя ставки, оплаченная заведением (Free Bet): на нее платится выигрыш, но сама она не возвращается.
+    _padding: [u8; 2],
 }
 
 impl Hand {
     /// Создает новую пустую руку с заданной ставкой.
-    pub fn new(token_mint: Pubkey, bet_amount: u64) -> Self {
+    pub fn new(token_mint: Pubkey, bet_amount: u64, bet_usd_value: u64) -> Self {
         Self {
-            cards: Vec::with_capacity(MAX_CARDS_IN_HAND), // Предварительное выделение памяти
-            status: HandStatus::Playing,                  // Начальный статус - "в игре"
-            bet_multiplier_x100: 100,                     // Начальный множитель 1.0x
             token_mint_for_bet: token_mint,
             original_bet_amount: bet_amount,
+            bet_usd_value,
+            bet_multiplier_x100: 100,                     // Начальный множитель 1.0x
+            ..Self::default()                             // Без карт, статус "в игре"
         }
     }
 
-    /// Добавляет карту в руку.
-    pub fn add_card(&mut self, card: Card) {
-        if self.cards.len() < MAX_CARDS_IN_HAND { // Защита от переполнения Vec, если он был бы фиксированным
-            self.cards.push(card);
+    pub fn is_split(&self) -> bool {
+        self.is_split != 0
+    }
+
+    pub fn set_split(&mut self, is_split: bool) {
+        self.is_split = is_split as u8;
+    }
+
+    /// Карты в руке в порядке получения.
+    pub fn cards(&self) -> Vec<Card> {
+        self.iter_cards().collect()
+    }
+
+    fn iter_cards(&self) -> impl Iterator<Item = Card> + '_ {
+        self.cards[..self.card_count as usize].iter().map(|packed| Card::from_packed(*packed))
+    }
+
+    /// Количество карт в руке.
+    pub fn card_count(&self) -> usize {
+        self.card_count as usize
+    }
+
+    /// Карта на позиции `index`, если она есть.
+    pub fn card(&self, index: usize) -> Option<Card> {
+        self.cards[..self.card_count as usize].get(index).map(|packed| Card::from_packed(*packed))
+    }
+
+    /// Забирает из руки последнюю карту (используется при сплите).
+    pub fn pop_card(&mut self) -> Option<Card> {
+        let last = self.card_count.checked_sub(1)?;
+        self.card_count = last;
+        Some(Card::from_packed(std::mem::take(&mut self.cards[last as usize])))
+    }
+
+    pub fn status(&self) -> HandStatus {
+        HandStatus::from_u8(self.status)
+    }
+
+    pub fn set_status(&mut self, status: HandStatus) {
+        self.status = status as u8;
+    }
+
+    /// Позиция в шузе сданной, но еще не раскрытой карты.
+    pub fn unrevealed_card_index(&self) -> Option<u16> {
+        (self.has_unrevealed_card != 0).then_some(self.unrevealed_card_index)
+    }
+
+    pub fn set_unrevealed_card_index(&mut self, index: Option<u16>) {
+        self.has_unrevealed_card = index.is_some() as u8;
+        self.unrevealed_card_index = index.unwrap_or_default();
+    }
+
+    /// Есть ли в руке сданная, но еще не раскрытая карта.
+    pub fn has_unrevealed_card(&self) -> bool {
+        self.unrevealed_card_index().is_some()
+    }
+
+    /// Заняты ли все `MAX_CARDS_IN_HAND` позиций руки: новую карту в нее сдать нельзя.
+    pub fn is_full(&self) -> bool {
+        self.card_count() >= MAX_CARDS_IN_HAND
+    }
+
+    /// Добавляет карту в руку. Карта сверх `MAX_CARDS_IN_HAND` не помещается в фиксированный массив
+    /// и отклоняется ошибкой, а не теряется.
+    pub fn add_card(&mut self, card: Card) -> Result<()> {
+        if self.is_full() {
+            return err!(crate::errors::TwentyOneError::HandFull);
         }
+        self.cards[self.card_count as usize] = card.to_packed();
+        self.card_count += 1;
+        Ok(())
     }
 
     /// Рассчитывает сумму очков в руке.
@@ -147,7 +613,7 @@ impl Hand {
         let mut score: u16 = 0; // Используем u16 для промежуточных сумм во избежание переполнения u8
         let mut num_aces = 0;
 
-        for card in &self.cards {
+        for card in self.iter_cards() {
             score += card.default_value() as u16;
             if card.is_ace() {
                 num_aces += 1;
@@ -164,7 +630,7 @@ impl Hand {
         // Рука "мягкая", если в ней есть Туз, который все еще считается как 11 (т.е. num_aces не равен количеству всех тузов, если их значение было уменьшено).
         // Проще: если есть хотя бы один туз, и если его посчитать как 1, а не 11, то рука все еще "мягкая".
         // Это означает, что `score` была рассчитана с учетом хотя бы одного туза как 11, и `score <= 21`.
-        let is_soft = self.cards.iter().any(|c| c.is_ace()) && // Есть хотя бы один туз
+        let is_soft = self.iter_cards().any(|c| c.is_ace()) && // Есть хотя бы один туз
                       score <= 21 &&                            // Сумма не превышает 21
                       self.calculate_hard_score() != score;     // И сумма отличается от "жесткой" (где все тузы по 1)
                                                                 // Это значит, что хотя бы один туз был посчитан как 11.
@@ -173,14 +639,25 @@ impl Hand {
     
     /// Вспомогательный метод: рассчитывает "жесткую" сумму очков (все Тузы считаются как 1).
     fn calculate_hard_score(&self) -> u16 {
-        self.cards.iter().map(|card| {
+        self.iter_cards().map(|card| {
             if card.is_ace() { 1 } else { card.default_value() as u16 }
         }).sum()
     }
 
+    /// Игрок останавливается на руке. Удвоенная рука, ожидающая решения о спасении (Spanish 21),
+    /// остается удвоенной: игрок просто отказывается от спасения.
+    pub fn stand(&mut self) -> Result<()> {
+        match self.status() {
+            HandStatus::Playing => self.set_status(HandStatus::Stood),
+            HandStatus::DoubledAndStood => {}
+            _ => return err!(crate::errors::TwentyOneError::HandActionOnFinalizedHand),
+        }
+        Ok(())
+    }
+
     /// Проверяет, является ли рука "блэкджеком" (Туз + 10-очковая карта на первых двух картах).
     pub fn is_blackjack(&self) -> bool {
-        self.cards.len() == 2 && self.calculate_score().0 == 21
+        self.card_count() == 2 && self.calculate_score().0 == 21
     }
 
     /// Проверяет, является ли сумма очков в руке перебором (> 21).
@@ -192,12 +669,12 @@ impl Hand {
     /// Не устанавливает Blackjack, так как Blackjack определяется на начальной раздаче.
     pub fn update_status_after_card_drawn(&mut self) {
         if self.is_busted() {
-            self.status = HandStatus::Busted;
+            self.set_status(HandStatus::Busted);
         } else if self.calculate_score().0 == 21 {
             // Если 21 очко, и это не начальная раздача (т.к. Blackjack устанавливается отдельно),
             // то игрок автоматически "стоит" (Stood).
-            if self.status != HandStatus::Blackjack { // Не перезаписывать Blackjack, если он был установлен
-                self.status = HandStatus::Stood;
+            if self.status() != HandStatus::Blackjack { // Не перезаписывать Blackjack, если он был установлен
+                self.set_status(HandStatus::Stood);
             }
         }
         // В противном случае статус остается Playing (если не был изменен на Busted или Stood).
@@ -213,50 +690,240 @@ impl Hand {
     }
 }
 
+/// Побочная ставка на бокс места. Комбинация определяется в `deal_initial_cards`,
+/// выплата — в `finalize_round` по пейтейблу стола.
+#[zero_copy]
+#[derive(Debug, Default)]
+pub struct SideBet {
+    pub token_mint: Pubkey,               // Минт токена ставки.
+    pub amount: u64,                      // Сумма ставки (в UI единицах токена).
+    pub bet_usd_value: u64,               // Проверенная стоимость ставки в USD (в наименьших единицах).
+    pub spot_index: u8,                   // Бокс, к картам которого относится ставка.
+    kind: u8,                             // Вид ставки (SideBetKind).
+    outcome: u8,                          // Комбинация после раздачи (SideBetOutcome).
+    _padding: [u8; 5],
+}
 
-/// Представление места игрока за столом
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
+impl SideBet {
+    pub fn new(kind: SideBetKind, spot_index: u8, token_mint: Pubkey, amount: u64, bet_usd_value: u64) -> Self {
+        Self {
+            token_mint,
+            amount,
+            bet_usd_value,
+            spot_index,
+            kind: kind as u8,
+            ..Self::default()
+        }
+    }
+
+    pub fn kind(&self) -> SideBetKind {
+        SideBetKind::from_u8(self.kind)
+    }
+
+    pub fn outcome(&self) -> SideBetOutcome {
+        SideBetOutcome::from_u8(self.outcome)
+    }
+
+    pub fn set_outcome(&mut self, outcome: SideBetOutcome) {
+        self.outcome = outcome as u8;
+    }
+}
+
+/// Аккаунт места игрока за столом: PDA от (стол, номер места).
+/// Руки и ставка игрока хранятся отдельно от `GameSession`, поэтому ставки на разных местах
+/// не блокируют друг друга и аккаунт стола. Аккаунт создается при первой посадке за место
+/// и затем переиспользуется.
+#[account(zero_copy)]
+#[derive(Debug, Default)]
 pub struct PlayerSeat {
-    pub player_pubkey: Option<Pubkey>,    // Pubkey игрока, если место занято, иначе None.
-    pub is_active_in_round: bool,         // Участвует ли игрок в текущем раунде (сделал ли ставку).
-    pub hands: Vec<Hand>,                 // Руки игрока (обычно одна, две после сплита). Макс. 2 руки.
-    
-    // Информация о ставке на текущий раунд (до сплита/удвоения).
-    pub current_bet_token_mint: Option<Pubkey>, // Минт токена, которым сделана ставка в этом раунде.
-    pub current_bet_amount_staked_ui: u64,    // Сумма ставки в UI-единицах токена.
-    pub current_bet_usd_value: u64,           // Проверенная и сохраненная стоимость ставки в USD (в наименьших единицах, например центах).
+    pub game_session: Pubkey,             // Стол, к которому относится место.
+    player_pubkey: Pubkey,                // Pubkey игрока; `Pubkey::default()`, если место свободно.
+
+    // Руки игрока в порядке хода: по одной на каждый бокс со ставкой, рука после сплита идет сразу
+    // за рукой, из которой получена. Заняты первые `hand_count`; ставка хранится в каждой руке.
+    hands: [Hand; MAX_HANDS_PER_PLAYER],
+    hand_count: u8,
+    is_active_in_round: u8,               // Участвует ли игрок в текущем раунде (сделал ли ставку).
+    pub seat_index: u8,                   // Номер места за столом.
+    pub bump: u8,
+    pub bets_behind: u8,                  // Ставки зрителей на боксы места в текущем раунде (см. `BetBehind`).
+    side_bet_count: u8,
+    has_switched: u8,                     // Игрок уже поменял вторые карты в этом раунде (Blackjack Switch).
+    _padding: [u8; 1],
+
+    side_bets: [SideBet; MAX_SIDE_BETS_PER_SEAT], // Побочные ставки раунда; заняты первые `side_bet_count`.
 }
 
 impl PlayerSeat {
+    pub const SEED_PREFIX: &'static [u8] = b"player_seat";
+
+    // Размер аккаунта PlayerSeat для `#[account(init, space = ...)]`: дискриминатор + фиксированная раскладка.
+    pub const CALCULATED_LEN: usize = 8 + std::mem::size_of::<PlayerSeat>();
+
+    /// Pubkey игрока, если место занято.
+    pub fn player_pubkey(&self) -> Option<Pubkey> {
+        (self.player_pubkey != Pubkey::default()).then_some(self.player_pubkey)
+    }
+
+    pub fn set_player_pubkey(&mut self, player: Option<Pubkey>) {
+        self.player_pubkey = player.unwrap_or_default();
+    }
+
+    pub fn is_active_in_round(&self) -> bool {
+        self.is_active_in_round != 0
+    }
+
+    pub fn set_active_in_round(&mut self, active: bool) {
+        self.is_active_in_round = active as u8;
+    }
+
+    /// Руки игрока в текущем раунде.
+    pub fn hands(&self) -> &[Hand] {
+        &self.hands[..self.hand_count as usize]
+    }
+
+    pub fn hands_mut(&mut self) -> &mut [Hand] {
+        &mut self.hands[..self.hand_count as usize]
+    }
+
+    /// Добавляет ставку на следующий свободный бокс и возвращает его номер.
+    /// Ставки принимаются до раздачи, поэтому у каждого занятого бокса пока ровно одна рука.
+    pub fn add_spot(&mut self, mut hand: Hand) -> Result<u8> {
+        let spot_index = self.hand_count;
+        if spot_index as usize >= MAX_SPOTS_PER_SEAT {
+            return err!(crate::errors::TwentyOneError::TooManySpots);
+        }
+        hand.spot_index = spot_index;
+        self.hands[spot_index as usize] = hand;
+        self.hand_count += 1;
+        Ok(spot_index)
+    }
+
+    /// Побочные ставки места в текущем раунде.
+    pub fn side_bets(&self) -> &[SideBet] {
+        &self.side_bets[..self.side_bet_count as usize]
+    }
+
+    pub fn side_bets_mut(&mut self) -> &mut [SideBet] {
+        &mut self.side_bets[..self.side_bet_count as usize]
+    }
+
+    /// Добавляет побочную ставку на бокс, на котором уже есть основная ставка.
+    /// На каждый бокс принимается не более одной ставки каждого вида.
+    pub fn add_side_bet(&mut self, side_bet: SideBet) -> Result<()> {
+        if !self.hands().iter().any(|hand| hand.spot_index == side_bet.spot_index) {
+            return err!(crate::errors::TwentyOneError::SpotNotInPlay);
+        }
+        let count = self.side_bet_count as usize;
+        if count >= MAX_SIDE_BETS_PER_SEAT
+            || self.side_bets().iter().any(|bet| bet.spot_index == side_bet.spot_index && bet.kind == side_bet.kind)
+        {
+            return err!(crate::errors::TwentyOneError::SideBetAlreadyPlaced);
+        }
+        self.side_bets[count] = side_bet;
+        self.side_bet_count += 1;
+        Ok(())
+    }
+
+    /// Вставляет руку, полученную сплитом, сразу за рукой `hand_index`, чтобы она игралась следующей.
+    pub fn insert_split_hand(&mut self, hand_index: usize, hand: Hand) -> Result<()> {
+        let count = self.hand_count as usize;
+        if hand_index >= count {
+            return err!(crate::errors::TwentyOneError::InvalidHandIndex);
+        }
+        if count >= MAX_HANDS_PER_PLAYER {
+            return err!(crate::errors::TwentyOneError::CannotSplitAlreadySplit);
+        }
+        self.hands.copy_within(hand_index + 1..count, hand_index + 2);
+        self.hands[hand_index + 1] = hand;
+        self.hand_count += 1;
+        Ok(())
+    }
+
     /// Сбрасывает состояние места для нового раунда (ставки, руки).
     pub fn reset_for_new_round(&mut self) {
-        self.is_active_in_round = false;
-        self.hands.clear(); // Очищаем все руки
-        self.current_bet_token_mint = None;
-        self.current_bet_amount_staked_ui = 0;
-        self.current_bet_usd_value = 0;
+        self.set_active_in_round(false);
+        self.hands = Default::default(); // Очищаем все руки
+        self.hand_count = 0;
+        self.bets_behind = 0;
+        self.side_bets = Default::default();
+        self.side_bet_count = 0;
+        self.has_switched = 0;
+    }
+
+    /// Может ли место еще поменять карты (Blackjack Switch): обмен возможен один раз за раунд и только
+    /// до первого действия, пока у всех рук места по две карты и нет сплитов.
+    pub fn can_switch(&self) -> bool {
+        let hands = self.hands();
+        self.has_switched == 0
+            && hands.len() >= 2
+            && hands.iter().all(|hand| {
+                hand.card_count() == 2
+                    && !hand.is_split()
+                    && hand.bet_multiplier_x100 == 100
+                    && matches!(hand.status(), HandStatus::Playing | HandStatus::Blackjack)
+            })
+    }
+
+    /// Меняет местами вторые карты рук `first` и `second` (Blackjack Switch). Ставки на оба бокса
+    /// должны быть равны. 21 после обмена не считается блэкджеком: такая рука сразу останавливается.
+    pub fn switch_second_cards(&mut self, first: usize, second: usize) -> Result<()> {
+        let hands = self.hands();
+        if !self.can_switch() || first == second || first.max(second) >= hands.len() {
+            return err!(crate::errors::TwentyOneError::SwitchNotAllowed);
+        }
+        let (first_hand, second_hand) = (&hands[first], &hands[second]);
+        if first_hand.token_mint_for_bet != second_hand.token_mint_for_bet
+            || first_hand.original_bet_amount != second_hand.original_bet_amount
+        {
+            return err!(crate::errors::TwentyOneError::SwitchBetsNotEqual);
+        }
+
+        let first_card = self.hands[first].cards[1];
+        self.hands[first].cards[1] = self.hands[second].cards[1];
+        self.hands[second].cards[1] = first_card;
+        for index in [first, second] {
+            let hand = &mut self.hands[index];
+            let status = if hand.calculate_score().0 == 21 { HandStatus::Stood } else { HandStatus::Playing };
+            hand.set_status(status);
+        }
+        self.has_switched = 1;
+        Ok(())
     }
 
     /// Находит индекс первой активной руки (со статусом `Playing`).
     /// Используется для определения, какой рукой игрок ходит следующей.
     pub fn get_first_active_hand_index(&self) -> Option<usize> {
-        self.hands.iter().position(|h| h.status == HandStatus::Playing)
+        self.hands().iter().position(|h| h.status() == HandStatus::Playing)
     }
 }
 
 /// Аккаунт-синглтон для хранения конфигурации авторизации.
-/// Хранит публичный ключ бэкенда, который имеет право сажать игроков за стол.
+/// Хранит публичный ключ бэкенда, который имеет право сажать игроков за стол,
+/// и минты залога дилера, разрешенные администратором для кластера, где развернута программа.
 #[account]
-#[derive(Default)]
+#[derive(InitSpace, Default)]
 pub struct TableAuthorityConfig {
     pub backend_authority: Pubkey,
     pub bump: u8,
+    pub version: u8,
+    pub admin: Pubkey,                    // Меняет список минтов залога.
+    #[max_len(MAX_COLLATERAL_MINTS)]
+    pub collateral_mints: Vec<Pubkey>,    // Стейблкоины, которые дилер может внести залогом в `initialize_table`.
 }
 
 impl TableAuthorityConfig {
     pub const SEED_PREFIX: &'static [u8] = b"authority_config";
-    // 8 (дискриминатор) + 32 (Pubkey) + 1 (bump)
-    pub const CALCULATED_LEN: usize = 8 + 32 + 1;
+    // Версия 0 — раскладка без поля `version`, версия 1 — без администратора и минтов залога, версия 2 — текущая.
+    pub const VERSION: u8 = 2;
+    // Размер аккаунта: дискриминатор + Borsh-размер полей.
+    pub const CALCULATED_LEN: usize = TableAuthorityConfig::DISCRIMINATOR.len() + TableAuthorityConfig::INIT_SPACE;
+
+    /// Список минтов залога: не длиннее `MAX_COLLATERAL_MINTS`, без повторов и пустых ключей.
+    pub fn is_valid_collateral_mints(mints: &[Pubkey]) -> bool {
+        mints.len() <= MAX_COLLATERAL_MINTS
+            && mints.iter().enumerate().all(|(i, mint)| *mint != Pubkey::default() && !mints[..i].contains(mint))
+    }
 }
 
 /// Структура для отслеживания баланса токенов (например, в профите дилера)
@@ -268,14 +935,30 @@ pub struct TokenBalance {
     pub pyth_feed_index: u8,      // ← Индекс в remaining_accounts
 }
 
+/// Учтенная прибыль дилера в одном токене (фиксированная раскладка внутри `GameSession`).
+#[zero_copy]
+#[derive(Debug, Default)]
+pub struct ProfitBalance {
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
 // --- Account (Основной аккаунт контракта) ---
 
-/// Аккаунт, представляющий одну игровую сессию (один стол Блэкджека)
-#[account]
+/// Аккаунт, представляющий одну игровую сессию (один стол Блэкджека).
+/// Zero-copy: действия игроков читают и меняют поля прямо в данных аккаунта, без полной
+/// Borsh-десериализации. Поэтому все коллекции — массивы фиксированного размера, а перечисления,
+/// строки и `Option` хранятся в байтах и читаются через методы ниже.
+/// Поля сгруппированы по выравниванию, чтобы раскладка не содержала неявных отступов.
+#[account(zero_copy)]
 #[derive(Debug)]
 pub struct GameSession {
+    // --- Версия раскладки ---
+    // Всегда первое поле: версия читается по одному смещению в любой раскладке (см. `stored_version`).
+    pub version: u8,
+    _version_padding: [u8; 7],
+
     // --- Метаданные стола ---
-    pub table_name: String,                       // Уникальное, нормализованное имя стола (макс. 32 символа).
     pub dealer: Pubkey,                           // Pubkey пользователя, создавшего и профинансировавшего стол.
     pub dealer_usdc_escrow: Pubkey,               // PDA, хранящий заблокированный USDC дилера.
     pub dealer_locked_usdc_amount: u64,           // Сумма USDC, заблокированная дилером.
@@ -284,188 +967,448 @@ pub struct GameSession {
     pub min_accepted_token_liquidity: u64,   // Мин. ликвидность (в USD) SPL-токена, чтобы он принимался для ставок.
 
     // --- Состояние игры ---
-    pub game_state: GameState,                    // Текущее состояние игры (Прием ставок, Раздача, и т.д.).
-    pub deck: Vec<Card>,                          // Перетасованная последовательность из 4 стандартных 52-карточных колод (208 карт).
-    pub current_deck_index: u16,                  // Указатель на следующую карту для взятия из `deck`.
-    
-    // --- Руки и игроки ---
-    pub dealer_hand: Hand,                        // Рука дилера.
-    pub player_seats: Vec<PlayerSeat>,            // Места игроков (фиксированный размер, основанный на `max_players`).
+    pub round_id: u64,                            // Монотонный номер последнего розданного раунда (увеличивается в `deal_initial_cards`).
+    pub deck_commitment: [u8; 32],                // Корень Меркла над солеными позициями карт текущего шуза. Сам порядок карт ончейн не хранится.
+    current_turn_start_timestamp: i64,
+
+    // --- Рука дилера (руки игроков хранятся в аккаунтах `PlayerSeat`) ---
+    pub dealer_hand: Hand,
 
     // --- Отслеживание прибыли дилера ---
     /// Хранит "виртуальный" баланс токенов, которые дилер выиграл у игроков.
     /// Фактически токены лежат на эскроу-счетах ставок до момента вывода дилером.
-    pub dealer_profit_tracker: Vec<TokenBalance>,
+    dealer_profit_tracker: [ProfitBalance; MAX_DIFFERENT_TOKENS_IN_PROFIT],
 
     // --- Верификация тасования ---
-    pub seed_elements_hash: [u8; 32],             // Хеш элементов, использованных для сида тасования колоды (слот, время, pubkey дилера, nonce).
+    pub shoe_id: u64,                             // Номер текущего шуза (увеличивается в `commit_shoe`).
+    pub next_shuffle_commitment_slot: u64,        // Слот, в котором зафиксирован `next_shuffle_commitment`.
+    next_shuffle_commitment: [u8; 32],            // sha256 секрета бэкенда для следующей перетасовки.
+    pub player_seeds_hash: [u8; 32],              // Накопитель сидов, добавленных игроками к следующей перетасовке.
+    randomness_source_account: Pubkey,            // Аккаунт VRF источника энтропии стола (см. `randomness_source`).
+    active_shoe: ShoeEntropy,                     // Элементы сида текущего шуза. Секрет раскрывается, когда шуз выводится из игры.
+    pending_shoe: ShoeEntropy,                    // Зафиксированные элементы сида следующего шуза, ожидающего корня Меркла.
+
+    // --- Однобайтовые поля ---
+    table_name: [u8; TABLE_NAME_MAX_LEN],         // Уникальное, нормализованное имя стола; заняты первые `table_name_len` байт.
+    pub current_deck_index: u16,                  // Позиция следующей карты, которую бэкенд должен раскрыть.
+    table_name_len: u8,
+    game_state: u8,                               // Текущее состояние игры (GameState).
+    pending_draw: u8,                             // Действие игрока, ожидающее раскрытия карт бэкендом (см. `PendingDraw::to_u8`).
+    current_turn_seat_index: u8,
+    current_turn_hand_index: u8,
+    has_current_turn: u8,
+    closing_down: u8,
+    dealer_profit_tracker_len: u8,
+    has_active_shoe: u8,
+    has_pending_shoe: u8,
+    has_next_shuffle_commitment: u8,
+    randomness_source_kind: u8,                   // Тег источника энтропии стола (см. `RandomnessSourceKind::to_parts`).
+    pub player_seed_contributors: u8,             // Битовая маска мест, уже добавивших сид к следующей перетасовке.
+    num_decks: u8,                                // Количество колод в шузе стола (см. `shoe_rules`).
+
+    // --- Очередность мест (битовые маски по номеру места) ---
+    pub occupied_seats: u8,                       // Места, за которыми сидят игроки.
+    pub round_seats: u8,                          // Места, получившие карты в текущем раунде.
+    pub seats_to_act: u8,                         // Места раунда, которым предстоит ход (не все боксы с блэкджеком после раздачи).
+    pub seats_with_bets: u8,                      // Места со ставками, еще не рассчитанными в `finalize_round` (включая ставки до раздачи).
+    seat_count: u8,                               // Количество мест за столом, выбранное дилером при создании.
+    first_hand_to_act: [u8; MAX_PLAYERS_LIMIT as usize], // Для мест из `seats_to_act`: рука, с которой начинается ход места.
 
-    // --- Отслеживание текущего хода ---
-    pub current_turn_seat_index: Option<u8>,
-    pub current_turn_hand_index: Option<u8>,
-    pub current_turn_start_timestamp: Option<i64>,
-    pub closing_down: bool,
-    
-    // --- Commit-Reveal для перетасовки ---
-    pub next_shuffle_commitment: Option<[u8; 32]>, // Хеш для следующего сида тасования.
-    
     // --- Служебные поля PDA ---
     pub bump: u8,
     pub dealer_usdc_escrow_bump: u8,
+
+    // --- Версия 2 ---
+    // Поля новых версий только дописываются в конец: данные предыдущей версии остаются префиксом
+    // текущей раскладки, и обновление сводится к увеличению аккаунта (см. `upgrade_appended_fields`).
+    side_bet_paytable: [u16; SideBetPaytable::LEN], // Выплаты побочных ставок (см. `SideBetPaytable`).
+
+    // --- Версия 3 ---
+    game_variant: u8,                             // Разновидность игры стола (GameVariant).
+    deck_composition: u8,                         // Состав колод шуза (DeckComposition, см. `shoe_rules`).
+    is_tournament: u8,                            // За столом идет турнир (см. `Tournament`): ставки делаются фишками. Занимает байт отступа, у прежних столов равен нулю.
+    settles_winnings_in_usdc: u8,                 // Выигрыши платятся в USDC из залога дилера (см. `split_usdc_settlement`). Занимает байт отступа.
+    _padding_v3: [u8; 4],
+
+    // --- Версия 4 ---
+    pub round_dealt_at: i64,                      // Время раздачи текущего раунда: от него отсчитывается окно обмена Blackjack Switch.
+
+    // --- Версия 5 ---
+    pub dealer_collateral_balance: u64,           // Остаток залога дилера на USDC-эскроу: уменьшается выплатами выигрышей в USDC.
+
+    // --- Версия 6 ---
+    /// Суммарный баланс хранилищ игроков (`PlayerVault`) по токенам. Токены хранилищ лежат на эскроу ставок стола,
+    /// но принадлежат игрокам: дилер не может их вывести, а стол нельзя закрыть, пока баланс не выведен.
+    vault_totals: [ProfitBalance; MAX_VAULT_MINTS],
+    vault_totals_len: u8,
+    _padding_v6: [u8; 7],
 }
 
 impl GameSession {
-    // Расчет максимального размера аккаунта GameSession для `#[account(init, space = ...)]`
-    // Это КРИТИЧЕСКИ важно. Должен быть немного больше реального использования, чтобы избежать ошибок.
-
-    // Размер одной карты (Suit (1 байт) + Rank (1 байт))
-    pub const CARD_SIZE: usize = std::mem::size_of::<Suit>() + std::mem::size_of::<Rank>(); // = 2 байта
-
-    // Максимальный размер одной руки (Hand)
-    pub const HAND_MAX_LEN_FOR_GAME_SESSION: usize =
-        (4 + MAX_CARDS_IN_HAND * Self::CARD_SIZE)  // cards: Vec<Card> (4 байта для длины + N * размер карты)
-        + std::mem::size_of::<HandStatus>()      // status: HandStatus (1 байт)
-        + std::mem::size_of::<u16>()             // bet_multiplier_x100: u16 (2 байта)
-        + std::mem::size_of::<Pubkey>()          // token_mint_for_bet: Pubkey (32 байта)
-        + std::mem::size_of::<u64>();            // original_bet_amount: u64 (8 байт)
-                                                 // Примерно: (4 + 11*2) + 1 + 2 + 32 + 8 = 26 + 1 + 2 + 32 + 8 = 69 байт
-
-    // Размер 1 руки для PlayerSeat:
-    pub const HAND_MAX_LEN_FOR_PLAYER_SEAT: usize =
-        (Self::CARD_SIZE * MAX_CARDS_IN_HAND) + // cards (Vec<Card>)
-        std::mem::size_of::<HandStatus>() +     // status (enum)
-        2 +                                     // bet_multiplier_x100 (u16)
-        32 +                                    // token_mint_for_bet (Pubkey)
-        8;                                      // original_bet_amount (u64)
-
-    // Макс. размер для Vec<Hand> в PlayerSeat (2 руки)
-    pub const HANDS_VEC_MAX_LEN_FOR_PLAYER_SEAT: usize = 4 + (Self::HAND_MAX_LEN_FOR_PLAYER_SEAT * MAX_HANDS_PER_PLAYER); // 4 для Vec len
-
-    pub const PLAYER_SEAT_MAX_LEN: usize =
-        (1 + 32) +                              // player_pubkey (Option<Pubkey>)
-        1 +                                     // is_active_in_round (bool)
-        Self::HANDS_VEC_MAX_LEN_FOR_PLAYER_SEAT + // hands (Vec<Hand>)
-        (1 + 32) +                              // current_bet_token_mint (Option<Pubkey>)
-        8 +                                     // current_bet_amount_staked_ui (u64)
-        8;                                      // current_bet_usd_value (u64)
-
-    pub const PLAYER_SEATS_VEC_MAX_LEN: usize = 4 + (Self::PLAYER_SEAT_MAX_LEN * MAX_PLAYERS_LIMIT as usize);
-
-    // Размер 1 элемента в dealer_profit_tracker:
-    pub const TOKEN_BALANCE_SIZE: usize = std::mem::size_of::<Pubkey>() + std::mem::size_of::<u64>(); // 32 + 8 = 40 байт
-    // Макс. размер для Vec<TokenBalance> (предполагаем не более 10 разных токенов)
-    pub const DEALER_PROFIT_TRACKER_VEC_MAX_LEN: usize = 4 + (Self::TOKEN_BALANCE_SIZE * MAX_DIFFERENT_TOKENS_IN_PROFIT);
-
-    pub const DECK_VEC_MAX_LEN: usize = 4 + (Self::CARD_SIZE * TOTAL_CARDS as usize);
-
-    // Общий расчет размера аккаунта GameSession
-    pub const CALCULATED_LEN: usize = 
-        8 + // Discriminator
-        (4 + TABLE_NAME_MAX_LEN) +                  // table_name (String)
-        32 +                                        // dealer (Pubkey)
-        32 +                                        // dealer_usdc_escrow (Pubkey)
-        8 +                                         // dealer_locked_usdc_amount (u64)
-        std::mem::size_of::<GameState>() +          // game_state (enum)
-        Self::DECK_VEC_MAX_LEN +                    // deck (Vec<Card>)
-        2 +                                         // current_deck_index (u16)
-        Self::HAND_MAX_LEN_FOR_GAME_SESSION +       // dealer_hand (Hand)
-        Self::PLAYER_SEATS_VEC_MAX_LEN +            // player_seats (Vec<PlayerSeat>)
-        Self::DEALER_PROFIT_TRACKER_VEC_MAX_LEN +   // dealer_profit_tracker (Vec<TokenBalance>)
-        32 +                                        // seed_elements_hash ([u8; 32])
-        (1 + 1) +                                   // current_turn_seat_index (Option<u8>)
-        (1 + 1) +                                   // current_turn_hand_index (Option<u8>)
-        (1 + 8) +                                   // current_turn_start_timestamp (Option<i64>)
-        1 +                                         // closing_down (bool)
-        (1 + 32) +                                  // next_shuffle_commitment (Option<[u8; 32]>)
-        1 +                                         // bump (u8)
-        1;                                          // dealer_usdc_escrow_bump (u8)
-
-    /// Метод для получения следующей карты из колоды и продвижения индекса.
-    pub fn draw_card(&mut self) -> Result<Card> {
-        if self.current_deck_index >= TOTAL_CARDS {
-            // Этого не должно происходить, если есть логика своевременной перетасовки.
-            msg!("Error: Deck is empty! Current index: {}, Total cards: {}", self.current_deck_index, TOTAL_CARDS);
-            return err!(crate::errors::TwentyOneError::DeckEmpty);
+    // Размер аккаунта GameSession для `#[account(init, space = ...)]`: дискриминатор + фиксированная раскладка.
+    // Для zero-copy аккаунта это `size_of`, а не Borsh-размер: данные аккаунта и есть байты структуры.
+    pub const CALCULATED_LEN: usize = 8 + std::mem::size_of::<GameSession>();
+
+    // Версия 0 — устаревший Borsh-формат (см. `LegacyGameSession`), версия 1 — zero-copy раскладка,
+    // версия 2 — пейтейбл побочных ставок, версия 3 — разновидность игры и состав колод,
+    // версия 4 — время раздачи раунда, версия 5 — остаток залога дилера, версия 6 — балансы хранилищ игроков.
+    pub const VERSION: u8 = 6;
+
+    /// Размер аккаунта (с дискриминатором) в zero-copy раскладке версии `version`:
+    /// раскладка версии заканчивается там, где начинаются поля следующей.
+    pub fn layout_len(version: u8) -> Option<usize> {
+        match version {
+            1 => Some(8 + std::mem::offset_of!(GameSession, side_bet_paytable)),
+            2 => Some(8 + std::mem::offset_of!(GameSession, game_variant)),
+            3 => Some(8 + std::mem::offset_of!(GameSession, round_dealt_at)),
+            4 => Some(8 + std::mem::offset_of!(GameSession, dealer_collateral_balance)),
+            5 => Some(8 + std::mem::offset_of!(GameSession, vault_totals)),
+            Self::VERSION => Some(Self::CALCULATED_LEN),
+            _ => None,
         }
-        let card = self.deck[self.current_deck_index as usize];
-        self.current_deck_index += 1;
-        Ok(card)
     }
 
-    /// Сбрасывает колоду (создает новую из стандартных колод) и тасует ее.
-    /// Обновляет `current_deck_index` и `seed_elements_hash`.
-    pub fn shuffle_deck(&mut self, seed_elements_hash: [u8; 32]) -> Result<()> {
-        self.deck = crate::utils::create_standard_shoe(NUM_DECKS); // Создаем новую полную колоду
-        crate::utils::fisher_yates_shuffle(&mut self.deck, seed_elements_hash); // Тасуем ее
-        self.current_deck_index = 0; // Сбрасываем индекс на начало колоды
-        self.seed_elements_hash = seed_elements_hash; // Сохраняем хеш, использованный для тасования
+    /// Версия раскладки по сырым данным аккаунта (вместе с дискриминатором).
+    /// Borsh-формат версии 0 не хранит версию и отличается от zero-copy раскладок размером.
+    pub fn stored_version(data: &[u8]) -> u8 {
+        match data.get(8) {
+            Some(&version) if Self::layout_len(version) == Some(data.len()) => version,
+            _ => 0,
+        }
+    }
+
+    /// Нормализованное имя стола.
+    pub fn table_name(&self) -> &str {
+        std::str::from_utf8(&self.table_name[..self.table_name_len as usize]).unwrap_or_default()
+    }
+
+    pub fn set_table_name(&mut self, table_name: &str) -> Result<()> {
+        let bytes = table_name.as_bytes();
+        if bytes.len() > TABLE_NAME_MAX_LEN {
+            return err!(crate::errors::TwentyOneError::TableNameLengthInvalid);
+        }
+        self.table_name = [0u8; TABLE_NAME_MAX_LEN];
+        self.table_name[..bytes.len()].copy_from_slice(bytes);
+        self.table_name_len = bytes.len() as u8;
         Ok(())
     }
 
-    /// Находит изменяемую ссылку на место игрока по его Pubkey.
-    /// Возвращает кортеж (индекс места, ссылка на PlayerSeat).
-    pub fn find_player_seat_mut(&mut self, player_key: &Pubkey) -> Result<(usize, &mut PlayerSeat)> {
-        self.player_seats
-            .iter_mut()
-            .enumerate()
-            .find(|(_, seat)| seat.player_pubkey == Some(*player_key))
-            .ok_or_else(|| error!(crate::errors::TwentyOneError::CannotFindPlayerSeat))
+    pub fn game_state(&self) -> GameState {
+        GameState::from_u8(self.game_state)
     }
 
-    /// Находит неизменяемую ссылку на место игрока по его Pubkey.
-    #[allow(dead_code)] // Может быть полезен для read-only операций или проверок
-    pub fn find_player_seat(&self, player_key: &Pubkey) -> Result<(usize, &PlayerSeat)> {
-        self.player_seats
-            .iter()
-            .enumerate()
-            .find(|(_, seat)| seat.player_pubkey == Some(*player_key))
-            .ok_or_else(|| error!(crate::errors::TwentyOneError::CannotFindPlayerSeat))
+    pub fn set_game_state(&mut self, game_state: GameState) {
+        self.game_state = game_state as u8;
     }
-    
-    /// Сбрасывает руки и ставки для всех игроков и дилера для начала нового раунда.
+
+    /// Действие игрока, ожидающее раскрытия карт бэкендом.
+    pub fn pending_draw(&self) -> Option<PendingDraw> {
+        PendingDraw::from_u8(self.pending_draw)
+    }
+
+    pub fn set_pending_draw(&mut self, pending_draw: Option<PendingDraw>) {
+        self.pending_draw = PendingDraw::to_u8(pending_draw);
+    }
+
+    pub fn current_turn_seat_index(&self) -> Option<u8> {
+        (self.has_current_turn != 0).then_some(self.current_turn_seat_index)
+    }
+
+    pub fn current_turn_hand_index(&self) -> Option<u8> {
+        (self.has_current_turn != 0).then_some(self.current_turn_hand_index)
+    }
+
+    pub fn current_turn_start_timestamp(&self) -> Option<i64> {
+        (self.has_current_turn != 0).then_some(self.current_turn_start_timestamp)
+    }
+
+    /// Передает ход руке `hand_index` на месте `seat_index` и запускает таймер хода.
+    pub fn set_current_turn(&mut self, seat_index: u8, hand_index: u8, turn_start_timestamp: i64) {
+        self.current_turn_seat_index = seat_index;
+        self.current_turn_hand_index = hand_index;
+        self.current_turn_start_timestamp = turn_start_timestamp;
+        self.has_current_turn = 1;
+    }
+
+    /// Сбрасывает информацию о текущем ходе.
+    pub fn clear_current_turn(&mut self) {
+        self.current_turn_seat_index = 0;
+        self.current_turn_hand_index = 0;
+        self.current_turn_start_timestamp = 0;
+        self.has_current_turn = 0;
+    }
+
+    pub fn closing_down(&self) -> bool {
+        self.closing_down != 0
+    }
+
+    pub fn set_closing_down(&mut self, closing_down: bool) {
+        self.closing_down = closing_down as u8;
+    }
+
+    /// Элементы сида текущего шуза.
+    pub fn active_shoe(&self) -> Option<ShoeEntropy> {
+        (self.has_active_shoe != 0).then_some(self.active_shoe)
+    }
+
+    /// Зафиксированные элементы сида следующего шуза.
+    pub fn pending_shoe(&self) -> Option<ShoeEntropy> {
+        (self.has_pending_shoe != 0).then_some(self.pending_shoe)
+    }
+
+    pub fn set_pending_shoe(&mut self, pending_shoe: Option<ShoeEntropy>) {
+        self.has_pending_shoe = pending_shoe.is_some() as u8;
+        self.pending_shoe = pending_shoe.unwrap_or_default();
+    }
+
+    /// sha256 секрета бэкенда для следующей перетасовки.
+    pub fn next_shuffle_commitment(&self) -> Option<[u8; 32]> {
+        (self.has_next_shuffle_commitment != 0).then_some(self.next_shuffle_commitment)
+    }
+
+    pub fn set_next_shuffle_commitment(&mut self, commitment: Option<[u8; 32]>) {
+        self.has_next_shuffle_commitment = commitment.is_some() as u8;
+        self.next_shuffle_commitment = commitment.unwrap_or_default();
+    }
+
+    /// Источник энтропии для перетасовки этого стола.
+    pub fn randomness_source(&self) -> RandomnessSourceKind {
+        RandomnessSourceKind::from_parts(self.randomness_source_kind, self.randomness_source_account)
+    }
+
+    pub fn set_randomness_source(&mut self, source: RandomnessSourceKind) {
+        (self.randomness_source_kind, self.randomness_source_account) = source.to_parts();
+    }
+
+    /// Номер раунда, в котором будут сыграны ставки, принимаемые сейчас.
+    pub fn upcoming_round_id(&self) -> u64 {
+        self.round_id.saturating_add(1)
+    }
+
+    /// Берет из шуза раскрытые бэкендом карты, проверяя их против `deck_commitment`.
+    /// Диапазон должен начинаться с `current_deck_index`; индекс продвигается на число раскрытых карт.
+    pub fn draw_cards(&mut self, reveal: &CardRangeReveal) -> Result<Vec<Card>> {
+        if reveal.start_index != self.current_deck_index {
+            return err!(crate::errors::TwentyOneError::CardRevealIndexMismatch);
+        }
+        let cards = self.verify_revealed_cards(reveal)?;
+        self.current_deck_index += cards.len() as u16;
+        Ok(cards)
+    }
+
+    /// Резервирует следующую позицию шуза под карту, которая сдается закрытой, и возвращает эту позицию.
+    pub fn draw_unrevealed_card(&mut self) -> Result<u16> {
+        if self.current_deck_index >= self.shoe_rules().total_cards() {
+            return err!(crate::errors::TwentyOneError::DeckEmpty);
+        }
+        let index = self.current_deck_index;
+        self.current_deck_index += 1;
+        Ok(index)
+    }
+
+    /// Раскрывает закрытую карту дилера, сданную ранее на позицию `dealer_hand.unrevealed_card_index`.
+    pub fn reveal_dealer_hole_card(&mut self, reveal: &CardRangeReveal) -> Result<Card> {
+        let hole_card_index = self.dealer_hand.unrevealed_card_index()
+            .ok_or(crate::errors::TwentyOneError::NoUnrevealedHoleCard)?;
+        if reveal.cards.len() != 1 {
+            return err!(crate::errors::TwentyOneError::CardRevealCountMismatch);
+        }
+        if reveal.start_index != hole_card_index {
+            return err!(crate::errors::TwentyOneError::CardRevealIndexMismatch);
+        }
+        let hole_card = self.verify_revealed_cards(reveal)?[0];
+        self.dealer_hand.set_unrevealed_card_index(None);
+        self.dealer_hand.add_card(hole_card)?;
+        Ok(hole_card)
+    }
+
+    /// Проверяет доказательство Меркла для раскрытых карт, не меняя состояние шуза.
+    fn verify_revealed_cards(&self, reveal: &CardRangeReveal) -> Result<Vec<Card>> {
+        if reveal.cards.is_empty() {
+            return err!(crate::errors::TwentyOneError::CardRevealCountMismatch);
+        }
+        let end_index = (reveal.start_index as usize)
+            .checked_add(reveal.cards.len())
+            .ok_or(crate::errors::TwentyOneError::ArithmeticOverflow)?;
+        let total_cards = self.shoe_rules().total_cards();
+        if end_index > total_cards as usize {
+            // Этого не должно происходить, если есть логика своевременной перетасовки.
+            msg!("Error: Deck is empty! Current index: {}, Total cards: {}", self.current_deck_index, total_cards);
+            return err!(crate::errors::TwentyOneError::DeckEmpty);
+        }
+
+        let leaves: Vec<[u8; 32]> = reveal.cards.iter().enumerate()
+            .map(|(offset, revealed)| crate::utils::card_leaf_hash(reveal.start_index + offset as u16, &revealed.salt, &revealed.card))
+            .collect();
+        let depth = self.shoe_rules().merkle_depth();
+        if !crate::utils::verify_card_range(&self.deck_commitment, depth, reveal.start_index, &leaves, &reveal.proof) {
+            return err!(crate::errors::TwentyOneError::CardRevealProofInvalid);
+        }
+        Ok(reveal.cards.iter().map(|revealed| revealed.card).collect())
+    }
+
+    /// Вводит в игру новый шуз с корнем Меркла `deck_commitment` и элементами сида `entropy`.
+    pub fn start_new_shoe(&mut self, deck_commitment: [u8; 32], entropy: ShoeEntropy) -> Result<()> {
+        self.deck_commitment = deck_commitment;
+        self.current_deck_index = 0; // Сбрасываем индекс на начало колоды
+        self.active_shoe = entropy;
+        self.has_active_shoe = 1;
+        self.shoe_id = self.shoe_id.checked_add(1).ok_or(crate::errors::TwentyOneError::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    /// Состав шуза этого стола.
+    pub fn shoe_rules(&self) -> ShoeRules {
+        ShoeRules { num_decks: self.num_decks, composition: DeckComposition::from_u8(self.deck_composition) }
+    }
+
+    pub fn set_shoe_rules(&mut self, rules: ShoeRules) -> Result<()> {
+        if !rules.is_valid() {
+            return err!(crate::errors::TwentyOneError::InvalidNumDecks);
+        }
+        self.num_decks = rules.num_decks;
+        self.deck_composition = rules.composition as u8;
+        Ok(())
+    }
+
+    /// Разновидность игры стола.
+    pub fn game_variant(&self) -> GameVariant {
+        GameVariant::from_u8(self.game_variant)
+    }
+
+    /// Задает разновидность игры; состав колод шуза должен ей соответствовать (см. `set_shoe_rules`).
+    pub fn set_game_variant(&mut self, variant: GameVariant) -> Result<()> {
+        if self.shoe_rules().composition != variant.deck_composition() {
+            return err!(crate::errors::TwentyOneError::ShoeRulesVariantMismatch);
+        }
+        self.game_variant = variant as u8;
+        Ok(())
+    }
+
+    /// Требуется ли перетасовка перед следующей раздачей.
+    pub fn needs_shuffle(&self) -> bool {
+        self.current_deck_index >= self.shoe_rules().reshuffle_threshold_index()
+    }
+
+    /// Выплаты побочных ставок этого стола.
+    pub fn side_bet_paytable(&self) -> SideBetPaytable {
+        SideBetPaytable::from_array(self.side_bet_paytable)
+    }
+
+    pub fn set_side_bet_paytable(&mut self, paytable: SideBetPaytable) -> Result<()> {
+        if !paytable.is_valid() {
+            return err!(crate::errors::TwentyOneError::InvalidSideBetPaytable);
+        }
+        self.side_bet_paytable = paytable.to_array();
+        Ok(())
+    }
+
+    /// Количество мест за столом.
+    pub fn seat_count(&self) -> u8 {
+        self.seat_count
+    }
+
+    /// Идет ли за столом турнир: ставки делаются фишками `Tournament`, а не токенами.
+    pub fn is_tournament(&self) -> bool {
+        self.is_tournament != 0
+    }
+
+    pub fn set_tournament(&mut self, is_tournament: bool) {
+        self.is_tournament = is_tournament as u8;
+    }
+
+    /// Платятся ли выигрыши в USDC из залога дилера: в токене ставки возвращается только сама ставка.
+    pub fn settles_winnings_in_usdc(&self) -> bool {
+        self.settles_winnings_in_usdc != 0
+    }
+
+    pub fn set_settles_winnings_in_usdc(&mut self, enabled: bool) {
+        self.settles_winnings_in_usdc = enabled as u8;
+    }
+
+    pub fn set_seat_count(&mut self, seat_count: u8) -> Result<()> {
+        if seat_count == 0 || seat_count > MAX_PLAYERS_LIMIT {
+            return err!(crate::errors::TwentyOneError::InvalidSeatCount);
+        }
+        self.seat_count = seat_count;
+        Ok(())
+    }
+
+    /// Занято ли место с указанным номером.
+    pub fn is_seat_occupied(&self, seat_index: u8) -> bool {
+        self.occupied_seats & (1 << seat_index) != 0
+    }
+
+    pub fn set_seat_occupied(&mut self, seat_index: u8, occupied: bool) {
+        if occupied {
+            self.occupied_seats |= 1 << seat_index;
+        } else {
+            self.occupied_seats &= !(1 << seat_index);
+        }
+    }
+
+    /// Первое место, которому предстоит ход в текущем раунде. Места, закончившие ход, из очереди убираются,
+    /// поэтому это же место ходит следующим после текущего.
+    pub fn first_seat_to_act(&self) -> Option<u8> {
+        (0..self.seat_count).find(|idx| self.seats_to_act & (1 << idx) != 0)
+    }
+
+    /// Убирает место из очереди хода раунда: место закончило ход или ему больше нечем ходить.
+    pub fn clear_seat_to_act(&mut self, seat_index: u8) {
+        self.seats_to_act &= !(1 << seat_index);
+    }
+
+    /// Открыто ли окно обмена Blackjack Switch: сразу после раздачи места могут поменять карты
+    /// до своего хода, в том числе места, у которых все боксы — блэкджеки.
+    pub fn is_switch_window_open(&self, now: i64) -> bool {
+        self.game_variant().allows_switch()
+            && matches!(self.game_state(), GameState::PlayerTurns | GameState::DealerTurn)
+            && now <= self.round_dealt_at.saturating_add(SWITCH_WINDOW_SECONDS)
+    }
+
+    /// Рука, с которой начинается ход места (первый бокс без блэкджека).
+    pub fn first_hand_to_act(&self, seat_index: u8) -> u8 {
+        self.first_hand_to_act[seat_index as usize]
+    }
+
+    /// Ставит место в очередь хода раунда, начиная с руки `hand_index`.
+    pub fn set_seat_to_act(&mut self, seat_index: u8, hand_index: u8) {
+        self.seats_to_act |= 1 << seat_index;
+        self.first_hand_to_act[seat_index as usize] = hand_index;
+    }
+
+    /// Сбрасывает руку дилера и очередность мест для начала нового раунда.
+    /// Места игроков сбрасываются отдельно через `PlayerSeat::reset_for_new_round`.
     pub fn reset_hands_for_new_round(&mut self) {
         self.dealer_hand = Hand::default(); // Сброс руки дилера (статус, карты и т.д.)
-        for seat in self.player_seats.iter_mut() {
-            // Сбрасываем состояние только для занятых мест.
-            // Если игрок покинул стол, его player_pubkey будет None.
-            if seat.player_pubkey.is_some() {
-                seat.reset_for_new_round();
-            }
-        }
-        self.current_turn_seat_index = None; // Сбрасываем информацию о текущем ходе
-        self.current_turn_hand_index = None;
-        self.current_turn_start_timestamp = None;
+        self.set_pending_draw(None);
+        self.round_seats = 0;
+        self.seats_to_act = 0;
+        self.seats_with_bets = 0;
+        self.first_hand_to_act = [0; MAX_PLAYERS_LIMIT as usize];
+        self.clear_current_turn(); // Сбрасываем информацию о текущем ходе
     }
 
-    /// Проверяет, есть ли за столом активные игроки, сделавшие ставки.
-    #[allow(dead_code)] // Может быть полезен для проверок перед некоторыми действиями
-    pub fn has_active_players_with_bets(&self) -> bool {
-        self.player_seats.iter().any(|seat| seat.is_active_in_round)
+    /// Учтенная прибыль дилера по токенам.
+    pub fn dealer_profit_tracker(&self) -> &[ProfitBalance] {
+        &self.dealer_profit_tracker[..self.dealer_profit_tracker_len as usize]
     }
 
     /// Добавляет или обновляет сумму в трекере прибыли дилера для указанного токена.
     pub fn add_dealer_profit(&mut self, token_mint: Pubkey, amount: u64) -> Result<()> {
         if amount == 0 { return Ok(()); } // Не добавлять нулевой профит
 
-        if let Some(balance) = self.dealer_profit_tracker.iter_mut().find(|b| b.mint == token_mint) {
+        let tracked = self.dealer_profit_tracker_len as usize;
+        if let Some(balance) = self.dealer_profit_tracker[..tracked].iter_mut().find(|b| b.mint == token_mint) {
             balance.amount = balance.amount.checked_add(amount)
                 .ok_or(crate::errors::TwentyOneError::ArithmeticOverflow)?;
+        } else if tracked >= MAX_DIFFERENT_TOKENS_IN_PROFIT {
+            // Место под новый тип токена не зарезервировано.
+            // Это не фатальная ошибка для игры, но профит по новому токену не будет отслежен.
+            msg!("Dealer profit tracker is full, cannot add new token type.");
         } else {
-            // Если трекер заполнен, то новый тип токена добавить нельзя,
-            // так как место под него не было зарезервировано.
-            if self.dealer_profit_tracker.len() >= MAX_DIFFERENT_TOKENS_IN_PROFIT {
-                 msg!("Dealer profit tracker is full, cannot add new token type.");
-                 // Это не фатальная ошибка для игры, но профит по новому токену не будет отслежен.
-                 // В идеале, здесь нужно возвращать ошибку, чтобы бэкенд знал о проблеме.
-                 // Например, можно добавить в errors.rs: ProfitTrackerFull
-                 // и вызывать `return err!(crate::errors::TwentyOneError::ProfitTrackerFull);`
-            }
-            self.dealer_profit_tracker.push(TokenBalance { 
-                mint: token_mint, 
-                amount,
-                value_usd: 0,           // Заглушка для совместимости
-                pyth_feed_index: 0,     // Заглушка для совместимости
-            });
+            self.dealer_profit_tracker[tracked] = ProfitBalance { mint: token_mint, amount };
+            self.dealer_profit_tracker_len += 1;
         }
         Ok(())
     }
@@ -474,7 +1417,8 @@ impl GameSession {
     pub fn reduce_dealer_profit(&mut self, token_mint: Pubkey, amount: u64) -> Result<()> {
         if amount == 0 { return Ok(()); }
 
-        if let Some(balance) = self.dealer_profit_tracker.iter_mut().find(|b| b.mint == token_mint) {
+        let tracked = self.dealer_profit_tracker_len as usize;
+        if let Some(balance) = self.dealer_profit_tracker[..tracked].iter_mut().find(|b| b.mint == token_mint) {
             balance.amount = balance.amount.checked_sub(amount)
                 .ok_or(crate::errors::TwentyOneError::ArithmeticOverflow)?; // Ошибка, если пытаемся вычесть больше, чем есть
         } else {
@@ -483,6 +1427,418 @@ impl GameSession {
         }
         Ok(())
     }
+
+    /// Суммарный баланс хранилищ игроков стола в токене `token_mint`.
+    pub fn vault_total(&self, token_mint: &Pubkey) -> u64 {
+        self.vault_totals[..self.vault_totals_len as usize].iter()
+            .find(|b| b.mint == *token_mint)
+            .map_or(0, |b| b.amount)
+    }
+
+    /// Есть ли у игроков невыведенные балансы хранилищ.
+    pub fn has_vault_balances(&self) -> bool {
+        self.vault_totals[..self.vault_totals_len as usize].iter().any(|b| b.amount > 0)
+    }
+
+    /// Учитывает зачисление на хранилище игрока (депозит или выплата раунда).
+    /// Токен без учтенного баланса занимает опустевшую запись или новую.
+    pub fn credit_vault_total(&mut self, token_mint: Pubkey, amount: u64) -> Result<()> {
+        if amount == 0 { return Ok(()); }
+
+        let tracked = self.vault_totals_len as usize;
+        if let Some(balance) = self.vault_totals[..tracked].iter_mut().find(|b| b.mint == token_mint) {
+            balance.amount = balance.amount.checked_add(amount)
+                .ok_or(crate::errors::TwentyOneError::ArithmeticOverflow)?;
+        } else if let Some(balance) = self.vault_totals[..tracked].iter_mut().find(|b| b.amount == 0) {
+            *balance = ProfitBalance { mint: token_mint, amount };
+        } else if tracked >= MAX_VAULT_MINTS {
+            // В отличие от прибыли дилера, неучтенный баланс хранилища дилер смог бы вывести.
+            return err!(crate::errors::TwentyOneError::VaultTokensLimitReached);
+        } else {
+            self.vault_totals[tracked] = ProfitBalance { mint: token_mint, amount };
+            self.vault_totals_len += 1;
+        }
+        Ok(())
+    }
+
+    /// Учитывает списание с хранилища игрока (вывод или ставка из хранилища).
+    pub fn debit_vault_total(&mut self, token_mint: Pubkey, amount: u64) -> Result<()> {
+        if amount == 0 { return Ok(()); }
+
+        let tracked = self.vault_totals_len as usize;
+        let balance = self.vault_totals[..tracked].iter_mut().find(|b| b.mint == token_mint)
+            .ok_or(crate::errors::TwentyOneError::InsufficientVaultBalance)?;
+        balance.amount = balance.amount.checked_sub(amount)
+            .ok_or(crate::errors::TwentyOneError::InsufficientVaultBalance)?;
+        Ok(())
+    }
+
+    /// Проверяет, что дилер выводит `amount` токена `token_mint` только из своей части эскроу `escrow`
+    /// с балансом `escrow_amount`: балансы хранилищ игроков, нерассчитанные ставки и, на USDC-эскроу,
+    /// остаток залога остаются на эскроу. Ставки не учитываются по токенам, поэтому пока они есть, вывод запрещен целиком.
+    pub fn verify_dealer_withdrawal(&self, escrow: &Pubkey, token_mint: &Pubkey, escrow_amount: u64, amount: u64) -> Result<()> {
+        if self.seats_with_bets != 0 {
+            return err!(crate::errors::TwentyOneError::WithdrawalWithPendingBets);
+        }
+        let collateral = if *escrow == self.dealer_usdc_escrow { self.dealer_collateral_balance } else { 0 };
+        let available = escrow_amount
+            .saturating_sub(self.vault_total(token_mint))
+            .saturating_sub(collateral);
+        if amount > available {
+            return err!(crate::errors::TwentyOneError::WithdrawalExceedsDealerBalance);
+        }
+        Ok(())
+    }
+
+    /// Списывает выплату выигрыша в USDC с остатка залога дилера.
+    pub fn pay_from_collateral(&mut self, amount: u64) -> Result<()> {
+        self.dealer_collateral_balance = self.dealer_collateral_balance.checked_sub(amount)
+            .ok_or(crate::errors::TwentyOneError::CollateralBalanceExceeded)?;
+        Ok(())
+    }
+}
+
+/// Архивная запись одного раунда для последующего аудита.
+/// PDA с сидами [ROUND_RECORD_SEED, game_session, round_id], создается по желанию бэкенда в `finalize_round`.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct RoundRecord {
+    pub game_session: Pubkey,              // Стол, к которому относится раунд.
+    pub round_id: u64,                     // Номер раунда.
+    pub shoe_id: u64,                      // Номер шуза, из которого раздавался раунд.
+    pub deck_commitment: [u8; 32],         // Корень Меркла этого шуза (сверяется с сидом из `ShoeRevealed`).
+    // Элементы сида шуза: по раскрытому секрету s проверяется sha256(s) == shuffle_secret_commitment,
+    // сид sha256(s || shuffle_entropy) пересобирает шуз с корнем `deck_commitment`.
+    pub shuffle_secret_commitment: [u8; 32],
+    pub shuffle_entropy: [u8; 32],
+    #[max_len(MAX_CARDS_IN_HAND)]
+    pub dealer_hand: Vec<Card>,            // Итоговая рука дилера.
+    pub dealer_score: u8,                  // Итоговые очки дилера.
+    #[max_len(MAX_ROUND_RESULTS)]
+    pub results: Vec<PlayerHandResult>,    // Итоговые руки и выплаты игроков.
+    pub bump: u8,
+}
+
+impl RoundRecord {
+    // Размер аккаунта: дискриминатор + Borsh-размер полей при максимальном заполнении векторов.
+    pub const CALCULATED_LEN: usize = RoundRecord::DISCRIMINATOR.len() + RoundRecord::INIT_SPACE;
+}
+
+/// Ставка зрителя "за спиной" игрока: повторяет исход бокса места, но не дает права на действия.
+/// PDA с сидами [BET_BEHIND_SEED, game_session, seat_index, spot_index, backer], живет один раунд
+/// и закрывается в `finalize_round`, рента возвращается зрителю.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct BetBehind {
+    pub game_session: Pubkey,              // Стол, к которому относится ставка.
+    pub backer: Pubkey,                    // Кошелек зрителя.
+    pub round_id: u64,                     // Раунд, на который сделана ставка.
+    pub seat_index: u8,                    // Место игрока, за которым стоит зритель.
+    pub spot_index: u8,                    // Бокс этого места.
+    pub token_mint: Pubkey,                // Минт токена ставки.
+    pub amount: u64,                       // Сумма ставки в UI-единицах токена.
+    pub bet_usd_value: u64,                // Проверенная стоимость ставки в USD (в наименьших единицах).
+    pub bump: u8,
+}
+
+impl BetBehind {
+    pub const CALCULATED_LEN: usize = BetBehind::DISCRIMINATOR.len() + BetBehind::INIT_SPACE;
+}
+
+/// Хранилище игрока за столом в одном токене: PDA с сидами [PLAYER_VAULT_SEED, game_session, player, token_mint].
+/// Внесенные токены лежат на эскроу ставок стола, ставки списываются и выплаты зачисляются без переводов токенов.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct PlayerVault {
+    pub game_session: Pubkey,              // Стол, к которому относится хранилище.
+    pub player: Pubkey,                    // Владелец, единственный, кто может ставить и выводить.
+    pub token_mint: Pubkey,                // Минт токена хранилища.
+    pub balance: u64,                      // Свободный баланс в UI-единицах токена (без ставок в игре).
+    pub bump: u8,
+}
+
+impl PlayerVault {
+    pub const CALCULATED_LEN: usize = PlayerVault::DISCRIMINATOR.len() + PlayerVault::INIT_SPACE;
+
+    pub fn debit(&mut self, amount: u64) -> Result<()> {
+        self.balance = self.balance.checked_sub(amount).ok_or(crate::errors::TwentyOneError::InsufficientVaultBalance)?;
+        Ok(())
+    }
+
+    pub fn credit(&mut self, amount: u64) -> Result<()> {
+        self.balance = self.balance.checked_add(amount).ok_or(crate::errors::TwentyOneError::ArithmeticOverflow)?;
+        Ok(())
+    }
+}
+
+/// Этап турнира.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TournamentStatus {
+    #[default]
+    Registration, // Прием участников
+    Running,      // Раунды турнира
+    Finished,     // Все раунды сыграны, ожидается `finalize_tournament`
+}
+
+/// Участник турнира и его стек фишек.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TournamentEntrant {
+    pub player: Pubkey,
+    pub chips: u64,
+}
+
+/// Итог участника в `TournamentFinished`: места по порядку, приз у призовых мест.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct TournamentStanding {
+    pub player: Pubkey,
+    pub chips: u64,
+    pub prize: u64,
+}
+
+/// Турнир за столом дилера: участники вносят бай-ин в призовой эскроу и получают одинаковый стек фишек.
+/// Ставки турнира делаются фишками, поэтому токены переводятся только при взносе и выплате призов.
+/// После `total_rounds` раундов места делятся по фишкам (ничьи — в пользу раньше записавшихся).
+/// PDA с сидами [TOURNAMENT_SEED, game_session], закрывается в `finalize_tournament`.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct Tournament {
+    pub game_session: Pubkey,              // Стол турнира.
+    pub buy_in_mint: Pubkey,               // Минт токена бай-ина и призов.
+    pub buy_in_amount: u64,                // Бай-ин в UI-единицах токена.
+    pub starting_chips: u64,               // Стартовый стек фишек каждого участника.
+    pub total_rounds: u16,                 // Сколько раундов играется.
+    pub rounds_played: u16,
+    pub max_entrants: u8,
+    pub status: TournamentStatus,
+    #[max_len(MAX_TOURNAMENT_PAYOUT_PLACES)]
+    pub payout_bps: Vec<u16>,              // Доли призового фонда по местам (в базисных пунктах, в сумме 10000).
+    #[max_len(MAX_TOURNAMENT_ENTRANTS)]
+    pub entrants: Vec<TournamentEntrant>,  // Участники в порядке записи.
+    pub bump: u8,
+    pub prize_escrow_bump: u8,
+}
+
+impl Tournament {
+    pub const CALCULATED_LEN: usize = Tournament::DISCRIMINATOR.len() + Tournament::INIT_SPACE;
+
+    /// Доли призов: от одного до `MAX_TOURNAMENT_PAYOUT_PLACES` мест, каждое с ненулевой долей, в сумме 100%.
+    pub fn is_valid_payout(payout_bps: &[u16]) -> bool {
+        !payout_bps.is_empty()
+            && payout_bps.len() <= MAX_TOURNAMENT_PAYOUT_PLACES
+            && payout_bps.iter().all(|bps| *bps > 0)
+            && payout_bps.iter().map(|bps| *bps as u64).sum::<u64>() == BASIS_POINTS_DIVISOR
+    }
+
+    fn entrant_mut(&mut self, player: &Pubkey) -> Result<&mut TournamentEntrant> {
+        self.entrants.iter_mut()
+            .find(|entrant| entrant.player == *player)
+            .ok_or_else(|| error!(crate::errors::TwentyOneError::NotTournamentEntrant))
+    }
+
+    /// Списывает фишки участника на ставку.
+    pub fn debit_chips(&mut self, player: &Pubkey, amount: u64) -> Result<()> {
+        let entrant = self.entrant_mut(player)?;
+        entrant.chips = entrant.chips.checked_sub(amount)
+            .ok_or(crate::errors::TwentyOneError::InsufficientChips)?;
+        Ok(())
+    }
+
+    /// Зачисляет участнику выплату по ставке.
+    pub fn credit_chips(&mut self, player: &Pubkey, amount: u64) -> Result<()> {
+        let entrant = self.entrant_mut(player)?;
+        entrant.chips = entrant.chips.checked_add(amount)
+            .ok_or(crate::errors::TwentyOneError::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    /// Участники по убыванию фишек; при равенстве выше тот, кто записался раньше.
+    pub fn standings(&self) -> Vec<TournamentEntrant> {
+        let mut standings = self.entrants.clone();
+        standings.sort_by_key(|entrant| std::cmp::Reverse(entrant.chips)); // Сортировка устойчива: порядок записи сохраняется
+        standings
+    }
+
+    /// Призы по местам из фонда `prize_pool`. Остаток от округления получает первое место.
+    pub fn prizes(&self, prize_pool: u64) -> Result<Vec<u64>> {
+        let mut prizes = self.payout_bps.iter()
+            .map(|bps| ((prize_pool as u128 * *bps as u128) / BASIS_POINTS_DIVISOR as u128) as u64)
+            .collect::<Vec<_>>();
+        let paid = prizes.iter().sum::<u64>();
+        let first = prizes.first_mut().ok_or(crate::errors::TwentyOneError::InvalidTournamentConfig)?;
+        *first = first.checked_add(prize_pool - paid).ok_or(crate::errors::TwentyOneError::ArithmeticOverflow)?;
+        Ok(prizes)
+    }
+}
+
+// --- УСТАРЕВШИЕ ФОРМАТЫ АККАУНТОВ (ТОЛЬКО ДЛЯ МИГРАЦИИ) ---
+// Столы версии 0, созданные до перехода на zero-copy, хранят GameSession в Borsh с тем же дискриминатором.
+// `migrate_game_session` читает их через эти структуры и переписывает в фиксированную раскладку.
+// Конфиги версий 0 и 1 не содержат администратора и минтов залога и обновляются в `migrate_authority_config`.
+
+/// Рука в устаревшем Borsh-формате.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
+pub struct LegacyHand {
+    pub cards: Vec<Card>,
+    pub status: HandStatus,
+    pub bet_multiplier_x100: u16,
+    pub token_mint_for_bet: Pubkey,
+    pub original_bet_amount: u64,
+}
+
+/// Место игрока в устаревшем Borsh-формате.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
+pub struct LegacyPlayerSeat {
+    pub player_pubkey: Option<Pubkey>,
+    pub is_active_in_round: bool,
+    pub hands: Vec<LegacyHand>,
+    pub current_bet_token_mint: Option<Pubkey>,
+    pub current_bet_amount_staked_ui: u64,
+    pub current_bet_usd_value: u64,
+}
+
+/// TableAuthorityConfig версии 0 (без поля `version`). Версия 1 отличается только байтом `version` в конце.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Debug)]
+pub struct LegacyTableAuthorityConfig {
+    pub backend_authority: Pubkey,
+    pub bump: u8,
+}
+
+impl LegacyTableAuthorityConfig {
+    pub const CALCULATED_LEN: usize = TableAuthorityConfig::DISCRIMINATOR.len() + LegacyTableAuthorityConfig::INIT_SPACE;
+    pub const V1_LEN: usize = LegacyTableAuthorityConfig::CALCULATED_LEN + 1;
+}
+
+/// GameSession в устаревшем Borsh-формате (данные после дискриминатора).
+/// Шуз хранился целиком и открыто в `deck`, номеров раундов еще не было.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct LegacyGameSession {
+    pub table_name: String,
+    pub dealer: Pubkey,
+    pub dealer_usdc_escrow: Pubkey,
+    pub dealer_locked_usdc_amount: u64,
+    pub min_bet_usd_equivalent: u64,
+    pub max_bet_usd_equivalent: u64,
+    pub min_accepted_token_liquidity: u64,
+    pub game_state: GameState,
+    pub deck: Vec<Card>,
+    pub current_deck_index: u16,
+    pub dealer_hand: LegacyHand,
+    pub player_seats: Vec<LegacyPlayerSeat>,
+    pub dealer_profit_tracker: Vec<TokenBalance>,
+    pub seed_elements_hash: [u8; 32],
+    pub current_turn_seat_index: Option<u8>,
+    pub current_turn_hand_index: Option<u8>,
+    pub current_turn_start_timestamp: Option<i64>,
+    pub closing_down: bool,
+    pub next_shuffle_commitment: Option<[u8; 32]>,
+    pub bump: u8,
+    pub dealer_usdc_escrow_bump: u8,
+}
+
+impl LegacyGameSession {
+    /// Размер, с которым создавались столы версии 0: место под имя из 16 байт, шуз из 208 карт,
+    /// 6 мест по 2 руки и 50 токенов прибыли дилера (вместе с дискриминатором).
+    pub const CALCULATED_LEN: usize = 3983;
+
+    /// Читает стол версии 0 из сырых данных аккаунта (вместе с дискриминатором).
+    pub fn from_account_data(data: &[u8]) -> Result<Self> {
+        if data.len() < 8 || data[..8] != *GameSession::DISCRIMINATOR {
+            return err!(anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch);
+        }
+        if data.len() != Self::CALCULATED_LEN {
+            return err!(crate::errors::TwentyOneError::LegacyGameSessionInvalid);
+        }
+        Self::deserialize(&mut &data[8..])
+            .map_err(|_| error!(crate::errors::TwentyOneError::LegacyGameSessionInvalid))
+    }
+}
+
+impl GameSession {
+    /// Переписывает данные аккаунта, уже увеличенного до `CALCULATED_LEN`, в текущую раскладку.
+    /// Дискриминатор сохраняется, остальные байты заполняются заново из `legacy`.
+    pub fn upgrade_from_v0(
+        data: &mut [u8],
+        legacy: &LegacyGameSession,
+        collateral_escrow: Pubkey,
+        collateral_escrow_bump: u8,
+        shuffle_commitment: [u8; 32],
+        commitment_slot: u64,
+    ) -> Result<()> {
+        if data.len() != Self::CALCULATED_LEN {
+            return err!(crate::errors::TwentyOneError::LegacyGameSessionInvalid);
+        }
+        data[8..].fill(0);
+        let game_session: &mut GameSession = bytemuck::from_bytes_mut(&mut data[8..]);
+        game_session.migrate_from_legacy(legacy)?;
+        // Стол версии 0 не записывал адрес эскроу залога, а его коммитмент тасования относится
+        // к прежней схеме сида, поэтому оба значения передает бэкенд при миграции.
+        game_session.dealer_usdc_escrow = collateral_escrow;
+        game_session.dealer_usdc_escrow_bump = collateral_escrow_bump;
+        game_session.set_next_shuffle_commitment(Some(shuffle_commitment));
+        game_session.next_shuffle_commitment_slot = commitment_slot;
+        Ok(())
+    }
+
+    /// Обновляет zero-copy раскладку версии `from_version` в данных аккаунта, уже увеличенного до `CALCULATED_LEN`.
+    /// Поля, дописанные после `from_version`, обнуляются: стол остается классическим со стандартным шузом
+    /// и не принимает побочные ставки.
+    pub fn upgrade_appended_fields(data: &mut [u8], from_version: u8) -> Result<()> {
+        let old_len = Self::layout_len(from_version)
+            .filter(|_| from_version < Self::VERSION)
+            .ok_or(crate::errors::TwentyOneError::AccountVersionUnsupported)?;
+        if data.len() != Self::CALCULATED_LEN {
+            return err!(crate::errors::TwentyOneError::AccountVersionUnsupported);
+        }
+        data[old_len..].fill(0);
+        let game_session: &mut GameSession = bytemuck::from_bytes_mut(&mut data[8..]);
+        if from_version < 5 {
+            // До версии 5 выигрыши в USDC не списывались с залога: остаток равен всему залогу.
+            game_session.dealer_collateral_balance = game_session.dealer_locked_usdc_amount;
+        }
+        game_session.version = Self::VERSION;
+        Ok(())
+    }
+
+    /// Заполняет только что размеченный zero-copy аккаунт данными стола в устаревшем формате.
+    /// Места игроков теперь отдельные PDA, поэтому переносится только стол без идущего раунда:
+    /// сидевшие игроки садятся заново через `join_table`. Открыто хранившийся шуз не переносится:
+    /// стол начинает с новой перетасовки через `lock_shuffle_entropy` и `commit_shoe`.
+    pub fn migrate_from_legacy(&mut self, legacy: &LegacyGameSession) -> Result<()> {
+        if legacy.player_seats.len() > MAX_PLAYERS_LIMIT as usize
+            || legacy.dealer_profit_tracker.len() > MAX_DIFFERENT_TOKENS_IN_PROFIT
+        {
+            return err!(crate::errors::TwentyOneError::LegacyGameSessionInvalid);
+        }
+        if legacy.game_state != GameState::AcceptingBets
+            || legacy.player_seats.iter().any(|seat| seat.is_active_in_round)
+        {
+            return err!(crate::errors::TwentyOneError::LegacyTableRoundInProgress);
+        }
+
+        self.set_table_name(&legacy.table_name)?;
+        self.dealer = legacy.dealer;
+        self.dealer_locked_usdc_amount = legacy.dealer_locked_usdc_amount;
+        self.dealer_collateral_balance = legacy.dealer_locked_usdc_amount;
+        self.min_bet_usd_equivalent = legacy.min_bet_usd_equivalent;
+        self.max_bet_usd_equivalent = legacy.max_bet_usd_equivalent;
+        self.min_accepted_token_liquidity = legacy.min_accepted_token_liquidity;
+
+        self.set_game_state(legacy.game_state);
+        let shoe_rules = ShoeRules::default(); // До настраиваемого шуза все столы играли 4 колодами
+        self.set_shoe_rules(shoe_rules)?;
+        self.current_deck_index = shoe_rules.total_cards();
+
+        self.set_seat_count(legacy.player_seats.len() as u8)
+            .map_err(|_| error!(crate::errors::TwentyOneError::LegacyGameSessionInvalid))?;
+        for balance in &legacy.dealer_profit_tracker {
+            self.add_dealer_profit(balance.mint, balance.amount)?;
+        }
+        self.set_closing_down(legacy.closing_down);
+
+        self.bump = legacy.bump;
+        self.version = Self::VERSION;
+        Ok(())
+    }
 }
 
 // --- НОВЫЕ СТРУКТУРЫ И ПЕРЕЧИСЛЕНИЯ, ПЕРЕНЕСЕННЫЕ ИЗ LIB.RS ---
@@ -500,22 +1856,28 @@ pub enum PlayerActionType {
     Stand,
     DoubleDown,
     Split,
+    Surrender,
+    Rescue,
 }
 
-#[derive(Clone, Debug, PartialEq, AnchorSerialize, AnchorDeserialize)]
+#[derive(Clone, Debug, PartialEq, AnchorSerialize, AnchorDeserialize, InitSpace)]
 pub enum HandOutcome {
     Win,              // Обычная победа (1:1)
     Loss,             // Проигрыш
     Push,             // Ничья
     BlackjackWin,     // Блэкджек игрока (3:2)
     BlackjackPush,    // Блэкджек у обоих (возврат ставки)
+    Surrender,        // Поздний отказ (возврат половины ставки)
+    Rescue,           // Удвоение забрано (возврат удвоения, первоначальная ставка проиграна)
+    BonusWin,         // 21 с бонусной выплатой Spanish 21 (5+ карт, 6-7-8, 7-7-7)
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Debug)]
 pub struct PlayerHandResult {
     pub player: Pubkey,
     pub seat_index: u8,
     pub hand_index: u8,
+    #[max_len(MAX_CARDS_IN_HAND)]
     pub hand_cards: Vec<Card>,
     pub hand_score: u8,
     pub outcome: HandOutcome,
@@ -526,9 +1888,11 @@ pub struct PlayerHandResult {
 pub struct InitialPlayerHand {
     pub player: Pubkey,
     pub seat_index: u8,
+    pub spot_index: u8,
     pub hand: Vec<Card>,
 }
 
+#[allow(dead_code)] // Формат выплаты, которым пользуется бэкенд
 #[derive(Clone, Debug, AnchorSerialize, AnchorDeserialize)]
 pub struct Payout {
     pub payout_amount_ui: u64,
@@ -558,12 +1922,463 @@ pub struct FinalizeInstruction {
     pub payout_amount_ui: u64,
     /// Ожидаемая цена токена в USD (в наименьших единицах), использованная бэкендом для расчета.
     pub expected_price: i64,
+    /// Выигрыш в USDC для столов с расчетом в USDC (см. `GameSession::settles_winnings_in_usdc`),
+    /// где `payout_amount_ui` — только возврат ставки. За остальными столами 0.
+    pub usdc_payout_amount: u64,
 
     // --- Индексы аккаунтов в `remaining_accounts` ---
-    /// Индекс токен-аккаунта игрока, куда будет отправлена выплата.
+    /// Индекс токен-аккаунта игрока, куда будет отправлена выплата, или его `PlayerVault` за этим столом.
     pub player_token_account_index: u8,
     /// Индекс escrow-счета (PDA), с которого будет производиться выплата.
     pub escrow_account_index: u8,
+    /// Индекс минта токена выплаты: нужен для `transfer_checked`.
+    pub mint_account_index: u8,
     /// Индекс аккаунта с ценой Pyth для токена этой выплаты.
     pub pyth_feed_index: u8,
+    /// Индекс USDC-аккаунта игрока для выигрыша в USDC.
+    pub usdc_token_account_index: u8,
+}
+
+/// Расчет одной ставки зрителя в `finalize_round`. Ставка получает исход первой руки бокса,
+/// за которым стоит, без учета удвоения и сплита, которые делает игрок.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct BetBehindInstruction {
+    /// Исход ставки, по мнению бэкенда. Контракт пересчитает и сверит.
+    pub outcome: HandOutcome,
+    /// Сумма выплаты в UI-единицах токена. Включает возврат ставки и выигрыш.
+    pub payout_amount_ui: u64,
+    /// Ожидаемая цена токена в USD (в наименьших единицах), использованная бэкендом для расчета.
+    pub expected_price: i64,
+    /// Выигрыш в USDC, как в `FinalizeInstruction::usdc_payout_amount`.
+    pub usdc_payout_amount: u64,
+
+    // --- Индексы аккаунтов в `remaining_accounts` ---
+    /// Индекс аккаунта `BetBehind`.
+    pub bet_behind_index: u8,
+    /// Индекс кошелька зрителя, которому возвращается рента закрытого `BetBehind`.
+    pub backer_account_index: u8,
+    /// Индекс токен-аккаунта зрителя, куда будет отправлена выплата, или его `PlayerVault` за этим столом.
+    pub backer_token_account_index: u8,
+    /// Индекс escrow-счета (PDA), с которого будет производиться выплата.
+    pub escrow_account_index: u8,
+    /// Индекс минта токена выплаты: нужен для `transfer_checked`.
+    pub mint_account_index: u8,
+    /// Индекс аккаунта с ценой Pyth для токена этой выплаты.
+    pub pyth_feed_index: u8,
+    /// Индекс USDC-аккаунта зрителя для выигрыша в USDC.
+    pub usdc_token_account_index: u8,
+}
+
+/// Итог ставки зрителя для события `RoundFinished`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct BetBehindResult {
+    pub backer: Pubkey,
+    pub seat_index: u8,
+    pub spot_index: u8,
+    pub outcome: HandOutcome,
+    pub payout: u64,
+}
+
+/// Комбинация побочной ставки, определенная при раздаче (для события `RoundStarted`).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SideBetEvaluation {
+    pub player: Pubkey,
+    pub seat_index: u8,
+    pub spot_index: u8,
+    pub kind: SideBetKind,
+    pub outcome: SideBetOutcome,
+}
+
+/// Расчет одной побочной ставки в `finalize_round`. Комбинация уже определена контрактом при раздаче,
+/// бэкенд только пересчитывает выплату в токен ставки.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SideBetInstruction {
+    /// Индекс места, на котором сделана ставка.
+    pub seat_index: u8,
+    /// Индекс ставки в `PlayerSeat::side_bets`.
+    pub side_bet_index: u8,
+    /// Сумма выплаты в UI-единицах токена: ставка плюс выигрыш по пейтейблу, 0 при проигрыше.
+    /// Контракт сверит ее стоимость в USD с выплатой по пейтейблу через курс Pyth.
+    pub payout_amount_ui: u64,
+    /// Выигрыш в USDC, как в `FinalizeInstruction::usdc_payout_amount`.
+    pub usdc_payout_amount: u64,
+
+    // --- Индексы аккаунтов в `remaining_accounts` ---
+    /// Индекс токен-аккаунта игрока, куда будет отправлена выплата, или его `PlayerVault` за этим столом.
+    pub player_token_account_index: u8,
+    /// Индекс escrow-счета (PDA), с которого будет производиться выплата.
+    pub escrow_account_index: u8,
+    /// Индекс минта токена выплаты: нужен для `transfer_checked`.
+    pub mint_account_index: u8,
+    /// Индекс аккаунта с ценой Pyth для токена этой выплаты.
+    pub pyth_feed_index: u8,
+    /// Индекс USDC-аккаунта игрока для выигрыша в USDC.
+    pub usdc_token_account_index: u8,
+}
+
+/// Итог побочной ставки для события `RoundFinished`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SideBetResult {
+    pub player: Pubkey,
+    pub seat_index: u8,
+    pub spot_index: u8,
+    pub kind: SideBetKind,
+    pub outcome: SideBetOutcome,
+    pub payout: u64,
+}
+
+/// Карта, раскрытая бэкендом, вместе с солью ее листа в дереве Меркла.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct RevealedCard {
+    pub card: Card,
+    pub salt: [u8; CARD_SALT_LEN],
+}
+
+/// Раскрытие подряд идущих карт шуза с доказательством Меркла для диапазона.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct CardRangeReveal {
+    /// Позиция первой раскрытой карты в шузе. Должна совпадать с `current_deck_index`.
+    pub start_index: u16,
+    /// Раскрытые карты в порядке их позиций.
+    pub cards: Vec<RevealedCard>,
+    /// Соседние узлы по уровням снизу вверх: на каждом уровне сначала левый сосед (если диапазон начинается
+    /// с правого ребенка), затем правый (если диапазон заканчивается левым ребенком).
+    pub proof: Vec<[u8; 32]>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Стол версии 0 (Borsh-формат) между раундами, сериализованный программой до перехода на zero-copy:
+    /// игроки на местах 0 и 3, из шуза сдано 57 карт, прибыль дилера в двух токенах.
+    const GAME_SESSION_V0: &[u8] = include_bytes!("../tests/fixtures/game_session_v0.bin");
+
+    #[test]
+    fn v0_game_session_loads_after_migration() {
+        assert_eq!(GameSession::stored_version(GAME_SESSION_V0), 0);
+        assert_eq!(GAME_SESSION_V0.len(), LegacyGameSession::CALCULATED_LEN);
+        let legacy = LegacyGameSession::from_account_data(GAME_SESSION_V0).unwrap();
+        // Раскладка `LegacyGameSession` должна совпадать с исходной байт в байт: повторная сериализация
+        // воспроизводит данные аккаунта, а остаток зарезервированного места пуст.
+        let serialized = legacy.try_to_vec().unwrap();
+        assert_eq!(serialized[..], GAME_SESSION_V0[8..8 + serialized.len()]);
+        assert!(GAME_SESSION_V0[8 + serialized.len()..].iter().all(|byte| *byte == 0));
+        assert!(LegacyGameSession::from_account_data(&GAME_SESSION_V0[..GAME_SESSION_V0.len() - 1]).is_err());
+        assert_eq!(legacy.deck.len(), 208);
+        assert_eq!(legacy.current_deck_index, 57);
+        assert_eq!(legacy.player_seats[3].player_pubkey, Some(Pubkey::new_from_array([3u8; 32])));
+
+        // Данные аккаунтов выровнены по 8 байт, как того требует zero-copy раскладка.
+        let mut buffer = vec![0u64; GameSession::CALCULATED_LEN / 8];
+        let data: &mut [u8] = bytemuck::cast_slice_mut(&mut buffer);
+        data[..8].copy_from_slice(GameSession::DISCRIMINATOR);
+        let collateral_escrow = Pubkey::new_from_array([8u8; 32]);
+        GameSession::upgrade_from_v0(data, &legacy, collateral_escrow, 252, [9u8; 32], 1_000).unwrap();
+
+        assert_eq!(GameSession::stored_version(data), GameSession::VERSION);
+        let game_session: &GameSession = bytemuck::from_bytes(&data[8..]);
+        assert_eq!(game_session.table_name(), "high-rollers");
+        assert_eq!(game_session.dealer, Pubkey::new_from_array([1u8; 32]));
+        assert_eq!(game_session.dealer_locked_usdc_amount, 5_000_000_000);
+        assert_eq!(game_session.dealer_collateral_balance, 5_000_000_000);
+        assert_eq!((game_session.dealer_usdc_escrow, game_session.dealer_usdc_escrow_bump), (collateral_escrow, 252));
+        assert_eq!(game_session.game_state(), GameState::AcceptingBets);
+        assert_eq!(game_session.round_id, 0);
+        assert_eq!(game_session.shoe_rules(), ShoeRules::default());
+        // Открытый шуз версии 0 не переносится: до первого раунда стол перетасовывается заново.
+        assert!(game_session.needs_shuffle());
+        assert_eq!(game_session.active_shoe(), None);
+        assert_eq!(game_session.next_shuffle_commitment(), Some([9u8; 32]));
+        assert_eq!(game_session.next_shuffle_commitment_slot, 1_000);
+        assert_eq!(game_session.seat_count(), 6);
+        assert_eq!(game_session.occupied_seats, 0);
+        assert_eq!(game_session.dealer_profit_tracker().len(), 2);
+        assert_eq!(game_session.dealer_profit_tracker()[0].amount, 1_250);
+        assert_eq!(game_session.dealer_profit_tracker()[1].amount, 80);
+        assert_eq!(game_session.bump, 254);
+    }
+
+    #[test]
+    fn v1_game_session_upgrades_in_place() {
+        let mut game_session: GameSession = bytemuck::Zeroable::zeroed();
+        game_session.version = 1;
+        game_session.set_table_name("high-rollers").unwrap();
+        game_session.round_id = 42;
+        game_session.dealer_locked_usdc_amount = 1_000;
+
+        // Аккаунт версии 1 — префикс текущей раскладки; хвост после увеличения аккаунта может содержать мусор.
+        let v1_len = GameSession::layout_len(1).unwrap();
+        let mut buffer = vec![0u64; GameSession::CALCULATED_LEN / 8];
+        let data: &mut [u8] = bytemuck::cast_slice_mut(&mut buffer);
+        data[..8].copy_from_slice(GameSession::DISCRIMINATOR);
+        data[8..v1_len].copy_from_slice(&bytemuck::bytes_of(&game_session)[..v1_len - 8]);
+        assert_eq!(GameSession::stored_version(&data[..v1_len]), 1);
+        data[v1_len..].fill(u8::MAX);
+
+        GameSession::upgrade_appended_fields(data, 1).unwrap();
+        assert_eq!(GameSession::stored_version(data), GameSession::VERSION);
+        let upgraded: &GameSession = bytemuck::from_bytes(&data[8..]);
+        assert_eq!(upgraded.table_name(), "high-rollers");
+        assert_eq!(upgraded.round_id, 42);
+        assert_eq!(upgraded.side_bet_paytable(), SideBetPaytable::default());
+        assert!(!upgraded.side_bet_paytable().offers(SideBetKind::PerfectPairs));
+        assert_eq!(upgraded.game_variant(), GameVariant::Classic);
+        assert_eq!(upgraded.shoe_rules().composition, DeckComposition::Standard);
+        assert_eq!(upgraded.dealer_collateral_balance, 1_000);
+    }
+
+    #[test]
+    fn usdc_payouts_reduce_collateral_balance() {
+        let mut game_session: GameSession = bytemuck::Zeroable::zeroed();
+        let escrow = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        game_session.dealer_usdc_escrow = escrow;
+        game_session.dealer_collateral_balance = 500;
+
+        game_session.pay_from_collateral(200).unwrap();
+        assert_eq!(game_session.dealer_collateral_balance, 300);
+        assert!(game_session.pay_from_collateral(301).is_err());
+
+        // С USDC-эскроу выводится только то, что сверх остатка залога.
+        assert!(game_session.verify_dealer_withdrawal(&escrow, &mint, 500, 200).is_ok());
+        assert!(game_session.verify_dealer_withdrawal(&escrow, &mint, 500, 201).is_err());
+        assert!(game_session.verify_dealer_withdrawal(&Pubkey::new_unique(), &mint, 500, 500).is_ok());
+
+        game_session.seats_with_bets = 1;
+        assert!(game_session.verify_dealer_withdrawal(&Pubkey::new_unique(), &mint, 500, 1).is_err());
+    }
+
+    fn max_hand_cards() -> Vec<Card> {
+        (0..MAX_CARDS_IN_HAND).map(|i| Card { suit: Suit::ALL[i % 4], rank: Rank::ALL[i % 13] }).collect()
+    }
+
+    #[test]
+    fn dealer_withdrawal_leaves_vault_balances() {
+        let mint = Pubkey::new_unique();
+        let other_mint = Pubkey::new_unique();
+        let escrow = Pubkey::new_unique();
+        let mut game_session: GameSession = bytemuck::Zeroable::zeroed();
+
+        // Эскроу: 100 токенов депозита игрока и 50 прибыли дилера.
+        game_session.credit_vault_total(mint, 100).unwrap();
+        assert!(game_session.has_vault_balances());
+        assert!(game_session.verify_dealer_withdrawal(&escrow, &mint, 150, 51).is_err());
+        game_session.verify_dealer_withdrawal(&escrow, &mint, 150, 50).unwrap();
+        game_session.verify_dealer_withdrawal(&escrow, &other_mint, 150, 150).unwrap();
+
+        // Ставка из хранилища остается на эскроу до расчета раунда.
+        game_session.debit_vault_total(mint, 40).unwrap();
+        game_session.seats_with_bets = 1;
+        assert!(game_session.verify_dealer_withdrawal(&escrow, &mint, 150, 1).is_err());
+        game_session.seats_with_bets = 0;
+
+        assert!(game_session.debit_vault_total(mint, 61).is_err());
+        game_session.debit_vault_total(mint, 60).unwrap();
+        assert!(!game_session.has_vault_balances());
+        assert!(game_session.debit_vault_total(other_mint, 1).is_err());
+
+        // Опустевшая запись переиспользуется, лимит токенов не превышается.
+        for i in 0..MAX_VAULT_MINTS {
+            game_session.credit_vault_total(Pubkey::new_from_array([i as u8 + 1; 32]), 1).unwrap();
+        }
+        assert!(game_session.credit_vault_total(other_mint, 1).is_err());
+    }
+
+    #[test]
+    fn full_hand_rejects_extra_card() {
+        let mut hand = Hand::new(Pubkey::default(), 100, 100);
+        for card in max_hand_cards() {
+            hand.add_card(card).unwrap();
+        }
+        let extra = Card { suit: Suit::Hearts, rank: Rank::Two };
+        assert_eq!(hand.add_card(extra).unwrap_err(), error!(crate::errors::TwentyOneError::HandFull));
+        assert_eq!(hand.card_count(), MAX_CARDS_IN_HAND);
+        assert!(hand.is_full());
+    }
+
+    #[test]
+    fn max_filled_game_session_fits_account() {
+        let mut game_session: GameSession = bytemuck::Zeroable::zeroed();
+        game_session.version = GameSession::VERSION;
+        game_session.set_table_name(&"t".repeat(TABLE_NAME_MAX_LEN)).unwrap();
+        game_session.set_seat_count(MAX_PLAYERS_LIMIT).unwrap();
+        game_session.set_shoe_rules(ShoeRules { num_decks: MAX_NUM_DECKS, ..ShoeRules::default() }).unwrap();
+        game_session.set_side_bet_paytable(SideBetPaytable::from_array([MAX_SIDE_BET_PAYOUT_ODDS; SideBetPaytable::LEN])).unwrap();
+        for card in max_hand_cards() {
+            game_session.dealer_hand.add_card(card).unwrap();
+        }
+        for i in 0..MAX_DIFFERENT_TOKENS_IN_PROFIT {
+            game_session.add_dealer_profit(Pubkey::new_from_array([i as u8 + 1; 32]), u64::MAX).unwrap();
+        }
+        game_session.set_randomness_source(RandomnessSourceKind::VrfAccount(Pubkey::new_from_array([9u8; 32])));
+        game_session.set_current_turn(MAX_PLAYERS_LIMIT - 1, MAX_HANDS_PER_PLAYER as u8 - 1, i64::MAX);
+
+        // Стол создается через CPI в System Program, поэтому аккаунт не может превышать лимит роста данных за инструкцию.
+        const { assert!(GameSession::CALCULATED_LEN <= anchor_lang::solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE) };
+
+        let mut buffer = vec![0u64; GameSession::CALCULATED_LEN / 8];
+        let data: &mut [u8] = bytemuck::cast_slice_mut(&mut buffer);
+        data[..8].copy_from_slice(GameSession::DISCRIMINATOR);
+        data[8..].copy_from_slice(bytemuck::bytes_of(&game_session));
+
+        let stored: &GameSession = bytemuck::from_bytes(&data[8..]);
+        assert_eq!(stored.table_name().len(), TABLE_NAME_MAX_LEN);
+        assert_eq!(stored.dealer_hand.card_count(), MAX_CARDS_IN_HAND);
+        assert_eq!(stored.dealer_profit_tracker().len(), MAX_DIFFERENT_TOKENS_IN_PROFIT);
+        assert_eq!(stored.shoe_rules().total_cards(), MAX_NUM_DECKS as u16 * CARDS_IN_DECK);
+    }
+
+    #[test]
+    fn max_filled_round_record_fits_account() {
+        let record = RoundRecord {
+            game_session: Pubkey::new_unique(),
+            round_id: u64::MAX,
+            shoe_id: u64::MAX,
+            deck_commitment: [u8::MAX; 32],
+            shuffle_secret_commitment: [u8::MAX; 32],
+            shuffle_entropy: [u8::MAX; 32],
+            dealer_hand: max_hand_cards(),
+            dealer_score: u8::MAX,
+            results: (0..MAX_ROUND_RESULTS).map(|i| PlayerHandResult {
+                player: Pubkey::new_unique(),
+                seat_index: (i / MAX_HANDS_PER_PLAYER) as u8,
+                hand_index: (i % MAX_HANDS_PER_PLAYER) as u8,
+                hand_cards: max_hand_cards(),
+                hand_score: u8::MAX,
+                outcome: HandOutcome::BlackjackPush,
+                payout: u64::MAX,
+            }).collect(),
+            bump: u8::MAX,
+        };
+
+        let mut data = Vec::new();
+        record.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), RoundRecord::CALCULATED_LEN);
+        const { assert!(RoundRecord::CALCULATED_LEN <= anchor_lang::solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE) };
+    }
+
+    #[test]
+    fn switch_swaps_second_cards_once() {
+        let card = |rank, suit| Card { rank, suit };
+        let mint = Pubkey::new_unique();
+        let mut seat: PlayerSeat = bytemuck::Zeroable::zeroed();
+        for cards in [[card(Rank::Ace, Suit::Hearts), card(Rank::Six, Suit::Clubs)], [card(Rank::Ten, Suit::Spades), card(Rank::King, Suit::Diamonds)]] {
+            let spot_index = seat.add_spot(Hand::new(mint, 100, 100)).unwrap();
+            let hand = &mut seat.hands_mut()[spot_index as usize];
+            cards.into_iter().for_each(|c| hand.add_card(c).unwrap());
+        }
+
+        seat.switch_second_cards(0, 1).unwrap();
+        let [soft_21, sixteen] = seat.hands() else { panic!("expected two hands") };
+        assert_eq!(soft_21.cards(), vec![card(Rank::Ace, Suit::Hearts), card(Rank::King, Suit::Diamonds)]);
+        assert_eq!(soft_21.status(), HandStatus::Stood);
+        assert_eq!(sixteen.cards(), vec![card(Rank::Ten, Suit::Spades), card(Rank::Six, Suit::Clubs)]);
+        assert_eq!(sixteen.status(), HandStatus::Playing);
+
+        assert!(seat.switch_second_cards(0, 1).is_err());
+        seat.reset_for_new_round();
+        assert!(seat.switch_second_cards(0, 1).is_err());
+    }
+
+    #[test]
+    fn switch_requires_equal_spot_bets() {
+        let card = |rank, suit| Card { rank, suit };
+        let mint = Pubkey::new_unique();
+        let mut seat: PlayerSeat = bytemuck::Zeroable::zeroed();
+        // Оба бокса — блэкджеки: до своего хода такое место тоже может поменять карты.
+        for (bet, cards) in [(100, [card(Rank::Ace, Suit::Hearts), card(Rank::King, Suit::Clubs)]), (250, [card(Rank::Ace, Suit::Spades), card(Rank::Queen, Suit::Diamonds)])] {
+            let spot_index = seat.add_spot(Hand::new(mint, bet, bet)).unwrap();
+            let hand = &mut seat.hands_mut()[spot_index as usize];
+            cards.into_iter().for_each(|c| hand.add_card(c).unwrap());
+            hand.set_status(HandStatus::Blackjack);
+        }
+
+        assert!(seat.can_switch());
+        assert_eq!(seat.switch_second_cards(0, 1).unwrap_err(), error!(crate::errors::TwentyOneError::SwitchBetsNotEqual));
+        seat.hands_mut()[1].original_bet_amount = 100;
+        seat.switch_second_cards(0, 1).unwrap();
+        assert!(seat.hands().iter().all(|hand| hand.status() == HandStatus::Stood));
+        assert!(!seat.can_switch());
+    }
+
+    #[test]
+    fn player_vault_debit_and_credit() {
+        let mut vault = PlayerVault {
+            game_session: Pubkey::new_unique(),
+            player: Pubkey::new_unique(),
+            token_mint: Pubkey::new_unique(),
+            balance: 100,
+            bump: 255,
+        };
+        vault.debit(60).unwrap();
+        assert_eq!(vault.balance, 40);
+        assert!(vault.debit(41).is_err());
+        assert_eq!(vault.balance, 40);
+        vault.credit(120).unwrap();
+        assert_eq!(vault.balance, 160);
+        assert!(vault.credit(u64::MAX).is_err());
+    }
+
+    #[test]
+    fn tournament_standings_and_prizes() {
+        let players = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let mut tournament = Tournament {
+            game_session: Pubkey::new_unique(),
+            buy_in_mint: Pubkey::new_unique(),
+            buy_in_amount: 100,
+            starting_chips: 1000,
+            total_rounds: 10,
+            rounds_played: 0,
+            max_entrants: 3,
+            status: TournamentStatus::Running,
+            payout_bps: vec![6500, 3500],
+            entrants: players.iter().map(|player| TournamentEntrant { player: *player, chips: 1000 }).collect(),
+            bump: 0,
+            prize_escrow_bump: 0,
+        };
+
+        assert!(tournament.debit_chips(&players[1], 1001).is_err());
+        assert!(tournament.debit_chips(&Pubkey::new_unique(), 1).is_err());
+        tournament.debit_chips(&players[1], 400).unwrap();
+        tournament.debit_chips(&players[0], 400).unwrap();
+        tournament.credit_chips(&players[2], 200).unwrap();
+
+        // 1200 у третьего; у первого и второго по 600, выше первый как записавшийся раньше.
+        let order = tournament.standings().iter().map(|entrant| entrant.player).collect::<Vec<_>>();
+        assert_eq!(order, vec![players[2], players[0], players[1]]);
+        // 65% и 35% от 301: остаток от округления достается первому месту.
+        assert_eq!(tournament.prizes(301).unwrap(), vec![196, 105]);
+
+        assert!(Tournament::is_valid_payout(&[10000]));
+        assert!(!Tournament::is_valid_payout(&[5000, 4000]));
+        assert!(!Tournament::is_valid_payout(&[5000, 5000, 0]));
+        assert!(!Tournament::is_valid_payout(&[2500; 4]));
+    }
+
+    #[test]
+    fn authority_config_fits_account() {
+        let collateral_mints: Vec<Pubkey> = (0..MAX_COLLATERAL_MINTS).map(|_| Pubkey::new_unique()).collect();
+        assert!(TableAuthorityConfig::is_valid_collateral_mints(&collateral_mints));
+        let config = TableAuthorityConfig {
+            backend_authority: Pubkey::new_unique(),
+            bump: u8::MAX,
+            version: TableAuthorityConfig::VERSION,
+            admin: Pubkey::new_unique(),
+            collateral_mints,
+        };
+        let mut data = Vec::new();
+        config.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), TableAuthorityConfig::CALCULATED_LEN);
+    }
+
+    #[test]
+    fn collateral_mints_reject_duplicates_and_overflow() {
+        let mint = Pubkey::new_unique();
+        assert!(TableAuthorityConfig::is_valid_collateral_mints(&[]));
+        assert!(!TableAuthorityConfig::is_valid_collateral_mints(&[mint, mint]));
+        assert!(!TableAuthorityConfig::is_valid_collateral_mints(&[Pubkey::default()]));
+        let too_many: Vec<Pubkey> = (0..=MAX_COLLATERAL_MINTS).map(|_| Pubkey::new_unique()).collect();
+        assert!(!TableAuthorityConfig::is_valid_collateral_mints(&too_many));
+    }
 }
\ No newline at end of file
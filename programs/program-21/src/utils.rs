@@ -1,13 +1,24 @@
 use anchor_lang::prelude::*;
-use crate::state::{Card, Suit, Rank, GameState, GameSession, HandStatus, Hand, HandOutcome};
+use crate::state::{
+    Card, Suit, Rank, GameState, GameSession, PlayerSeat, HandStatus, Hand, HandOutcome, ShoeRules, SideBetKind, SideBetOutcome,
+    DeckComposition, GameVariant,
+};
 use crate::constants::{
-    NUM_DECKS, TABLE_NAME_MIN_LEN, TABLE_NAME_MAX_LEN, BLACKJACK_PAYOUT_PROFIT_NUMERATOR, BLACKJACK_PAYOUT_PROFIT_DENOMINATOR, MAX_PLAYERS_LIMIT,
+    CARD_SALT_LEN, SLOT_HASH_MIX_DEPTH, SWITCHBOARD_ON_DEMAND_PROGRAM_ID,
+    VRF_SEED_SLOT_OFFSET, VRF_REVEAL_SLOT_OFFSET, VRF_VALUE_OFFSET, TABLE_NAME_MIN_LEN, TABLE_NAME_MAX_LEN, BLACKJACK_PAYOUT_PROFIT_NUMERATOR, BLACKJACK_PAYOUT_PROFIT_DENOMINATOR, MAX_PLAYERS_LIMIT,
+    PYTH_RECEIVER_PROGRAM_ID, PAYOUT_PRICE_SLIPPAGE_BPS, SPANISH_21_FIVE_CARD_BONUS, SPANISH_21_SIX_CARD_BONUS,
+    SPANISH_21_SEVEN_CARD_BONUS, SPANISH_21_MIXED_TRIPLE_BONUS, SPANISH_21_SUITED_TRIPLE_BONUS, SPANISH_21_SPADED_TRIPLE_BONUS,
+    SUPPORTED_MINT_EXTENSIONS,
 };
 use crate::errors::TwentyOneError;
+use anchor_spl::token_interface::{self, Mint, SyncNative, TransferChecked};
+use anchor_spl::token_2022::spl_token_2022::{self, extension::{BaseStateWithExtensions, StateWithExtensions}};
+use anchor_lang::solana_program::hash::hashv;
 use sha2::{Sha256, Digest};
 use rand::seq::SliceRandom;
 use rand::SeedableRng;
 use rand_chacha::ChaCha8Rng;
+use pyth_sdk::PriceFeed;
 
 /// Нормализует и проверяет имя стола.
 pub fn normalize_and_validate_table_name(name: &str) -> Result<String> {
@@ -18,7 +29,7 @@ pub fn normalize_and_validate_table_name(name: &str) -> Result<String> {
 
     let normalized: String = trimmed.to_lowercase();
 
-    if !normalized.chars().all(|c| c.is_ascii_lowercase() || c.is_digit(10) || c == '-') {
+    if !normalized.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-') {
         return err!(TwentyOneError::TableNameInvalidChars);
     }
     if normalized.starts_with('-') || normalized.ends_with('-') {
@@ -31,62 +42,397 @@ pub fn normalize_and_validate_table_name(name: &str) -> Result<String> {
 }
 
 // Создание стандартной колоды
-pub fn create_standard_shoe(num_decks: u8) -> Vec<Card> {
-    let mut shoe = Vec::with_capacity((num_decks as u16 * NUM_DECKS as u16) as usize);
+pub fn create_standard_shoe(num_decks: u8, composition: DeckComposition) -> Vec<Card> {
+    let mut shoe = Vec::with_capacity((num_decks as u16 * composition.cards_per_deck()) as usize);
     let suits = [Suit::Hearts, Suit::Diamonds, Suit::Clubs, Suit::Spades];
-    let ranks = [
-        Rank::Two, Rank::Three, Rank::Four, Rank::Five, Rank::Six, Rank::Seven,
-        Rank::Eight, Rank::Nine, Rank::Ten, Rank::Jack, Rank::Queen, Rank::King, Rank::Ace,
-    ];
     for _ in 0..num_decks {
         for &suit in suits.iter() {
-            for &rank in ranks.iter() { shoe.push(Card { suit, rank }); }
+            for rank in composition.ranks() { shoe.push(Card { suit, rank }); }
         }
     }
     shoe
 }
 
 // Тасование Фишера-Йейтса
-pub fn fisher_yates_shuffle(deck: &mut Vec<Card>, seed_hash: [u8; 32]) {
+pub fn fisher_yates_shuffle(deck: &mut [Card], seed_hash: [u8; 32]) {
     if deck.is_empty() { return; }
     let mut rng = ChaCha8Rng::from_seed(seed_hash);
     deck.shuffle(&mut rng);
 }
 
-// Генерация хеша для сида тасования
-pub fn generate_shuffle_seed_hash(
-    slot: u64,
-    timestamp: i64,
-    dealer_pubkey: &Pubkey,
-    nonce: u64,
-) -> [u8; 32] {
+/// Воспроизводит порядок карт в шузе по раскрытому сиду.
+/// Это та же функция, которой пользуется контракт при перетасовке, поэтому офчейн-аудитор,
+/// получив сид из события `DeckShuffled`, получает ровно ту колоду, из которой раздавались карты.
+pub fn verify_shoe(seed: [u8; 32], rules: ShoeRules) -> Vec<Card> {
+    let mut shoe = create_standard_shoe(rules.num_decks, rules.composition);
+    fisher_yates_shuffle(&mut shoe, seed);
+    shoe
+}
+
+/// Хеш порядка карт в колоде: sha256 от упакованных карт (`Card::to_packed`) подряд.
+pub fn hash_deck_order(deck: &[Card]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for card in deck {
+        hasher.update([card.to_packed()]);
+    }
+    hasher.finalize().into()
+}
+
+// --- Коммитмент скрытого шуза ---
+// Листья и узлы хешируются через `hashv`, который ончейн исполняется сисколлом sol_sha256:
+// проверка целого шуза при его выводе из игры требует нескольких сотен хешей.
+
+/// Соль листа карты на позиции `index`: первые 16 байт sha256("card_salt" || secret || index).
+/// Соли выводятся из секрета шуза, поэтому после его раскрытия дерево можно пересобрать целиком.
+pub fn card_salt(secret: &[u8; 32], index: u16) -> [u8; CARD_SALT_LEN] {
+    let digest = hashv(&[b"card_salt", secret, &index.to_le_bytes()]).to_bytes();
+    let mut salt = [0u8; CARD_SALT_LEN];
+    salt.copy_from_slice(&digest[..CARD_SALT_LEN]);
+    salt
+}
+
+/// Лист дерева: sha256(0x00 || index || salt || packed_card).
+pub fn card_leaf_hash(index: u16, salt: &[u8; CARD_SALT_LEN], card: &Card) -> [u8; 32] {
+    hashv(&[&[0u8], &index.to_le_bytes(), salt, &[card.to_packed()]]).to_bytes()
+}
+
+/// Внутренний узел дерева: sha256(0x01 || left || right).
+pub fn merkle_node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    hashv(&[&[1u8], left, right]).to_bytes()
+}
+
+/// Корень Меркла шуза. Дерево дополняется нулевыми листьями до ближайшей степени двойки (см. `ShoeRules::merkle_depth`).
+pub fn compute_deck_merkle_root(deck: &[Card], secret: &[u8; 32]) -> [u8; 32] {
+    let mut level: Vec<[u8; 32]> = (0..deck.len().next_power_of_two())
+        .map(|index| match deck.get(index) {
+            Some(card) => card_leaf_hash(index as u16, &card_salt(secret, index as u16), card),
+            None => [0u8; 32],
+        })
+        .collect();
+    while level.len() > 1 {
+        level = level.chunks(2).map(|pair| merkle_node_hash(&pair[0], &pair[1])).collect();
+    }
+    level[0]
+}
+
+/// Проверяет доказательство Меркла для подряд идущих листьев, начиная с позиции `start_index`,
+/// в дереве глубины `depth`. Порядок узлов в `proof` описан в `CardRangeReveal`.
+pub fn verify_card_range(root: &[u8; 32], depth: u32, start_index: u16, leaves: &[[u8; 32]], proof: &[[u8; 32]]) -> bool {
+    if leaves.is_empty() || start_index as usize + leaves.len() > 1usize << depth {
+        return false;
+    }
+    let mut proof_iter = proof.iter();
+    let mut level: Vec<[u8; 32]> = leaves.to_vec();
+    let mut first_index = start_index as usize;
+
+    for _ in 0..depth {
+        if first_index % 2 == 1 {
+            match proof_iter.next() {
+                Some(left_sibling) => level.insert(0, *left_sibling),
+                None => return false,
+            }
+            first_index -= 1;
+        }
+        if level.len() % 2 == 1 {
+            match proof_iter.next() {
+                Some(right_sibling) => level.push(*right_sibling),
+                None => return false,
+            }
+        }
+        level = level.chunks(2).map(|pair| merkle_node_hash(&pair[0], &pair[1])).collect();
+        first_index /= 2;
+    }
+
+    proof_iter.next().is_none() && level.len() == 1 && level[0] == *root
+}
+
+/// Хеш-коммитмент секрета бэкенда для следующей перетасовки: sha256(secret).
+pub fn hash_shuffle_secret(secret: &[u8; 32]) -> [u8; 32] {
+    Sha256::digest(secret).into()
+}
+
+/// Добавляет сид игрока в накопитель сидов: sha256(accumulator || player || seed).
+pub fn accumulate_player_seed(accumulator: &[u8; 32], player: &Pubkey, seed: &[u8; 32]) -> [u8; 32] {
     let mut hasher = Sha256::new();
-    hasher.update(slot.to_le_bytes());
-    hasher.update(timestamp.to_le_bytes());
-    hasher.update(dealer_pubkey.as_ref());
-    hasher.update(nonce.to_le_bytes());
+    hasher.update(accumulator);
+    hasher.update(player.as_ref());
+    hasher.update(seed);
     hasher.finalize().into()
 }
 
+/// Итоговый сид тасования: sha256(secret || entropy).
+/// Секрет и энтропия публикуются после вывода шуза из игры, поэтому сид может проверить любой.
+pub fn generate_shuffle_seed_hash(secret: &[u8; 32], entropy: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(secret);
+    hasher.update(entropy);
+    hasher.finalize().into()
+}
+
+/// Читает до `count` самых свежих хешей из сисвара SlotHashes без десериализации всего списка.
+/// Формат данных: u64 (количество записей), затем записи (u64 slot, [u8; 32] hash), самая свежая первой.
+pub fn read_recent_slot_hashes(slot_hashes: &AccountInfo, count: usize) -> Result<Vec<[u8; 32]>> {
+    if *slot_hashes.key != anchor_lang::solana_program::sysvar::slot_hashes::ID {
+        return err!(TwentyOneError::InvalidSlotHashesAccount);
+    }
+    let data = slot_hashes.try_borrow_data()?;
+    let entries = data.get(..8)
+        .map(|len_bytes| u64::from_le_bytes(len_bytes.try_into().unwrap()) as usize)
+        .ok_or(TwentyOneError::InvalidSlotHashesAccount)?;
+    let count = count.min(entries);
+    if count == 0 || data.len() < 8 + count * 40 {
+        return err!(TwentyOneError::InvalidSlotHashesAccount);
+    }
+    Ok((0..count)
+        .map(|i| {
+            let hash_offset = 8 + i * 40 + 8;
+            let mut hash = [0u8; 32];
+            hash.copy_from_slice(&data[hash_offset..hash_offset + 32]);
+            hash
+        })
+        .collect())
+}
+
+/// Читает хеш самого свежего слота из сисвара SlotHashes.
+pub fn read_most_recent_slot_hash(slot_hashes: &AccountInfo) -> Result<[u8; 32]> {
+    Ok(read_recent_slot_hashes(slot_hashes, 1)?[0])
+}
+
+// --- Источники случайности для перетасовки ---
+
+/// Источник публичной энтропии для перетасовки шуза. Энтропия фиксируется в `lock_shuffle_entropy`
+/// и смешивается с заранее закоммиченным секретом бэкенда (см. `generate_shuffle_seed_hash`).
+pub trait RandomnessSource {
+    /// Возвращает энтропию для следующего шуза.
+    fn entropy(&self) -> Result<[u8; 32]>;
+}
+
+/// Коммит-раскрытие: сиды игроков, добавленные после коммита бэкенда, и хеш последнего слота.
+pub struct CommitRevealSource<'a, 'info> {
+    pub player_seeds_hash: [u8; 32],
+    pub slot_hashes: &'a AccountInfo<'info>,
+}
+
+impl RandomnessSource for CommitRevealSource<'_, '_> {
+    fn entropy(&self) -> Result<[u8; 32]> {
+        let recent_slot_hash = read_most_recent_slot_hash(self.slot_hashes)?;
+        let mut hasher = Sha256::new();
+        hasher.update(self.player_seeds_hash);
+        hasher.update(recent_slot_hash);
+        Ok(hasher.finalize().into())
+    }
+}
+
+/// Значение VRF из аккаунта случайности Switchboard On-Demand.
+/// Значение должно быть раскрыто оракулом и запрошено не раньше слота коммита секрета бэкенда.
+pub struct VrfAccountSource<'a, 'info> {
+    pub vrf_account: &'a AccountInfo<'info>,
+    pub expected_account: Pubkey,
+    pub min_seed_slot: u64,
+}
+
+impl RandomnessSource for VrfAccountSource<'_, '_> {
+    fn entropy(&self) -> Result<[u8; 32]> {
+        if *self.vrf_account.key != self.expected_account {
+            return err!(TwentyOneError::RandomnessAccountMismatch);
+        }
+        if *self.vrf_account.owner != SWITCHBOARD_ON_DEMAND_PROGRAM_ID {
+            return err!(TwentyOneError::InvalidRandomnessAccount);
+        }
+        let data = self.vrf_account.try_borrow_data()?;
+        if data.len() < VRF_VALUE_OFFSET + 32 {
+            return err!(TwentyOneError::InvalidRandomnessAccount);
+        }
+        let read_u64 = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+        let seed_slot = read_u64(VRF_SEED_SLOT_OFFSET);
+        let reveal_slot = read_u64(VRF_REVEAL_SLOT_OFFSET);
+        if reveal_slot == 0 || seed_slot < self.min_seed_slot {
+            return err!(TwentyOneError::RandomnessNotFresh);
+        }
+        let mut value = [0u8; 32];
+        value.copy_from_slice(&data[VRF_VALUE_OFFSET..VRF_VALUE_OFFSET + 32]);
+        Ok(value)
+    }
+}
+
+/// Смешивание нескольких последних хешей слотов, без участия игроков и внешних оракулов.
+pub struct SlotHashMixSource<'a, 'info> {
+    pub slot_hashes: &'a AccountInfo<'info>,
+}
+
+impl RandomnessSource for SlotHashMixSource<'_, '_> {
+    fn entropy(&self) -> Result<[u8; 32]> {
+        let mut hasher = Sha256::new();
+        for slot_hash in read_recent_slot_hashes(self.slot_hashes, SLOT_HASH_MIX_DEPTH)? {
+            hasher.update(slot_hash);
+        }
+        Ok(hasher.finalize().into())
+    }
+}
+
 // Проверка, является ли игрок дилером стола
-pub fn verify_dealer_signer(game_session: &Account<GameSession>, dealer_signer: &Signer) -> Result<()> {
+pub fn verify_dealer_signer(game_session: &GameSession, dealer_signer: &Signer) -> Result<()> {
     if game_session.dealer != dealer_signer.key() {
         return err!(TwentyOneError::DealerNotSigner);
     }
     Ok(())
 }
 
+/// Сверяет заявленную бэкендом стоимость суммы в токенах (ставки или выплаты) в USD с ценой Pyth
+/// (с допуском `PAYOUT_PRICE_SLIPPAGE_BPS`).
+pub fn verify_bet_usd_value(price_feed_account: &AccountInfo, amount_staked_ui: u64, usd_value_of_bet: u64) -> Result<()> {
+    if *price_feed_account.owner != PYTH_RECEIVER_PROGRAM_ID { return err!(TwentyOneError::InvalidPriceFeedOwner); }
+
+    let price_feed = PriceFeed::try_from_slice(&price_feed_account.data.borrow())
+        .map_err(|_| error!(TwentyOneError::PriceFeedStale))?;
+    let price = price_feed.get_price_unchecked();
+
+    let calculated_value_usd = (amount_staked_ui as u128)
+        .checked_mul(price.price as u128).ok_or(TwentyOneError::ArithmeticOverflow)?
+        .checked_div(10u128.pow(price.expo.unsigned_abs())).ok_or(TwentyOneError::ArithmeticOverflow)?;
+
+    let slippage_amount = (usd_value_of_bet as u128 * PAYOUT_PRICE_SLIPPAGE_BPS as u128) / 10000;
+    let lower_bound = (usd_value_of_bet as u128).saturating_sub(slippage_amount);
+    let upper_bound = (usd_value_of_bet as u128).saturating_add(slippage_amount);
+    
+    if calculated_value_usd < lower_bound || calculated_value_usd > upper_bound {
+         return err!(TwentyOneError::PayoutCalculationMismatch);
+    }
+    Ok(())
+}
+
+/// Сверяет цену, по которой бэкенд пересчитал выплату, с текущей ценой Pyth (с допуском `PAYOUT_PRICE_SLIPPAGE_BPS`).
+pub fn verify_payout_price(price_feed_account: &AccountInfo, expected_price: i64) -> Result<()> {
+    if *price_feed_account.owner != PYTH_RECEIVER_PROGRAM_ID { return err!(TwentyOneError::InvalidPriceFeedOwner); }
+
+    let price_feed = PriceFeed::try_from_slice(&price_feed_account.data.borrow())
+        .map_err(|_| error!(TwentyOneError::PriceFeedStale))?;
+    let pyth_price = price_feed.get_price_unchecked();
+
+    let price_diff = (pyth_price.price - expected_price).abs();
+    let max_diff = (expected_price * PAYOUT_PRICE_SLIPPAGE_BPS as i64) / 10000;
+    if price_diff > max_diff {
+        return err!(TwentyOneError::PayoutCalculationMismatch);
+    }
+    Ok(())
+}
+
+/// Оборачивает лампорты игрока в WSOL на эскроу стола: перевод на токен-аккаунт эскроу и `sync_native`.
+pub fn wrap_sol_into_escrow<'info>(
+    player: &AccountInfo<'info>,
+    escrow: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    lamports: u64,
+) -> Result<()> {
+    anchor_lang::system_program::transfer(
+        CpiContext::new(
+            system_program.clone(),
+            anchor_lang::system_program::Transfer {
+                from: player.clone(),
+                to: escrow.clone(),
+            },
+        ),
+        lamports,
+    )?;
+    token_interface::sync_native(CpiContext::new(
+        token_program.clone(),
+        SyncNative { account: escrow.clone() },
+    ))
+}
+
+/// Проверяет, что минт можно принимать в ставки и залог. Минты Token-2022 допускаются только
+/// с расширениями из `SUPPORTED_MINT_EXTENSIONS`: иначе на эскроу может прийти не та сумма, что записана в ставке.
+pub fn verify_supported_mint(mint: &AccountInfo) -> Result<()> {
+    if *mint.owner != spl_token_2022::ID {
+        return Ok(());
+    }
+    let mint_data = mint.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+    if mint_state.get_extension_types()?.iter().any(|extension| !SUPPORTED_MINT_EXTENSIONS.contains(extension)) {
+        return err!(TwentyOneError::UnsupportedMintExtension);
+    }
+    Ok(())
+}
+
+/// Выбирает программу перевода по владельцу минта. Token-2022 передается в инструкцию необязательным аккаунтом.
+pub fn token_program_for_mint<'a, 'info>(
+    mint: &AccountInfo<'info>,
+    token_program: &'a AccountInfo<'info>,
+    token_2022_program: Option<&'a AccountInfo<'info>>,
+) -> Result<&'a AccountInfo<'info>> {
+    if *mint.owner == spl_token_2022::ID {
+        token_2022_program.ok_or_else(|| error!(TwentyOneError::Token2022ProgramRequired))
+    } else {
+        Ok(token_program)
+    }
+}
+
+/// Перевод через `transfer_checked` программы минта (Token или Token-2022). Знаки после запятой читаются из минта,
+/// соответствие минта счетам проверяет сама программа. Для переводов от имени PDA передаются `signer_seeds`.
+pub fn transfer_tokens<'info>(
+    token_program: &AccountInfo<'info>,
+    from: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let decimals = Mint::try_deserialize(&mut &mint.try_borrow_data()?[..])?.decimals;
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            token_program.clone(),
+            TransferChecked {
+                from: from.clone(),
+                mint: mint.clone(),
+                to: to.clone(),
+                authority: authority.clone(),
+            },
+            signer_seeds,
+        ),
+        amount,
+        decimals,
+    )
+}
+
+/// Доплачивает ренту перед увеличением аккаунта программы до `new_len` байт.
+pub fn top_up_rent_for_resize<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    new_len: usize,
+) -> Result<()> {
+    let required_lamports = Rent::get()?.minimum_balance(new_len);
+    let missing_lamports = required_lamports.saturating_sub(account.lamports());
+    if missing_lamports > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                anchor_lang::system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            missing_lamports,
+        )?;
+    }
+    Ok(())
+}
+
 // Проверка, является ли игрок тем, кто сидит на указанном месте
 pub fn verify_player_at_seat(
-    game_session: &GameSession,
+    player_seat: &PlayerSeat,
     player_account: &AccountInfo,
     seat_index: u8,
 ) -> Result<()> {
-    let seat_idx = seat_index as usize;
-    if seat_idx >= game_session.player_seats.len() {
+    if seat_index >= MAX_PLAYERS_LIMIT {
         return err!(TwentyOneError::InvalidSeatIndex);
     }
-    match game_session.player_seats[seat_idx].player_pubkey {
+    if player_seat.seat_index != seat_index {
+        return err!(TwentyOneError::SeatAccountMismatch);
+    }
+    match player_seat.player_pubkey() {
         Some(key) if key == player_account.key() => Ok(()),
         _ => err!(TwentyOneError::PlayerNotAtSeatIndex),
     }
@@ -95,92 +441,173 @@ pub fn verify_player_at_seat(
 // Проверка, является ли сейчас ход этого игрока на этом месте и для этой руки
 pub fn verify_player_turn_and_hand(
     game_session: &GameSession,
+    player_seat: &PlayerSeat,
+    player_account: &AccountInfo,
+    seat_index: u8,
+    hand_index: u8,
+) -> Result<()> {
+    verify_player_turn(game_session, player_seat, player_account, seat_index, hand_index)?;
+
+    if player_seat.hands()[hand_index as usize].status() != HandStatus::Playing {
+        return err!(TwentyOneError::HandActionOnFinalizedHand);
+    }
+
+    Ok(())
+}
+
+// Проверка хода без требования, чтобы рука была в игре:
+// удвоенная рука Spanish 21 сохраняет ход до решения о спасении.
+pub fn verify_player_turn(
+    game_session: &GameSession,
+    player_seat: &PlayerSeat,
     player_account: &AccountInfo,
     seat_index: u8,
     hand_index: u8,
 ) -> Result<()> {
-    if game_session.game_state != GameState::PlayerTurns { return err!(TwentyOneError::NotPlayerTurnsState); }
-    match game_session.current_turn_seat_index {
+    if game_session.game_state() != GameState::PlayerTurns { return err!(TwentyOneError::NotPlayerTurnsState); }
+    match game_session.current_turn_seat_index() {
         Some(current_seat_idx) if current_seat_idx == seat_index => {},
         _ => return err!(TwentyOneError::WrongSeatForTurn),
     }
     
-    if Some(hand_index) != game_session.current_turn_hand_index { return err!(TwentyOneError::WrongHandForTurn); }
-    verify_player_at_seat(game_session, player_account, seat_index)?;
+    if Some(hand_index) != game_session.current_turn_hand_index() { return err!(TwentyOneError::WrongHandForTurn); }
+    if game_session.pending_draw().is_some() { return err!(TwentyOneError::CardRevealPending); }
+    verify_player_at_seat(player_seat, player_account, seat_index)?;
 
-    let hand = game_session.player_seats[seat_index as usize].hands.get(hand_index as usize)
-        .ok_or_else(|| error!(TwentyOneError::InvalidHandIndex))?;
-
-    if hand.status != HandStatus::Playing {
-        return err!(TwentyOneError::HandActionOnFinalizedHand);
+    if player_seat.hands().get(hand_index as usize).is_none() {
+        return err!(TwentyOneError::InvalidHandIndex);
     }
 
     Ok(())
 }
 
+// Проверка, что аккаунт места — это место текущего хода за этим столом
+pub fn verify_current_turn_seat(
+    game_session: &GameSession,
+    game_session_key: &Pubkey,
+    player_seat: &PlayerSeat,
+) -> Result<()> {
+    if player_seat.game_session != *game_session_key
+        || Some(player_seat.seat_index) != game_session.current_turn_seat_index()
+    {
+        return err!(TwentyOneError::SeatAccountMismatch);
+    }
+    Ok(())
+}
+
+/// Загружает аккаунты мест стола из `remaining_accounts`.
+/// Аккаунты должны идти первыми, по возрастанию номера места, и покрывать ровно места из `seat_mask`.
+pub fn load_seat_accounts<'info>(
+    accounts: &'info [AccountInfo<'info>],
+    game_session_key: &Pubkey,
+    seat_mask: u8,
+) -> Result<Vec<AccountLoader<'info, PlayerSeat>>> {
+    let expected_seats: Vec<u8> = (0..MAX_PLAYERS_LIMIT).filter(|idx| seat_mask & (1 << idx) != 0).collect();
+    if accounts.len() < expected_seats.len() {
+        return err!(TwentyOneError::SeatAccountsIncomplete);
+    }
+
+    let mut seats = Vec::with_capacity(expected_seats.len());
+    for (account, seat_index) in accounts.iter().zip(expected_seats) {
+        let loader = AccountLoader::<PlayerSeat>::try_from(account)?;
+        {
+            let seat = loader.load()?;
+            if seat.game_session != *game_session_key || seat.seat_index != seat_index {
+                return err!(TwentyOneError::SeatAccountsIncomplete);
+            }
+        }
+        seats.push(loader);
+    }
+    Ok(seats)
+}
+
 // Определение следующего игрока/руки или переход к дилеру
-pub fn determine_next_player_or_transition_to_dealer(game_session: &mut Account<GameSession>, turn_start_timestamp: i64) -> Result<()> {
-    let mut found_next_turn = false;
-
-    if let Some(current_seat_idx_u8) = game_session.current_turn_seat_index {
-        
-        let current_seat_idx = current_seat_idx_u8 as usize;
-
-        // Ищем следующую активную руку у текущего игрока
-        if let Some(next_hand_idx) = game_session.player_seats[current_seat_idx].get_first_active_hand_index() {
-             game_session.current_turn_hand_index = Some(next_hand_idx as u8);
-             game_session.current_turn_start_timestamp = Some(turn_start_timestamp);
-             found_next_turn = true;
-        }
-
-        // Если у текущего игрока больше нет рук для игры, ищем следующего игрока
-        if !found_next_turn {
-            for i in 1..MAX_PLAYERS_LIMIT {
-                let next_potential_seat_idx = (current_seat_idx + i as usize) % (MAX_PLAYERS_LIMIT as usize);
-                
-                if game_session.player_seats[next_potential_seat_idx].is_active_in_round {
-                    if let Some(next_hand_to_play_idx) = game_session.player_seats[next_potential_seat_idx].get_first_active_hand_index() {
-                        game_session.current_turn_seat_index = Some(next_potential_seat_idx as u8);
-                        game_session.current_turn_hand_index = Some(next_hand_to_play_idx as u8);
-                        game_session.current_turn_start_timestamp = Some(turn_start_timestamp);
-                        found_next_turn = true;
-                        break; 
-                    }
+pub fn determine_next_player_or_transition_to_dealer(
+    game_session: &mut GameSession,
+    player_seat: &PlayerSeat,
+    turn_start_timestamp: i64,
+) -> Result<()> {
+    let next_turn = match game_session.current_turn_seat_index() {
+        Some(current_seat_idx) => {
+            // Ищем следующую активную руку у текущего игрока
+            match player_seat.get_first_active_hand_index() {
+                Some(next_hand_idx) => Some((current_seat_idx, next_hand_idx as u8)),
+                // Иначе место уходит из очереди, и ход переходит к первой неразыгранной руке следующего места.
+                None => {
+                    game_session.clear_seat_to_act(current_seat_idx);
+                    game_session.first_seat_to_act()
+                        .map(|next_seat_idx| (next_seat_idx, game_session.first_hand_to_act(next_seat_idx)))
                 }
             }
         }
-    } else {
-        // Если не нашли никого, переходим к ходу дилера
-        game_session.game_state = GameState::DealerTurn;
-        game_session.current_turn_seat_index = None;
-        game_session.current_turn_hand_index = None;
-        game_session.current_turn_start_timestamp = None;
-    }
+        None => None,
+    };
 
-    if !found_next_turn {
-        // Если не нашли никого, переходим к ходу дилера
-        game_session.game_state = GameState::DealerTurn;
-        game_session.current_turn_seat_index = None;
-        game_session.current_turn_hand_index = None;
-        game_session.current_turn_start_timestamp = None;
+    match next_turn {
+        Some((seat_index, hand_index)) => game_session.set_current_turn(seat_index, hand_index, turn_start_timestamp),
+        None => {
+            // Если не нашли никого, переходим к ходу дилера
+            game_session.set_game_state(GameState::DealerTurn);
+            game_session.clear_current_turn();
+        }
     }
     Ok(())
 }
 
-/// Рассчитывает ожидаемый возврат средств в USD для одной руки.
+/// Бонус Spanish 21 за 21 очко, если он положен: (числитель, знаменатель) профита.
+/// Бонусы платятся только за руки без удвоения.
+fn spanish_21_bonus(hand: &Hand) -> Option<(u64, u64)> {
+    if hand.bet_multiplier_x100 != 100 {
+        return None;
+    }
+    let cards = hand.cards();
+    match cards.len() {
+        3 => {
+            let mut ranks = cards.iter().map(|card| card.rank).collect::<Vec<_>>();
+            ranks.sort_unstable_by_key(|rank| *rank as u8);
+            if ranks != [Rank::Six, Rank::Seven, Rank::Eight] && ranks != [Rank::Seven; 3] {
+                return None;
+            }
+            Some(if cards.iter().all(|card| card.suit == Suit::Spades) {
+                SPANISH_21_SPADED_TRIPLE_BONUS
+            } else if cards.iter().all(|card| card.suit == cards[0].suit) {
+                SPANISH_21_SUITED_TRIPLE_BONUS
+            } else {
+                SPANISH_21_MIXED_TRIPLE_BONUS
+            })
+        }
+        5 => Some(SPANISH_21_FIVE_CARD_BONUS),
+        6 => Some(SPANISH_21_SIX_CARD_BONUS),
+        count if count >= 7 => Some(SPANISH_21_SEVEN_CARD_BONUS),
+        _ => None,
+    }
+}
+
+/// Рассчитывает ожидаемый возврат средств в USD для одной руки по правилам разновидности стола.
+/// `free_stake_usd` — часть `effective_bet_usd`, оплаченная заведением (Free Bet): она участвует в выигрыше,
+/// но не возвращается игроку. `original_stake_usd` — первоначальная ставка бокса, которую рука теряет
+/// против блэкджека дилера (0 для руки, отделенной сплитом).
+#[allow(clippy::too_many_arguments)] // Параметры руки и итог дилера передаются по отдельности, как их читает расчет раунда
 pub fn calculate_expected_usd_return(
     hand: &Hand,
     effective_bet_usd: u128,
+    original_stake_usd: u128,
+    free_stake_usd: u128,
     dealer_final_score: u8,
     dealer_is_busted: bool,
     dealer_has_blackjack: bool,
+    variant: GameVariant,
 ) -> Result<(u128, HandOutcome)> {
     let player_final_score = hand.calculate_score().0;
+    let is_spanish_21 = variant == GameVariant::Spanish21;
 
-    let result = match hand.status {
+    let result = match hand.status() {
         HandStatus::Blackjack => {
-            if dealer_has_blackjack {
+            // В Spanish 21 и Double Exposure блэкджек игрока выигрывает и у блэкджека дилера.
+            if dealer_has_blackjack && !is_spanish_21 && !variant.dealer_wins_ties() {
                 (effective_bet_usd, HandOutcome::BlackjackPush) // Пуш, возврат ставки
+            } else if variant.blackjack_pays_even_money() {
+                (effective_bet_usd.checked_mul(2).ok_or(TwentyOneError::ArithmeticOverflow)?, HandOutcome::BlackjackWin)
             } else {
                 // Выигрыш Блэкджек. Возврат ставки + профит.
                 let profit = (effective_bet_usd * BLACKJACK_PAYOUT_PROFIT_NUMERATOR as u128)
@@ -190,11 +617,31 @@ pub fn calculate_expected_usd_return(
             }
         },
         HandStatus::Busted => (0, HandOutcome::Loss), // Проигрыш, возврат 0
+        // Поздний отказ и спасение удвоения не действуют против блэкджека дилера.
+        HandStatus::Surrendered | HandStatus::Rescued if dealer_has_blackjack => (0, HandOutcome::Loss),
+        HandStatus::Surrendered => (effective_bet_usd / 2, HandOutcome::Surrender),
+        // Множитель удвоенной руки 2.0x: игрок получает назад удвоение, равное первоначальной ставке.
+        HandStatus::Rescued => (effective_bet_usd / 2, HandOutcome::Rescue),
+        HandStatus::Stood | HandStatus::DoubledAndStood if is_spanish_21 && player_final_score == 21 && !dealer_has_blackjack => {
+            // В Spanish 21 21 игрока выигрывает всегда, а без удвоения может принести бонус.
+            match spanish_21_bonus(hand) {
+                Some((numerator, denominator)) => {
+                    let profit = (effective_bet_usd * numerator as u128)
+                        .checked_div(denominator as u128)
+                        .ok_or(TwentyOneError::ArithmeticOverflow)?;
+                    (effective_bet_usd.checked_add(profit).ok_or(TwentyOneError::ArithmeticOverflow)?, HandOutcome::BonusWin)
+                }
+                None => (effective_bet_usd.checked_mul(2).ok_or(TwentyOneError::ArithmeticOverflow)?, HandOutcome::Win),
+            }
+        },
+        HandStatus::Stood | HandStatus::DoubledAndStood if variant.dealer_22_pushes() && dealer_final_score == 22 => {
+            (effective_bet_usd, HandOutcome::Push)
+        },
         HandStatus::Stood | HandStatus::DoubledAndStood => {
             if dealer_is_busted || player_final_score > dealer_final_score {
                 // Обычный выигрыш. Возврат ставки + выигрыш (равный ставке). Итого ставка * 2.
                 (effective_bet_usd.checked_mul(2).ok_or(TwentyOneError::ArithmeticOverflow)?, HandOutcome::Win)
-            } else if player_final_score == dealer_final_score {
+            } else if player_final_score == dealer_final_score && !variant.dealer_wins_ties() {
                 (effective_bet_usd, HandOutcome::Push) // Пуш, возврат ставки
             } else {
                 (0, HandOutcome::Loss) // Проигрыш, возврат 0
@@ -202,5 +649,509 @@ pub fn calculate_expected_usd_return(
         },
         _ => return err!(TwentyOneError::HandActionOnFinalizedHand), // Рука не в финальном статусе
     };
-    Ok(result)
+    let (expected_return, outcome) = match result {
+        // Дилер не заглядывает под закрытую карту, поэтому против его блэкджека проигрывается только
+        // первоначальная ставка бокса: удвоение и ставка на отделенную сплитом руку возвращаются.
+        (_, HandOutcome::Loss) if dealer_has_blackjack && !variant.exposes_dealer_cards() => {
+            let refund = effective_bet_usd.saturating_sub(original_stake_usd);
+            (refund, if original_stake_usd == 0 { HandOutcome::Push } else { HandOutcome::Loss })
+        },
+        result => result,
+    };
+    Ok((expected_return.saturating_sub(free_stake_usd), outcome))
+}
+
+/// Делит ожидаемый возврат по ставке для стола с выигрышами в USDC: собственная ставка игрока возвращается
+/// в токене ставки, остальное — выигрыш в USD. При частичном возврате (отказ, спасение) возвращается
+/// та же доля токенов. Возвращает (возврат в UI-единицах токена, выигрыш в USD).
+pub fn split_usdc_settlement(expected_return_usd: u128, stake_usd: u128, stake_amount: u64) -> Result<(u64, u128)> {
+    let returned_stake_usd = expected_return_usd.min(stake_usd);
+    let returned_stake_amount = (stake_amount as u128)
+        .checked_mul(returned_stake_usd).ok_or(TwentyOneError::ArithmeticOverflow)?
+        .checked_div(stake_usd).unwrap_or_default();
+    Ok((
+        u64::try_from(returned_stake_amount).map_err(|_| error!(TwentyOneError::ArithmeticOverflow))?,
+        expected_return_usd - returned_stake_usd,
+    ))
+}
+
+/// Сверяет расчет ставки за столом с выигрышами в USDC: возврат ставки в токене должен совпасть точно,
+/// стоимость выигрыша в USDC сверяется с курсом Pyth на момент расчета.
+pub fn verify_usdc_settlement(
+    usdc_price_feed: Option<&AccountInfo>,
+    expected_return_usd: u128,
+    stake_usd: u128,
+    stake_amount: u64,
+    payout_amount_ui: u64,
+    usdc_payout_amount: u64,
+) -> Result<()> {
+    let (returned_stake, winnings_usd) = split_usdc_settlement(expected_return_usd, stake_usd, stake_amount)?;
+    if payout_amount_ui != returned_stake {
+        return err!(TwentyOneError::PayoutCalculationMismatch);
+    }
+    if winnings_usd == 0 {
+        if usdc_payout_amount != 0 {
+            return err!(TwentyOneError::PayoutCalculationMismatch);
+        }
+        return Ok(());
+    }
+    let usdc_price_feed = usdc_price_feed.ok_or(TwentyOneError::UsdcSettlementAccountsRequired)?;
+    let winnings_usd = u64::try_from(winnings_usd).map_err(|_| error!(TwentyOneError::ArithmeticOverflow))?;
+    verify_bet_usd_value(usdc_price_feed, usdc_payout_amount, winnings_usd)
+}
+
+/// Красная ли масть (для цветной пары в Perfect Pairs).
+fn is_red(suit: Suit) -> bool {
+    matches!(suit, Suit::Hearts | Suit::Diamonds)
+}
+
+/// Комбинация 21+3: две карты игрока и открытая карта дилера как трехкарточная покерная рука.
+pub fn evaluate_twenty_one_plus_three(player_cards: [Card; 2], dealer_up_card: Card) -> SideBetOutcome {
+    let cards = [player_cards[0], player_cards[1], dealer_up_card];
+    let is_flush = cards.iter().all(|card| card.suit == cards[0].suit);
+    let is_trips = cards.iter().all(|card| card.rank == cards[0].rank);
+
+    // Порядок `Rank` — от двойки до туза, поэтому туз старший; A-2-3 проверяется отдельно.
+    let mut ranks = cards.map(|card| card.rank as u8);
+    ranks.sort_unstable();
+    let is_straight = (ranks[1] == ranks[0] + 1 && ranks[2] == ranks[1] + 1)
+        || ranks == [Rank::Two as u8, Rank::Three as u8, Rank::Ace as u8];
+
+    match (is_trips, is_straight, is_flush) {
+        (true, _, true) => SideBetOutcome::SuitedTrips,
+        (true, _, false) => SideBetOutcome::ThreeOfAKind,
+        (false, true, true) => SideBetOutcome::StraightFlush,
+        (false, true, false) => SideBetOutcome::Straight,
+        (false, false, true) => SideBetOutcome::Flush,
+        (false, false, false) => SideBetOutcome::Loss,
+    }
+}
+
+/// Комбинация Perfect Pairs по двум первым картам игрока.
+pub fn evaluate_perfect_pairs(first: Card, second: Card) -> SideBetOutcome {
+    if first.rank != second.rank {
+        SideBetOutcome::Loss
+    } else if first.suit == second.suit {
+        SideBetOutcome::PerfectPair
+    } else if is_red(first.suit) == is_red(second.suit) {
+        SideBetOutcome::ColoredPair
+    } else {
+        SideBetOutcome::MixedPair
+    }
+}
+
+/// Комбинация побочной ставки по начальной руке бокса и открытой карте дилера.
+pub fn evaluate_side_bet(kind: SideBetKind, hand: &Hand, dealer_up_card: Card) -> Result<SideBetOutcome> {
+    let (first, second) = match (hand.card(0), hand.card(1)) {
+        (Some(first), Some(second)) => (first, second),
+        _ => return err!(TwentyOneError::CardRevealCountMismatch),
+    };
+    Ok(match kind {
+        SideBetKind::TwentyOnePlusThree => evaluate_twenty_one_plus_three([first, second], dealer_up_card),
+        SideBetKind::PerfectPairs => evaluate_perfect_pairs(first, second),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spl_token_2022::extension::ExtensionType;
+
+    const VRF_VALUE: [u8; 32] = [7u8; 32];
+
+    /// Данные аккаунта `RandomnessAccountData` с заданными слотами и значением.
+    fn mock_vrf_data(seed_slot: u64, reveal_slot: u64) -> Vec<u8> {
+        let mut data = vec![0u8; VRF_VALUE_OFFSET + 32 + 64];
+        data[VRF_SEED_SLOT_OFFSET..VRF_SEED_SLOT_OFFSET + 8].copy_from_slice(&seed_slot.to_le_bytes());
+        data[VRF_REVEAL_SLOT_OFFSET..VRF_REVEAL_SLOT_OFFSET + 8].copy_from_slice(&reveal_slot.to_le_bytes());
+        data[VRF_VALUE_OFFSET..VRF_VALUE_OFFSET + 32].copy_from_slice(&VRF_VALUE);
+        data
+    }
+
+    fn read_vrf(key: &Pubkey, owner: &Pubkey, data: &mut [u8], expected_account: Pubkey, min_seed_slot: u64) -> Result<[u8; 32]> {
+        let mut lamports = 1_000_000u64;
+        let account = AccountInfo::new(key, false, false, &mut lamports, data, owner, false, 0);
+        VrfAccountSource { vrf_account: &account, expected_account, min_seed_slot }.entropy()
+    }
+
+    #[test]
+    fn vrf_source_reads_revealed_value() {
+        let key = Pubkey::new_unique();
+        let mut data = mock_vrf_data(100, 101);
+        let value = read_vrf(&key, &SWITCHBOARD_ON_DEMAND_PROGRAM_ID, &mut data, key, 100).unwrap();
+        assert_eq!(value, VRF_VALUE);
+    }
+
+    #[test]
+    fn vrf_source_rejects_unrevealed_or_stale_value() {
+        let key = Pubkey::new_unique();
+        let mut unrevealed = mock_vrf_data(100, 0);
+        assert_eq!(
+            read_vrf(&key, &SWITCHBOARD_ON_DEMAND_PROGRAM_ID, &mut unrevealed, key, 100).unwrap_err(),
+            TwentyOneError::RandomnessNotFresh.into()
+        );
+        let mut stale = mock_vrf_data(99, 101);
+        assert_eq!(
+            read_vrf(&key, &SWITCHBOARD_ON_DEMAND_PROGRAM_ID, &mut stale, key, 100).unwrap_err(),
+            TwentyOneError::RandomnessNotFresh.into()
+        );
+    }
+
+    #[test]
+    fn vrf_source_rejects_foreign_account() {
+        let key = Pubkey::new_unique();
+        let mut data = mock_vrf_data(100, 101);
+        assert_eq!(
+            read_vrf(&key, &SWITCHBOARD_ON_DEMAND_PROGRAM_ID, &mut data, Pubkey::new_unique(), 100).unwrap_err(),
+            TwentyOneError::RandomnessAccountMismatch.into()
+        );
+        assert_eq!(
+            read_vrf(&key, &Pubkey::new_unique(), &mut data, key, 100).unwrap_err(),
+            TwentyOneError::InvalidRandomnessAccount.into()
+        );
+        let mut truncated = mock_vrf_data(100, 101);
+        assert_eq!(
+            read_vrf(&key, &SWITCHBOARD_ON_DEMAND_PROGRAM_ID, &mut truncated[..VRF_VALUE_OFFSET], key, 100).unwrap_err(),
+            TwentyOneError::InvalidRandomnessAccount.into()
+        );
+    }
+
+    fn card(rank: Rank, suit: Suit) -> Card {
+        Card { rank, suit }
+    }
+
+    fn settled_hand(cards: &[Card], status: HandStatus, bet_multiplier_x100: u16) -> Hand {
+        let mut hand = Hand::new(Pubkey::default(), 100, 100);
+        for card in cards {
+            hand.add_card(*card).unwrap();
+        }
+        hand.set_status(status);
+        hand.bet_multiplier_x100 = bet_multiplier_x100;
+        hand
+    }
+
+    #[test]
+    fn dealer_blackjack_takes_only_original_stake() {
+        use Rank::*;
+        use Suit::*;
+        let classic = GameVariant::Classic;
+
+        // Удвоение против закрытого блэкджека возвращается, даже если рука перебрала.
+        let doubled = settled_hand(&[card(Five, Hearts), card(Six, Clubs), card(Nine, Spades)], HandStatus::DoubledAndStood, 200);
+        assert_eq!(calculate_expected_usd_return(&doubled, 200, 100, 0, 21, false, true, classic).unwrap(), (100, HandOutcome::Loss));
+        let doubled_bust = settled_hand(&[card(Five, Hearts), card(Six, Clubs), card(King, Spades), card(Two, Clubs)], HandStatus::Busted, 200);
+        assert_eq!(calculate_expected_usd_return(&doubled_bust, 200, 100, 0, 21, false, true, classic).unwrap(), (100, HandOutcome::Loss));
+
+        // Отделенная сплитом рука возвращается целиком, первая рука бокса проигрывает ставку.
+        let split_off = settled_hand(&[card(Eight, Hearts), card(King, Clubs)], HandStatus::Stood, 100);
+        assert_eq!(calculate_expected_usd_return(&split_off, 100, 0, 0, 21, false, true, classic).unwrap(), (100, HandOutcome::Push));
+        assert_eq!(calculate_expected_usd_return(&split_off, 100, 100, 0, 21, false, true, classic).unwrap(), (0, HandOutcome::Loss));
+
+        // Бесплатное удвоение Free Bet не возвращается: его оплатило заведение.
+        assert_eq!(calculate_expected_usd_return(&doubled, 200, 100, 100, 21, false, true, GameVariant::FreeBet).unwrap(), (0, HandOutcome::Loss));
+    }
+
+    #[test]
+    fn twenty_one_plus_three_ranks_combinations() {
+        use Rank::*;
+        use Suit::*;
+        let cases = [
+            ([card(Seven, Hearts), card(Seven, Hearts)], card(Seven, Hearts), SideBetOutcome::SuitedTrips),
+            ([card(Seven, Hearts), card(Seven, Clubs)], card(Seven, Spades), SideBetOutcome::ThreeOfAKind),
+            ([card(Nine, Clubs), card(Jack, Clubs)], card(Ten, Clubs), SideBetOutcome::StraightFlush),
+            ([card(Ace, Spades), card(Two, Hearts)], card(Three, Clubs), SideBetOutcome::Straight),
+            ([card(Queen, Spades), card(Ace, Hearts)], card(King, Clubs), SideBetOutcome::Straight),
+            ([card(Two, Diamonds), card(King, Diamonds)], card(Six, Diamonds), SideBetOutcome::Flush),
+            ([card(King, Spades), card(Ace, Hearts)], card(Two, Clubs), SideBetOutcome::Loss),
+        ];
+        for (player_cards, dealer_up_card, expected) in cases {
+            assert_eq!(evaluate_twenty_one_plus_three(player_cards, dealer_up_card), expected, "{:?} + {}", player_cards, dealer_up_card);
+        }
+    }
+
+    #[test]
+    fn perfect_pairs_ranks_combinations() {
+        use Rank::*;
+        use Suit::*;
+        assert_eq!(evaluate_perfect_pairs(card(Eight, Spades), card(Eight, Spades)), SideBetOutcome::PerfectPair);
+        assert_eq!(evaluate_perfect_pairs(card(Eight, Hearts), card(Eight, Diamonds)), SideBetOutcome::ColoredPair);
+        assert_eq!(evaluate_perfect_pairs(card(Eight, Hearts), card(Eight, Clubs)), SideBetOutcome::MixedPair);
+        assert_eq!(evaluate_perfect_pairs(card(Eight, Hearts), card(Nine, Hearts)), SideBetOutcome::Loss);
+    }
+
+    #[test]
+    fn verify_shoe_matches_on_chain_shuffle() {
+        use Rank::*;
+        use Suit::*;
+        let rules = ShoeRules::default();
+        let seed = generate_shuffle_seed_hash(&[11u8; 32], &[22u8; 32]);
+
+        // Так контракт пересобирает шуз в `commit_shoe` и публикует его `hash_deck_order` в `ShoeRevealed`;
+        // аудитор повторяет это через `verify_shoe`.
+        let mut on_chain = create_standard_shoe(rules.num_decks, rules.composition);
+        fisher_yates_shuffle(&mut on_chain, seed);
+        let audited = verify_shoe(seed, rules);
+        assert_eq!(audited, on_chain);
+        assert_eq!(hash_deck_order(&audited), hash_deck_order(&on_chain));
+        let packed: Vec<u8> = on_chain.iter().map(Card::to_packed).collect();
+        assert_eq!(hash_deck_order(&audited), <[u8; 32]>::from(Sha256::digest(&packed)));
+
+        // Порядок и хеш закреплены: смена генератора или алгоритма тасования сломает проверку уже сыгранных шузов.
+        assert_eq!(
+            audited[..6],
+            [card(King, Spades), card(Queen, Hearts), card(Ten, Spades), card(Three, Diamonds), card(Queen, Clubs), card(Four, Spades)],
+        );
+        assert_eq!(hash_deck_order(&audited), [
+            199, 47, 192, 167, 240, 227, 66, 213, 0, 3, 203, 32, 167, 201, 241, 38,
+            203, 102, 147, 129, 220, 194, 49, 244, 42, 174, 123, 101, 131, 190, 134, 57,
+        ]);
+    }
+
+    /// Уровни дерева Меркла шуза снизу вверх, с теми же листьями, что и в `compute_deck_merkle_root`.
+    fn merkle_levels(deck: &[Card], secret: &[u8; 32]) -> Vec<Vec<[u8; 32]>> {
+        let mut levels = vec![(0..deck.len().next_power_of_two())
+            .map(|index| match deck.get(index) {
+                Some(card) => card_leaf_hash(index as u16, &card_salt(secret, index as u16), card),
+                None => [0u8; 32],
+            })
+            .collect::<Vec<_>>()];
+        while levels.last().unwrap().len() > 1 {
+            let next = levels.last().unwrap().chunks(2).map(|pair| merkle_node_hash(&pair[0], &pair[1])).collect();
+            levels.push(next);
+        }
+        levels
+    }
+
+    /// Доказательство для листьев `start..start + len` в порядке `CardRangeReveal::proof`.
+    fn range_proof(levels: &[Vec<[u8; 32]>], start: usize, len: usize) -> Vec<[u8; 32]> {
+        let (mut first, mut last) = (start, start + len - 1);
+        let mut proof = Vec::new();
+        for level in &levels[..levels.len() - 1] {
+            if first % 2 == 1 { proof.push(level[first - 1]); }
+            if last % 2 == 0 { proof.push(level[last + 1]); }
+            first /= 2;
+            last /= 2;
+        }
+        proof
+    }
+
+    #[test]
+    fn card_range_proof_verifies_across_levels() {
+        let rules = ShoeRules::default();
+        let secret = [9u8; 32];
+        let deck = verify_shoe([5u8; 32], rules);
+        let depth = rules.merkle_depth();
+        let root = compute_deck_merkle_root(&deck, &secret);
+        let levels = merkle_levels(&deck, &secret);
+        assert_eq!(levels.len() as u32, depth + 1);
+        assert_eq!(levels[depth as usize][0], root);
+
+        // Диапазон 13..19 начинается правым ребенком и заканчивается левым: соседи нужны с обеих сторон.
+        let proof = range_proof(&levels, 13, 6);
+        assert!(verify_card_range(&root, depth, 13, &levels[0][13..19], &proof));
+        assert!(verify_card_range(&root, depth, 7, &levels[0][7..8], &range_proof(&levels, 7, 1)));
+
+        // Подмененный лист и сдвинутый диапазон отклоняются.
+        let mut tampered = levels[0][13..19].to_vec();
+        let other_card = deck.iter().find(|card| **card != deck[15]).unwrap();
+        tampered[2] = card_leaf_hash(15, &card_salt(&secret, 15), other_card);
+        assert!(!verify_card_range(&root, depth, 13, &tampered, &proof));
+        assert!(!verify_card_range(&root, depth, 14, &levels[0][13..19], &proof));
+
+        // Недостающий сосед и лишний узел доказательства отклоняются.
+        assert!(!verify_card_range(&root, depth, 13, &levels[0][13..19], &proof[..proof.len() - 1]));
+        assert!(!verify_card_range(&root, depth, 13, &levels[0][13..19], &proof[1..]));
+        let mut extended = proof.clone();
+        extended.push([0u8; 32]);
+        assert!(!verify_card_range(&root, depth, 13, &levels[0][13..19], &extended));
+
+        // Диапазон за пределами дерева и пустой диапазон отклоняются.
+        let width = 1usize << depth;
+        assert!(!verify_card_range(&root, depth, (width - 2) as u16, &levels[0][width - 3..], &[]));
+        assert!(!verify_card_range(&root, depth, 13, &[], &proof));
+    }
+
+    #[test]
+    fn card_range_proof_covers_padding_leaves() {
+        // 6 колод — 312 карт, дерево дополняется нулевыми листьями до 512.
+        let rules = ShoeRules { num_decks: 6, composition: DeckComposition::Standard };
+        let secret = [4u8; 32];
+        let deck = verify_shoe([6u8; 32], rules);
+        let depth = rules.merkle_depth();
+        let total = rules.total_cards() as usize;
+        assert_eq!(1usize << depth, 512);
+        let root = compute_deck_merkle_root(&deck, &secret);
+        let levels = merkle_levels(&deck, &secret);
+        assert!(levels[0][total..].iter().all(|leaf| *leaf == [0u8; 32]));
+
+        // Последние карты шуза доказываются через соседа над нулевыми листьями 312..320.
+        let zero_subtree = (0..3).fold([0u8; 32], |node, _| merkle_node_hash(&node, &node));
+        let proof = range_proof(&levels, total - 3, 3);
+        assert_eq!(levels[3][total / 8], zero_subtree);
+        assert!(proof.contains(&zero_subtree));
+        assert!(verify_card_range(&root, depth, (total - 3) as u16, &levels[0][total - 3..total], &proof));
+
+        // Карта на месте дополнения не входит в дерево.
+        let fake = card_leaf_hash(total as u16, &card_salt(&secret, total as u16), &deck[0]);
+        assert!(!verify_card_range(&root, depth, total as u16, &[fake], &range_proof(&levels, total, 1)));
+    }
+
+    #[test]
+    fn spanish_shoe_has_no_tens() {
+        let rules = ShoeRules { num_decks: 6, composition: DeckComposition::Spanish };
+        let shoe = verify_shoe([3u8; 32], rules);
+        assert_eq!(shoe.len(), rules.total_cards() as usize);
+        assert_eq!(shoe.len(), 6 * 48);
+        assert!(shoe.iter().all(|card| card.rank != Rank::Ten));
+        assert_eq!(shoe.iter().filter(|card| card.rank == Rank::King).count(), 6 * 4);
+    }
+
+    #[test]
+    fn spanish_21_settlement_rules() {
+        use Rank::*;
+        use Suit::*;
+        let spanish = GameVariant::Spanish21;
+        let settle = |hand: &Hand, bet: u128, dealer_score: u8, dealer_blackjack: bool| {
+            calculate_expected_usd_return(hand, bet, bet * 100 / hand.bet_multiplier_x100 as u128, 0, dealer_score, false, dealer_blackjack, spanish).unwrap()
+        };
+
+        // 21 игрока выигрывает у 21 дилера; в классике это пуш.
+        let twenty_one = settled_hand(&[card(King, Hearts), card(Five, Clubs), card(Six, Spades)], HandStatus::Stood, 100);
+        assert_eq!(settle(&twenty_one, 100, 21, false), (200, HandOutcome::Win));
+        assert_eq!(
+            calculate_expected_usd_return(&twenty_one, 100, 100, 0, 21, false, false, GameVariant::Classic).unwrap(),
+            (100, HandOutcome::Push)
+        );
+
+        // Бонусы: 5 карт 3:2, 7-7-7 пик 3:1; после удвоения бонус не платится.
+        let five_cards = settled_hand(
+            &[card(Two, Hearts), card(Three, Clubs), card(Four, Spades), card(Five, Hearts), card(Seven, Clubs)],
+            HandStatus::Stood, 100,
+        );
+        assert_eq!(settle(&five_cards, 100, 20, false), (250, HandOutcome::BonusWin));
+        let spaded_sevens = settled_hand(&[card(Seven, Spades), card(Seven, Spades), card(Seven, Spades)], HandStatus::Stood, 100);
+        assert_eq!(settle(&spaded_sevens, 100, 20, false), (400, HandOutcome::BonusWin));
+        let doubled_678 = settled_hand(&[card(Six, Hearts), card(Seven, Hearts), card(Eight, Hearts)], HandStatus::DoubledAndStood, 200);
+        assert_eq!(settle(&doubled_678, 200, 20, false), (400, HandOutcome::Win));
+
+        // Блэкджек игрока бьет блэкджек дилера.
+        let blackjack = settled_hand(&[card(Ace, Hearts), card(King, Clubs)], HandStatus::Blackjack, 100);
+        assert_eq!(settle(&blackjack, 100, 21, true), (230, HandOutcome::BlackjackWin));
+
+        // Отказ и спасение возвращают половину ставки, но не против блэкджека дилера.
+        let surrendered = settled_hand(&[card(King, Hearts), card(Six, Clubs)], HandStatus::Surrendered, 100);
+        assert_eq!(settle(&surrendered, 100, 20, false), (50, HandOutcome::Surrender));
+        assert_eq!(settle(&surrendered, 100, 21, true), (0, HandOutcome::Loss));
+        let rescued = settled_hand(&[card(Five, Hearts), card(Six, Clubs), card(Two, Spades)], HandStatus::Rescued, 200);
+        assert_eq!(settle(&rescued, 200, 20, false), (100, HandOutcome::Rescue));
+    }
+
+    #[test]
+    fn free_bet_settlement_rules() {
+        use Rank::*;
+        use Suit::*;
+        let free_bet = GameVariant::FreeBet;
+
+        // Бесплатное удвоение: на кону 200, из них 100 заведения; при выигрыше игрок получает 300.
+        let doubled = settled_hand(&[card(Five, Hearts), card(Six, Clubs), card(Nine, Spades)], HandStatus::DoubledAndStood, 200);
+        assert_eq!(calculate_expected_usd_return(&doubled, 200, 100, 100, 18, false, false, free_bet).unwrap(), (300, HandOutcome::Win));
+        assert_eq!(calculate_expected_usd_return(&doubled, 200, 100, 100, 20, false, false, free_bet).unwrap(), (100, HandOutcome::Push));
+        assert_eq!(calculate_expected_usd_return(&doubled, 200, 100, 100, 21, false, false, free_bet).unwrap(), (0, HandOutcome::Loss));
+
+        // 22 у дилера — пуш для рук без перебора, блэкджек игрока выигрывает, перебор игрока проигрывает.
+        assert_eq!(calculate_expected_usd_return(&doubled, 200, 100, 100, 22, true, false, free_bet).unwrap(), (100, HandOutcome::Push));
+        let blackjack = settled_hand(&[card(Ace, Hearts), card(King, Clubs)], HandStatus::Blackjack, 100);
+        assert_eq!(calculate_expected_usd_return(&blackjack, 100, 100, 0, 22, true, false, free_bet).unwrap(), (230, HandOutcome::BlackjackWin));
+        let busted = settled_hand(&[card(King, Hearts), card(Six, Clubs), card(Nine, Spades)], HandStatus::Busted, 100);
+        assert_eq!(calculate_expected_usd_return(&busted, 100, 100, 0, 22, true, false, free_bet).unwrap(), (0, HandOutcome::Loss));
+        assert_eq!(calculate_expected_usd_return(&doubled, 200, 100, 100, 23, true, false, free_bet).unwrap(), (300, HandOutcome::Win));
+    }
+
+    #[test]
+    fn double_exposure_settlement_rules() {
+        use Rank::*;
+        use Suit::*;
+        let double_exposure = GameVariant::DoubleExposure;
+
+        // Ничьи выигрывает дилер, блэкджек платит 1:1 и выигрывает у блэкджека дилера.
+        let twenty = settled_hand(&[card(King, Hearts), card(Queen, Clubs)], HandStatus::Stood, 100);
+        assert_eq!(calculate_expected_usd_return(&twenty, 100, 100, 0, 20, false, false, double_exposure).unwrap(), (0, HandOutcome::Loss));
+        assert_eq!(calculate_expected_usd_return(&twenty, 100, 100, 0, 19, false, false, double_exposure).unwrap(), (200, HandOutcome::Win));
+        let blackjack = settled_hand(&[card(Ace, Hearts), card(King, Clubs)], HandStatus::Blackjack, 100);
+        assert_eq!(calculate_expected_usd_return(&blackjack, 100, 100, 0, 20, false, false, double_exposure).unwrap(), (200, HandOutcome::BlackjackWin));
+        assert_eq!(calculate_expected_usd_return(&blackjack, 100, 100, 0, 21, false, true, double_exposure).unwrap(), (200, HandOutcome::BlackjackWin));
+    }
+
+    #[test]
+    fn usdc_settlement_returns_stake_in_bet_token() {
+        // Ставка 5000 токенов стоимостью 100: выигрыш, блэкджек, отказ, проигрыш.
+        assert_eq!(split_usdc_settlement(200, 100, 5000).unwrap(), (5000, 100));
+        assert_eq!(split_usdc_settlement(230, 100, 5000).unwrap(), (5000, 130));
+        assert_eq!(split_usdc_settlement(50, 100, 5000).unwrap(), (2500, 0));
+        assert_eq!(split_usdc_settlement(0, 100, 5000).unwrap(), (0, 0));
+
+        // Бесплатное удвоение Free Bet: своя ставка 100 из 200 на кону, при выигрыше возврат 300.
+        assert_eq!(split_usdc_settlement(300, 100, 5000).unwrap(), (5000, 200));
+
+        assert!(verify_usdc_settlement(None, 100, 100, 5000, 5000, 0).is_ok());
+        assert!(verify_usdc_settlement(None, 100, 100, 5000, 4999, 0).is_err());
+        assert!(verify_usdc_settlement(None, 200, 100, 5000, 5000, 100).is_err());
+    }
+
+    #[test]
+    fn blackjack_switch_settlement_rules() {
+        use Rank::*;
+        use Suit::*;
+        let switch = GameVariant::BlackjackSwitch;
+
+        // Блэкджек платит 1:1, 22 у дилера — пуш, но блэкджек игрока против 22 выигрывает.
+        let blackjack = settled_hand(&[card(Ace, Hearts), card(King, Clubs)], HandStatus::Blackjack, 100);
+        assert_eq!(calculate_expected_usd_return(&blackjack, 100, 100, 0, 20, false, false, switch).unwrap(), (200, HandOutcome::BlackjackWin));
+        assert_eq!(calculate_expected_usd_return(&blackjack, 100, 100, 0, 22, true, false, switch).unwrap(), (200, HandOutcome::BlackjackWin));
+        assert_eq!(calculate_expected_usd_return(&blackjack, 100, 100, 0, 21, false, true, switch).unwrap(), (100, HandOutcome::BlackjackPush));
+        let eighteen = settled_hand(&[card(King, Hearts), card(Eight, Clubs)], HandStatus::Stood, 100);
+        assert_eq!(calculate_expected_usd_return(&eighteen, 100, 100, 0, 22, true, false, switch).unwrap(), (100, HandOutcome::Push));
+        assert_eq!(calculate_expected_usd_return(&eighteen, 100, 100, 0, 18, false, false, switch).unwrap(), (100, HandOutcome::Push));
+        assert_eq!(calculate_expected_usd_return(&eighteen, 100, 100, 0, 23, true, false, switch).unwrap(), (200, HandOutcome::Win));
+    }
+
+    /// Данные инициализированного минта Token-2022 с заданными расширениями.
+    fn mock_token_2022_mint(extensions: &[ExtensionType]) -> Vec<u8> {
+        use spl_token_2022::extension::{transfer_fee::TransferFeeConfig, metadata_pointer::MetadataPointer, BaseStateWithExtensionsMut, StateWithExtensionsMut};
+        let len = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(extensions).unwrap();
+        let mut data = vec![0u8; len];
+        let mut mint = StateWithExtensionsMut::<spl_token_2022::state::Mint>::unpack_uninitialized(&mut data).unwrap();
+        for extension in extensions {
+            match extension {
+                ExtensionType::TransferFeeConfig => { mint.init_extension::<TransferFeeConfig>(true).unwrap(); }
+                ExtensionType::MetadataPointer => { mint.init_extension::<MetadataPointer>(true).unwrap(); }
+                _ => unreachable!(),
+            }
+        }
+        mint.base = spl_token_2022::state::Mint { decimals: 6, is_initialized: true, ..Default::default() };
+        mint.pack_base();
+        mint.init_account_type().unwrap();
+        data
+    }
+
+    #[test]
+    fn token_2022_mints_with_transfer_fees_are_rejected() {
+        let key = Pubkey::new_unique();
+        let owner = spl_token_2022::ID;
+        let mut lamports = 1_000_000u64;
+
+        let mut plain = mock_token_2022_mint(&[]);
+        let mint = AccountInfo::new(&key, false, false, &mut lamports, &mut plain, &owner, false, 0);
+        assert!(verify_supported_mint(&mint).is_ok());
+
+        let mut lamports = 1_000_000u64;
+        let mut with_metadata = mock_token_2022_mint(&[ExtensionType::MetadataPointer]);
+        let mint = AccountInfo::new(&key, false, false, &mut lamports, &mut with_metadata, &owner, false, 0);
+        assert!(verify_supported_mint(&mint).is_ok());
+
+        let mut lamports = 1_000_000u64;
+        let mut with_fee = mock_token_2022_mint(&[ExtensionType::TransferFeeConfig]);
+        let mint = AccountInfo::new(&key, false, false, &mut lamports, &mut with_fee, &owner, false, 0);
+        assert_eq!(verify_supported_mint(&mint).unwrap_err(), TwentyOneError::UnsupportedMintExtension.into());
+    }
 }
\ No newline at end of file